@@ -0,0 +1,713 @@
+//! Samples of non-uniform distributions driven by the words of the stream.
+
+use std::{f64::consts::PI, fmt, io, str::FromStr, sync::OnceLock};
+
+use crate::{
+    numbers::{self, Endian, TextWriter},
+    RandomStream,
+};
+
+/// Probability distribution to sample.
+///
+/// Parsed from `NAME:PARAMS` with comma-separated parameters, e.g. `normal:0,1` or `poisson:4.5`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Distribution {
+    /// Normal distribution `normal:mu,sigma`.
+    Normal {
+        /// Mean.
+        mu: f64,
+        /// Standard deviation.
+        sigma: f64,
+    },
+    /// Exponential distribution `exp:lambda`.
+    Exp {
+        /// Rate.
+        lambda: f64,
+    },
+    /// Poisson distribution `poisson:lambda`.
+    Poisson(Poisson),
+    /// Binomial distribution `binomial:n,p`.
+    Binomial(Binomial),
+    /// Gamma distribution `gamma:k,theta`.
+    Gamma {
+        /// Shape.
+        k: f64,
+        /// Scale.
+        theta: f64,
+    },
+    /// Beta distribution `beta:alpha,beta`.
+    Beta {
+        /// First shape parameter.
+        alpha: f64,
+        /// Second shape parameter.
+        beta: f64,
+    },
+    /// Log-normal distribution `lognormal:mu,sigma` of the parameters of the underlying normal
+    /// distribution.
+    LogNormal {
+        /// Mean of the logarithm.
+        mu: f64,
+        /// Standard deviation of the logarithm.
+        sigma: f64,
+    },
+    /// Zipf distribution `zipf:n,s` over `1..=n`.
+    Zipf(Zipf),
+}
+
+/// Sample of a [`Distribution`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Sample {
+    /// Sample of a continuous distribution.
+    Float(f64),
+    /// Sample of a discrete distribution.
+    Int(u64),
+}
+
+impl Distribution {
+    /// Returns `true` if the samples are integers.
+    pub const fn is_discrete(&self) -> bool {
+        matches!(self, Self::Poisson(_) | Self::Binomial(_) | Self::Zipf(_))
+    }
+
+    /// Draws a sample.
+    pub fn sample(&self, stream: &mut RandomStream) -> io::Result<Sample> {
+        Ok(match *self {
+            Self::Normal { mu, sigma } => Sample::Float(mu + sigma * standard_normal(stream)?),
+            Self::Exp { lambda } => Sample::Float(standard_exp(stream)? / lambda),
+            Self::Poisson(d) => Sample::Int(d.sample(stream)?),
+            Self::Binomial(d) => Sample::Int(d.sample(stream)?),
+            Self::Gamma { k, theta } => Sample::Float(theta * standard_gamma(k, stream)?),
+            Self::Beta { alpha, beta } => Sample::Float(standard_beta(alpha, beta, stream)?),
+            Self::LogNormal { mu, sigma } => {
+                Sample::Float((mu + sigma * standard_normal(stream)?).exp())
+            }
+            Self::Zipf(d) => Sample::Int(d.sample(stream)?),
+        })
+    }
+}
+
+/// Error returned when parsing an unknown distribution or invalid parameters.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseDistributionError(String);
+
+impl fmt::Display for ParseDistributionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid distribution '{}'", self.0)
+    }
+}
+
+impl std::error::Error for ParseDistributionError {}
+
+impl FromStr for Distribution {
+    type Err = ParseDistributionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseDistributionError(s.to_owned());
+        let (name, params) = s.split_once(':').ok_or_else(err)?;
+        let params = params
+            .split(',')
+            .map(|p| p.trim().parse::<f64>().ok().filter(|p| p.is_finite()))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(err)?;
+        let positive = |x: f64| x > 0.0;
+
+        let dist = match (name.to_ascii_lowercase().as_str(), params.as_slice()) {
+            ("normal", &[mu, sigma]) if sigma >= 0.0 => Self::Normal { mu, sigma },
+            ("exp", &[lambda]) if positive(lambda) => Self::Exp { lambda },
+            ("poisson", &[lambda]) if positive(lambda) && lambda < 1e15 => {
+                Self::Poisson(Poisson::new(lambda))
+            }
+            ("binomial", &[n, p]) if n >= 0.0 && n.fract() == 0.0 && (0.0..=1.0).contains(&p) => {
+                Self::Binomial(Binomial::new(n as u64, p))
+            }
+            ("gamma", &[k, theta]) if positive(k) && positive(theta) => Self::Gamma { k, theta },
+            ("beta", &[alpha, beta]) if positive(alpha) && positive(beta) => {
+                Self::Beta { alpha, beta }
+            }
+            ("lognormal", &[mu, sigma]) if sigma >= 0.0 => Self::LogNormal { mu, sigma },
+            ("zipf", &[n, s]) if n >= 1.0 && n.fract() == 0.0 && positive(s) => {
+                Self::Zipf(Zipf::new(n as u64, s))
+            }
+            _ => return Err(err()),
+        };
+        Ok(dist)
+    }
+}
+
+/// Returns a float uniformly distributed in [0, 1) from the upper 53 bits of a word.
+fn unit_f64(bits: u64) -> f64 {
+    (bits >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+}
+
+/// Returns a float uniformly distributed in (0, 1).
+fn open01(stream: &mut RandomStream) -> io::Result<f64> {
+    Ok(((stream.next_u64()? >> 12) as f64 + 0.5) * (1.0 / (1u64 << 52) as f64))
+}
+
+/// Ziggurat of 256 layers (Marsaglia and Tsang 2000) of an unnormalized decreasing density.
+struct Ziggurat {
+    /// Right edges of the layers, from `x[0] = V / f(R)` and `x[1] = R` down to `x[256] = 0`.
+    x: [f64; 257],
+    /// Density at `x`.
+    f: [f64; 257],
+}
+
+impl Ziggurat {
+    /// Builds the layers of area `v` with the base strip starting at `r`.
+    fn new(r: f64, v: f64, pdf: fn(f64) -> f64, pdf_inv: fn(f64) -> f64) -> Self {
+        let mut x = [0.0; 257];
+        x[0] = v / pdf(r);
+        x[1] = r;
+        for i in 1..255 {
+            x[i + 1] = pdf_inv(v / x[i] + pdf(x[i]));
+        }
+        x[256] = 0.0;
+        Self { x, f: x.map(pdf) }
+    }
+
+    /// Draws a sample, calling `tail` to sample beyond `R` in the base strip.
+    fn sample(
+        &self,
+        stream: &mut RandomStream,
+        symmetric: bool,
+        pdf: fn(f64) -> f64,
+        tail: fn(&mut RandomStream, f64) -> io::Result<f64>,
+    ) -> io::Result<f64> {
+        loop {
+            let bits = stream.next_u64()?;
+            let i = (bits & 0xff) as usize;
+            let u = if symmetric {
+                2.0 * unit_f64(bits) - 1.0
+            } else {
+                unit_f64(bits)
+            };
+            let x = u * self.x[i];
+            if x.abs() < self.x[i + 1] {
+                return Ok(x);
+            }
+            if i == 0 {
+                let t = tail(stream, self.x[1])?;
+                return Ok(if u < 0.0 { -t } else { t });
+            }
+            let y = self.f[i + 1] + (self.f[i] - self.f[i + 1]) * unit_f64(stream.next_u64()?);
+            if y < pdf(x) {
+                return Ok(x);
+            }
+        }
+    }
+}
+
+fn standard_normal(stream: &mut RandomStream) -> io::Result<f64> {
+    fn pdf(x: f64) -> f64 {
+        (-0.5 * x * x).exp()
+    }
+    fn pdf_inv(y: f64) -> f64 {
+        (-2.0 * y.ln()).sqrt()
+    }
+    fn tail(stream: &mut RandomStream, r: f64) -> io::Result<f64> {
+        // Marsaglia 1964
+        loop {
+            let x = open01(stream)?.ln() / r;
+            let y = open01(stream)?.ln();
+            if -2.0 * y >= x * x {
+                return Ok(r - x);
+            }
+        }
+    }
+
+    static TABLE: OnceLock<Ziggurat> = OnceLock::new();
+    let table = TABLE
+        .get_or_init(|| Ziggurat::new(3.654_152_885_361_009, 0.004_928_673_233_99, pdf, pdf_inv));
+    table.sample(stream, true, pdf, tail)
+}
+
+fn standard_exp(stream: &mut RandomStream) -> io::Result<f64> {
+    fn pdf(x: f64) -> f64 {
+        (-x).exp()
+    }
+    fn pdf_inv(y: f64) -> f64 {
+        -y.ln()
+    }
+    fn tail(stream: &mut RandomStream, r: f64) -> io::Result<f64> {
+        Ok(r - open01(stream)?.ln())
+    }
+
+    static TABLE: OnceLock<Ziggurat> = OnceLock::new();
+    let table = TABLE.get_or_init(|| {
+        Ziggurat::new(
+            7.697_117_470_131_05,
+            0.003_949_659_822_581_557,
+            pdf,
+            pdf_inv,
+        )
+    });
+    table.sample(stream, false, pdf, tail)
+}
+
+/// Draws a sample of the gamma distribution of scale 1 (Marsaglia and Tsang 2000).
+fn standard_gamma(k: f64, stream: &mut RandomStream) -> io::Result<f64> {
+    if k < 1.0 {
+        // boost the shape and scale the sample by U^(1/k)
+        let g = standard_gamma(k + 1.0, stream)?;
+        return Ok(g * open01(stream)?.powf(1.0 / k));
+    }
+
+    let d = k - 1.0 / 3.0;
+    let c = 1.0 / (9.0 * d).sqrt();
+    loop {
+        let x = standard_normal(stream)?;
+        let v = 1.0 + c * x;
+        if v <= 0.0 {
+            continue;
+        }
+        let v = v * v * v;
+        let u = open01(stream)?;
+        let x2 = x * x;
+        if u < 1.0 - 0.0331 * x2 * x2 || u.ln() < 0.5 * x2 + d * (1.0 - v + v.ln()) {
+            return Ok(d * v);
+        }
+    }
+}
+
+/// Returns the logarithm of a sample of the gamma distribution of scale 1, which does not
+/// underflow for small shapes `k` like the sample itself.
+fn ln_standard_gamma(k: f64, stream: &mut RandomStream) -> io::Result<f64> {
+    if k < 1.0 {
+        let g = standard_gamma(k + 1.0, stream)?;
+        return Ok(g.ln() + open01(stream)?.ln() / k);
+    }
+    Ok(standard_gamma(k, stream)?.ln())
+}
+
+/// Draws a sample of the beta distribution as `X / (X + Y)` of two gamma samples.
+///
+/// For shapes well below 1 both gamma samples often underflow to zero, e.g. in over a fifth of
+/// the draws of `beta:0.001,0.001`, so the ratio is then computed from their logarithms.
+fn standard_beta(alpha: f64, beta: f64, stream: &mut RandomStream) -> io::Result<f64> {
+    let ln_x = ln_standard_gamma(alpha, stream)?;
+    let ln_y = ln_standard_gamma(beta, stream)?;
+    let (x, y) = (ln_x.exp(), ln_y.exp());
+    if x + y > 0.0 {
+        return Ok(x / (x + y));
+    }
+    Ok(1.0 / (1.0 + (ln_y - ln_x).exp()))
+}
+
+/// Returns the logarithm of the gamma function for `x > 0` (Lanczos approximation, g = 7).
+pub(crate) fn ln_gamma(x: f64) -> f64 {
+    const COEFS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    let x = x - 1.0;
+    let a = COEFS[1..]
+        .iter()
+        .enumerate()
+        .fold(COEFS[0], |acc, (i, c)| acc + c / (x + (i + 1) as f64));
+    let t = x + 7.5;
+    0.5 * (2.0 * PI).ln() + (x + 0.5) * t.ln() - t + a.ln()
+}
+
+/// Poisson distribution sampled by multiplication of uniforms for small means, and otherwise by
+/// the transformed rejection method PTRS (Hörmann 1993).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Poisson {
+    lambda: f64,
+    // PTRS constants
+    b: f64,
+    a: f64,
+    inv_alpha: f64,
+    vr: f64,
+    ln_lambda: f64,
+}
+
+impl Poisson {
+    /// Creates the distribution of mean `lambda`.
+    pub fn new(lambda: f64) -> Self {
+        let b = 0.931 + 2.53 * lambda.sqrt();
+        Self {
+            lambda,
+            b,
+            a: -0.059 + 0.02483 * b,
+            inv_alpha: 1.1239 + 1.1328 / (b - 3.4),
+            vr: 0.9277 - 3.6224 / (b - 2.0),
+            ln_lambda: lambda.ln(),
+        }
+    }
+
+    fn sample(&self, stream: &mut RandomStream) -> io::Result<u64> {
+        if self.lambda < 10.0 {
+            let limit = (-self.lambda).exp();
+            let mut k = 0;
+            let mut p = open01(stream)?;
+            while p > limit {
+                p *= open01(stream)?;
+                k += 1;
+            }
+            return Ok(k);
+        }
+
+        loop {
+            let u = open01(stream)? - 0.5;
+            let v = open01(stream)?;
+            let us = 0.5 - u.abs();
+            let k = ((2.0 * self.a / us + self.b) * u + self.lambda + 0.43).floor();
+            if us >= 0.07 && v <= self.vr {
+                return Ok(k as u64);
+            }
+            if k < 0.0 || (us < 0.013 && v > us) {
+                continue;
+            }
+            let lhs = (v * self.inv_alpha / (self.a / (us * us) + self.b)).ln();
+            if lhs <= -self.lambda + k * self.ln_lambda - ln_gamma(k + 1.0) {
+                return Ok(k as u64);
+            }
+        }
+    }
+}
+
+/// Binomial distribution sampled by inversion for small means, and otherwise by the transformed
+/// rejection method BTRS (Hörmann 1993).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Binomial {
+    n: u64,
+    p: f64,
+    /// Whether samples are taken from `n` minus the distribution of `1 - p`.
+    flipped: bool,
+    // BTRS constants for the success probability of at most 0.5
+    b: f64,
+    a: f64,
+    c: f64,
+    vr: f64,
+    alpha: f64,
+    lpq: f64,
+    m: f64,
+    h: f64,
+}
+
+impl Binomial {
+    /// Creates the distribution of `n` trials with success probability `p`.
+    pub fn new(n: u64, p: f64) -> Self {
+        let (p, flipped) = if p > 0.5 { (1.0 - p, true) } else { (p, false) };
+        let nf = n as f64;
+        let q = 1.0 - p;
+        let spq = (nf * p * q).sqrt();
+        let b = 1.15 + 2.53 * spq;
+        let m = ((nf + 1.0) * p).floor();
+        Self {
+            n,
+            p,
+            flipped,
+            b,
+            a: -0.0873 + 0.0248 * b + 0.01 * p,
+            c: nf * p + 0.5,
+            vr: 0.92 - 4.2 / b,
+            alpha: (2.83 + 5.1 / b) * spq,
+            lpq: (p / q).ln(),
+            m,
+            h: ln_gamma(m + 1.0) + ln_gamma(nf - m + 1.0),
+        }
+    }
+
+    fn sample(&self, stream: &mut RandomStream) -> io::Result<u64> {
+        let k = if self.p == 0.0 {
+            0
+        } else if self.n as f64 * self.p < 10.0 {
+            self.sample_inversion(stream)?
+        } else {
+            self.sample_btrs(stream)?
+        };
+        Ok(if self.flipped { self.n - k } else { k })
+    }
+
+    fn sample_inversion(&self, stream: &mut RandomStream) -> io::Result<u64> {
+        let q = 1.0 - self.p;
+        let s = self.p / q;
+        let a = (self.n as f64 + 1.0) * s;
+        'retry: loop {
+            let mut r = q.powf(self.n as f64);
+            let mut u = unit_f64(stream.next_u64()?);
+            let mut k = 0;
+            while u > r {
+                u -= r;
+                k += 1;
+                if k > self.n {
+                    // lost in rounding errors
+                    continue 'retry;
+                }
+                r *= a / k as f64 - s;
+            }
+            return Ok(k);
+        }
+    }
+
+    fn sample_btrs(&self, stream: &mut RandomStream) -> io::Result<u64> {
+        let nf = self.n as f64;
+        loop {
+            let u = open01(stream)? - 0.5;
+            let v = open01(stream)?;
+            let us = 0.5 - u.abs();
+            let k = ((2.0 * self.a / us + self.b) * u + self.c).floor();
+            if k < 0.0 || k > nf {
+                continue;
+            }
+            if us >= 0.07 && v <= self.vr {
+                return Ok(k as u64);
+            }
+            let lhs = (v * self.alpha / (self.a / (us * us) + self.b)).ln();
+            let rhs = self.h - ln_gamma(k + 1.0) - ln_gamma(nf - k + 1.0) + (k - self.m) * self.lpq;
+            if lhs <= rhs {
+                return Ok(k as u64);
+            }
+        }
+    }
+}
+
+/// Zipf distribution over `1..=n` with exponent `s`, sampled by rejection-inversion (Hörmann and
+/// Derflinger 1996).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Zipf {
+    n: f64,
+    s: f64,
+    h_integral_x1: f64,
+    h_integral_n: f64,
+    threshold: f64,
+}
+
+impl Zipf {
+    /// Creates the distribution over `1..=n` with exponent `s`.
+    pub fn new(n: u64, s: f64) -> Self {
+        let mut d = Self {
+            n: n as f64,
+            s,
+            h_integral_x1: 0.0,
+            h_integral_n: 0.0,
+            threshold: 0.0,
+        };
+        d.h_integral_x1 = d.h_integral(1.5) - 1.0;
+        d.h_integral_n = d.h_integral(d.n + 0.5);
+        d.threshold = 2.0 - d.h_integral_inv(d.h_integral(2.5) - d.h(2.0));
+        d
+    }
+
+    fn sample(&self, stream: &mut RandomStream) -> io::Result<u64> {
+        loop {
+            let u = self.h_integral_n
+                + unit_f64(stream.next_u64()?) * (self.h_integral_x1 - self.h_integral_n);
+            let x = self.h_integral_inv(u);
+            let k = (x + 0.5).floor().clamp(1.0, self.n);
+            if k - x <= self.threshold || u >= self.h_integral(k + 0.5) - self.h(k) {
+                return Ok(k as u64);
+            }
+        }
+    }
+
+    fn h(&self, x: f64) -> f64 {
+        (-self.s * x.ln()).exp()
+    }
+
+    /// Integral of `h`, i.e. `(x^(1 - s) - 1) / (1 - s)`, or `ln(x)` if `s = 1`.
+    fn h_integral(&self, x: f64) -> f64 {
+        let ln_x = x.ln();
+        expm1_over_x((1.0 - self.s) * ln_x) * ln_x
+    }
+
+    fn h_integral_inv(&self, x: f64) -> f64 {
+        let t = (x * (1.0 - self.s)).max(-1.0);
+        (ln1p_over_x(t) * x).exp()
+    }
+}
+
+/// Returns `(e^x - 1) / x`, continuous at zero.
+fn expm1_over_x(x: f64) -> f64 {
+    if x.abs() > 1e-8 {
+        x.exp_m1() / x
+    } else {
+        1.0 + x * 0.5 * (1.0 + x / 3.0)
+    }
+}
+
+/// Returns `ln(1 + x) / x`, continuous at zero.
+fn ln1p_over_x(x: f64) -> f64 {
+    if x.abs() > 1e-8 {
+        x.ln_1p() / x
+    } else {
+        1.0 - x * (0.5 - x / 3.0)
+    }
+}
+
+/// Writes `count` samples of `dist` (or infinitely if `None`) to `out` in decimal.
+///
+/// Returns `Ok(())` also when `out` reports [`io::ErrorKind::BrokenPipe`].
+pub fn write_text<W: io::Write>(
+    stream: &mut RandomStream,
+    dist: &Distribution,
+    out: &mut TextWriter<W>,
+    count: Option<u64>,
+) -> io::Result<()> {
+    numbers::repeat(count, || match dist.sample(stream)? {
+        Sample::Float(v) => out.write_f64(v),
+        Sample::Int(v) => out.write_u64(v),
+    })?;
+    numbers::ignore_broken_pipe(out.flush())
+}
+
+/// Writes `count` samples of `dist` (or infinitely if `None`) to `out` as 8-byte binary `f64`s,
+/// or `u64`s for discrete distributions.
+///
+/// Returns `Ok(())` also when `out` reports [`io::ErrorKind::BrokenPipe`].
+pub fn write_binary(
+    stream: &mut RandomStream,
+    dist: &Distribution,
+    out: impl io::Write,
+    endian: Endian,
+    count: Option<u64>,
+) -> io::Result<()> {
+    let mut out = io::BufWriter::with_capacity(crate::BUF_SIZE, out);
+    numbers::repeat(count, || {
+        let bits = match dist.sample(stream)? {
+            Sample::Float(v) => v.to_bits(),
+            Sample::Int(v) => v,
+        };
+        endian.write_int(&mut out, u128::from(bits), 8)
+    })?;
+    numbers::ignore_broken_pipe(io::Write::flush(&mut out))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ln_gamma, Distribution, Sample};
+    use crate::Builder;
+
+    /// Returns the mean and variance of `n` samples.
+    fn moments(dist: &str, n: usize) -> (f64, f64) {
+        let dist: Distribution = dist.parse().unwrap();
+        let mut stream = Builder::new().seed(dist_seed(&dist)).build();
+        let samples: Vec<f64> = (0..n)
+            .map(|_| match dist.sample(&mut stream).unwrap() {
+                Sample::Float(v) => v,
+                Sample::Int(v) => v as f64,
+            })
+            .collect();
+        let mean = samples.iter().sum::<f64>() / n as f64;
+        let var = samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1) as f64;
+        (mean, var)
+    }
+
+    fn dist_seed(dist: &Distribution) -> &'static [u8] {
+        if dist.is_discrete() {
+            b"discrete"
+        } else {
+            b"continuous"
+        }
+    }
+
+    #[test]
+    fn sample_moments() {
+        const N: usize = 200_000;
+        // (spec, mean, variance)
+        let cases = [
+            ("normal:3,2", 3.0, 4.0),
+            ("exp:0.5", 2.0, 4.0),
+            ("poisson:3.5", 3.5, 3.5),
+            ("poisson:1000", 1000.0, 1000.0),
+            ("binomial:20,0.3", 6.0, 4.2),
+            ("binomial:1000,0.9", 900.0, 90.0),
+            ("gamma:2.5,2", 5.0, 10.0),
+            ("gamma:0.5,1", 0.5, 0.5),
+            ("beta:2,3", 0.4, 0.04),
+            ("beta:0.001,0.001", 0.5, 0.25 / 1.002),
+            (
+                "lognormal:0,0.5",
+                0.125f64.exp(),
+                (0.25f64.exp() - 1.0) * 0.25f64.exp(),
+            ),
+        ];
+        for (spec, mean, var) in cases {
+            let (m, v) = moments(spec, N);
+            // standard error of the mean, with a loose bound on that of the variance
+            let se = (var / N as f64).sqrt();
+            assert!(
+                (m - mean).abs() < 5.0 * se,
+                "{}: mean {} != {}",
+                spec,
+                m,
+                mean
+            );
+            assert!(
+                (v / var - 1.0).abs() < 0.05,
+                "{}: variance {} != {}",
+                spec,
+                v,
+                var
+            );
+        }
+    }
+
+    #[test]
+    fn zipf_frequencies() {
+        let dist: Distribution = "zipf:10,1.2".parse().unwrap();
+        let mut stream = Builder::new().seed(b"zipf").build();
+        let mut counts = [0u32; 10];
+        for _ in 0..100_000 {
+            match dist.sample(&mut stream).unwrap() {
+                Sample::Int(k) => counts[k as usize - 1] += 1,
+                s => panic!("unexpected sample {:?}", s),
+            }
+        }
+        let norm: f64 = (1..=10).map(|k| (k as f64).powf(-1.2)).sum();
+        for (k, &c) in (1..=10).zip(counts.iter()) {
+            let p = (k as f64).powf(-1.2) / norm;
+            let expected = p * 100_000.0;
+            assert!(
+                (c as f64 - expected).abs() < 5.0 * (expected * (1.0 - p)).sqrt(),
+                "{}: {} != {}",
+                k,
+                c,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn ln_gamma_values() {
+        assert!(ln_gamma(1.0).abs() < 1e-14);
+        assert!(ln_gamma(2.0).abs() < 1e-14);
+        assert!((ln_gamma(5.0) - 24f64.ln()).abs() < 1e-13);
+        assert!((ln_gamma(0.5) - std::f64::consts::PI.sqrt().ln()).abs() < 1e-13);
+        assert!((ln_gamma(101.0) - 363.739_375_555_563_5).abs() < 1e-10);
+    }
+
+    #[test]
+    fn parse_distributions() {
+        assert_eq!(
+            "normal:0,1".parse(),
+            Ok(Distribution::Normal {
+                mu: 0.0,
+                sigma: 1.0
+            })
+        );
+        assert_eq!("EXP: 2".parse(), Ok(Distribution::Exp { lambda: 2.0 }));
+        for src in [
+            "normal",
+            "normal:0",
+            "normal:0,-1",
+            "exp:0",
+            "poisson:nan",
+            "binomial:2.5,0.5",
+            "binomial:10,1.5",
+            "zipf:0,1",
+            "gamma:1,inf",
+            "uniform:0,1",
+        ] {
+            assert!(src.parse::<Distribution>().is_err(), "{}", src);
+        }
+    }
+}
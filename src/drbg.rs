@@ -0,0 +1,719 @@
+//! Deterministic random bit generators specified in NIST SP 800-90A Rev. 1.
+//!
+//! All mechanisms are instantiated at the 256-bit security strength: [`HashDrbg`] with SHA-256,
+//! [`HmacDrbg`] with HMAC-SHA-256 and [`CtrDrbg`] with AES-256 (without the derivation function).
+//! [`DrbgGenerator`] drives them as a [`Generator`] with entropy inputs from [`getrandom`].
+
+use std::fmt;
+
+use aes::cipher::{BlockEncrypt, KeyInit};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use zeroize::Zeroize;
+
+use crate::generators::Generator;
+
+/// Maximum number of bytes returned by a single generate request (2^19 bits).
+pub const MAX_BYTES_PER_REQUEST: usize = 1 << 16;
+
+/// Maximum number of generate requests between reseeds allowed by the specification.
+pub const MAX_RESEED_INTERVAL: u64 = 1 << 48;
+
+/// Number of bytes of entropy input that provide the full security strength of 256 bits.
+pub const SECURITY_STRENGTH: usize = 32;
+
+/// Error returned by a generate request when the reseed interval has been exceeded.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ReseedRequired;
+
+impl fmt::Display for ReseedRequired {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("DRBG reseed interval exceeded")
+    }
+}
+
+impl std::error::Error for ReseedRequired {}
+
+/// Common interface of the DRBG mechanisms.
+pub trait Drbg {
+    /// Number of bytes of entropy input required by instantiate and reseed.
+    const ENTROPY_LEN: usize;
+
+    /// Number of bytes of nonce required by instantiate.
+    const NONCE_LEN: usize;
+
+    /// Reseeds the internal state with fresh entropy input and optional additional input.
+    fn reseed(&mut self, entropy: &[u8], additional: &[u8]);
+
+    /// Fills `out` with pseudorandom bytes, or returns [`ReseedRequired`] if the number of
+    /// requests since the last (re)seeding exceeds the reseed interval.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `out` is longer than [`MAX_BYTES_PER_REQUEST`].
+    fn generate(&mut self, out: &mut [u8], additional: &[u8]) -> Result<(), ReseedRequired>;
+
+    /// Fills `out` with prediction resistance, i.e. reseeds with `entropy` and `additional`
+    /// before generating without additional input (Sec. 9.3.1).
+    fn generate_with_prediction_resistance(
+        &mut self,
+        entropy: &[u8],
+        out: &mut [u8],
+        additional: &[u8],
+    ) {
+        self.reseed(entropy, additional);
+        self.generate(out, &[])
+            .expect("DRBG must be usable right after reseed");
+    }
+}
+
+/// Options applied by [`DrbgGenerator`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct DrbgOptions {
+    /// Reseeds before every generate request if `true`.
+    pub prediction_resistance: bool,
+    /// Maximum number of generate requests between reseeds.
+    pub reseed_interval: u64,
+}
+
+impl Default for DrbgOptions {
+    fn default() -> Self {
+        Self {
+            prediction_resistance: false,
+            reseed_interval: MAX_RESEED_INTERVAL,
+        }
+    }
+}
+
+/// Checks the reseed counter and the request length shared by all mechanisms.
+fn check_request(
+    out: &[u8],
+    reseed_counter: u64,
+    reseed_interval: u64,
+) -> Result<(), ReseedRequired> {
+    assert!(
+        out.len() <= MAX_BYTES_PER_REQUEST,
+        "DRBG request too large: {} bytes",
+        out.len()
+    );
+    if reseed_counter > reseed_interval {
+        Err(ReseedRequired)
+    } else {
+        Ok(())
+    }
+}
+
+/// Adds big-endian integer `src` to big-endian integer `dst` modulo `2^(8 * dst.len())`.
+fn add_be(dst: &mut [u8], src: &[u8]) {
+    let mut carry = 0u16;
+    let mut src = src.iter().rev();
+    for d in dst.iter_mut().rev() {
+        let sum = u16::from(*d) + u16::from(src.next().copied().unwrap_or(0)) + carry;
+        *d = sum as u8;
+        carry = sum >> 8;
+    }
+}
+
+/// Hash_DRBG (SP 800-90A Sec. 10.1.1) with SHA-256.
+pub struct HashDrbg {
+    v: [u8; Self::SEED_LEN],
+    c: [u8; Self::SEED_LEN],
+    reseed_counter: u64,
+    reseed_interval: u64,
+}
+
+impl HashDrbg {
+    /// Length in bytes of `V` and `C` (440 bits).
+    const SEED_LEN: usize = 55;
+
+    /// Instantiates the mechanism.
+    pub fn new(entropy: &[u8], nonce: &[u8], personalization: &[u8]) -> Self {
+        let mut v = [0; Self::SEED_LEN];
+        Self::hash_df(&[entropy, nonce, personalization], &mut v);
+        let mut c = [0; Self::SEED_LEN];
+        Self::hash_df(&[&[0x00], &v], &mut c);
+        Self {
+            v,
+            c,
+            reseed_counter: 1,
+            reseed_interval: MAX_RESEED_INTERVAL,
+        }
+    }
+
+    /// Sets the maximum number of generate requests between reseeds.
+    pub fn with_reseed_interval(mut self, reseed_interval: u64) -> Self {
+        self.reseed_interval = reseed_interval;
+        self
+    }
+
+    /// Hash derivation function (Sec. 10.3.1) of the concatenation of `inputs`.
+    fn hash_df(inputs: &[&[u8]], out: &mut [u8]) {
+        let n_bits = (out.len() as u32 * 8).to_be_bytes();
+        for (counter, chunk) in (1u8..).zip(out.chunks_mut(32)) {
+            let mut hasher = Sha256::new();
+            hasher.update([counter]);
+            hasher.update(n_bits);
+            for input in inputs {
+                hasher.update(input);
+            }
+            chunk.copy_from_slice(&hasher.finalize()[..chunk.len()]);
+        }
+    }
+}
+
+impl Drbg for HashDrbg {
+    const ENTROPY_LEN: usize = SECURITY_STRENGTH;
+    const NONCE_LEN: usize = SECURITY_STRENGTH / 2;
+
+    fn reseed(&mut self, entropy: &[u8], additional: &[u8]) {
+        let mut v = self.v;
+        Self::hash_df(&[&[0x01], &v, entropy, additional], &mut self.v);
+        v.zeroize();
+        Self::hash_df(&[&[0x00], &self.v], &mut self.c);
+        self.reseed_counter = 1;
+    }
+
+    fn generate(&mut self, out: &mut [u8], additional: &[u8]) -> Result<(), ReseedRequired> {
+        check_request(out, self.reseed_counter, self.reseed_interval)?;
+
+        if !additional.is_empty() {
+            let w = Sha256::new()
+                .chain_update([0x02])
+                .chain_update(self.v)
+                .chain_update(additional)
+                .finalize();
+            add_be(&mut self.v, &w);
+        }
+
+        // Hashgen
+        let mut data = self.v;
+        for chunk in out.chunks_mut(32) {
+            let w = Sha256::digest(data);
+            chunk.copy_from_slice(&w[..chunk.len()]);
+            add_be(&mut data, &[1]);
+        }
+        data.zeroize();
+
+        let h = Sha256::new()
+            .chain_update([0x03])
+            .chain_update(self.v)
+            .finalize();
+        let c = self.c;
+        add_be(&mut self.v, &h);
+        add_be(&mut self.v, &c);
+        add_be(&mut self.v, &self.reseed_counter.to_be_bytes());
+        self.reseed_counter += 1;
+        Ok(())
+    }
+}
+
+impl Drop for HashDrbg {
+    fn drop(&mut self) {
+        self.v.zeroize();
+        self.c.zeroize();
+    }
+}
+
+/// HMAC_DRBG (SP 800-90A Sec. 10.1.2) with HMAC-SHA-256.
+pub struct HmacDrbg {
+    k: [u8; 32],
+    v: [u8; 32],
+    reseed_counter: u64,
+    reseed_interval: u64,
+}
+
+impl HmacDrbg {
+    /// Instantiates the mechanism.
+    pub fn new(entropy: &[u8], nonce: &[u8], personalization: &[u8]) -> Self {
+        let mut drbg = Self {
+            k: [0x00; 32],
+            v: [0x01; 32],
+            reseed_counter: 1,
+            reseed_interval: MAX_RESEED_INTERVAL,
+        };
+        drbg.update(&[entropy, nonce, personalization]);
+        drbg
+    }
+
+    /// Sets the maximum number of generate requests between reseeds.
+    pub fn with_reseed_interval(mut self, reseed_interval: u64) -> Self {
+        self.reseed_interval = reseed_interval;
+        self
+    }
+
+    fn hmac(&self) -> Hmac<Sha256> {
+        <Hmac<Sha256> as Mac>::new_from_slice(&self.k).expect("HMAC accepts keys of any length")
+    }
+
+    /// HMAC_DRBG update function with the concatenation of `provided` as the provided data.
+    fn update(&mut self, provided: &[&[u8]]) {
+        let is_empty = provided.iter().all(|e| e.is_empty());
+        for byte in [0x00, 0x01] {
+            let mut mac = self.hmac().chain_update(self.v).chain_update([byte]);
+            for e in provided {
+                mac.update(e);
+            }
+            self.k = mac.finalize().into_bytes().into();
+            self.v = self
+                .hmac()
+                .chain_update(self.v)
+                .finalize()
+                .into_bytes()
+                .into();
+            if is_empty {
+                break;
+            }
+        }
+    }
+}
+
+impl Drbg for HmacDrbg {
+    const ENTROPY_LEN: usize = SECURITY_STRENGTH;
+    const NONCE_LEN: usize = SECURITY_STRENGTH / 2;
+
+    fn reseed(&mut self, entropy: &[u8], additional: &[u8]) {
+        self.update(&[entropy, additional]);
+        self.reseed_counter = 1;
+    }
+
+    fn generate(&mut self, out: &mut [u8], additional: &[u8]) -> Result<(), ReseedRequired> {
+        check_request(out, self.reseed_counter, self.reseed_interval)?;
+
+        if !additional.is_empty() {
+            self.update(&[additional]);
+        }
+        for chunk in out.chunks_mut(32) {
+            self.v = self
+                .hmac()
+                .chain_update(self.v)
+                .finalize()
+                .into_bytes()
+                .into();
+            chunk.copy_from_slice(&self.v[..chunk.len()]);
+        }
+        self.update(&[additional]);
+        self.reseed_counter += 1;
+        Ok(())
+    }
+}
+
+impl Drop for HmacDrbg {
+    fn drop(&mut self) {
+        self.k.zeroize();
+        self.v.zeroize();
+    }
+}
+
+/// CTR_DRBG (SP 800-90A Sec. 10.2.1) with AES-256 and no derivation function.
+///
+/// Without the derivation function, the entropy input must be exactly 48 bytes (the seed length)
+/// and no nonce is used, while the personalization string and additional inputs are limited to 48
+/// bytes.
+pub struct CtrDrbg {
+    key: aes::Aes256,
+    v: u128,
+    reseed_counter: u64,
+    reseed_interval: u64,
+}
+
+impl CtrDrbg {
+    /// Length in bytes of the seed (key length plus block length).
+    pub const SEED_LEN: usize = 48;
+
+    /// Instantiates the mechanism.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `entropy` is not 48 bytes or `personalization` is longer than 48 bytes.
+    pub fn new(entropy: &[u8], personalization: &[u8]) -> Self {
+        let mut drbg = Self {
+            key: aes::Aes256::new(&[0; 32].into()),
+            v: 0,
+            reseed_counter: 1,
+            reseed_interval: MAX_RESEED_INTERVAL,
+        };
+        drbg.update(&Self::seed_material(entropy, personalization));
+        drbg
+    }
+
+    /// Sets the maximum number of generate requests between reseeds.
+    pub fn with_reseed_interval(mut self, reseed_interval: u64) -> Self {
+        self.reseed_interval = reseed_interval;
+        self
+    }
+
+    /// Returns `entropy` XORed with `input` padded with zeros.
+    fn seed_material(entropy: &[u8], input: &[u8]) -> [u8; Self::SEED_LEN] {
+        assert_eq!(
+            entropy.len(),
+            Self::SEED_LEN,
+            "invalid entropy input length"
+        );
+        let mut seed = Self::pad(input);
+        for (s, e) in seed.iter_mut().zip(entropy) {
+            *s ^= e;
+        }
+        seed
+    }
+
+    fn pad(input: &[u8]) -> [u8; Self::SEED_LEN] {
+        assert!(
+            input.len() <= Self::SEED_LEN,
+            "input longer than seed length"
+        );
+        let mut padded = [0; Self::SEED_LEN];
+        padded[..input.len()].copy_from_slice(input);
+        padded
+    }
+
+    /// Writes the encryptions of the successive values of `V` to `out`.
+    fn keystream(&mut self, out: &mut [u8]) {
+        const CHUNK_BLOCKS: usize = 64;
+        let mut blocks = [aes::Block::default(); CHUNK_BLOCKS];
+        for chunk in out.chunks_mut(CHUNK_BLOCKS * 16) {
+            let blocks = &mut blocks[..chunk.len().div_ceil(16)];
+            for block in blocks.iter_mut() {
+                self.v = self.v.wrapping_add(1);
+                block.copy_from_slice(&self.v.to_be_bytes());
+            }
+            self.key.encrypt_blocks(blocks);
+            for (dst, src) in chunk.chunks_mut(16).zip(blocks.iter()) {
+                dst.copy_from_slice(&src[..dst.len()]);
+            }
+        }
+        for block in blocks.iter_mut() {
+            block.as_mut_slice().zeroize();
+        }
+    }
+
+    /// CTR_DRBG update function.
+    fn update(&mut self, provided: &[u8; Self::SEED_LEN]) {
+        let mut temp = [0; Self::SEED_LEN];
+        self.keystream(&mut temp);
+        for (t, p) in temp.iter_mut().zip(provided) {
+            *t ^= p;
+        }
+        self.key = aes::Aes256::new(temp[..32].into());
+        self.v = u128::from_be_bytes(temp[32..].try_into().unwrap());
+        temp.zeroize();
+    }
+}
+
+impl Drbg for CtrDrbg {
+    const ENTROPY_LEN: usize = Self::SEED_LEN;
+    const NONCE_LEN: usize = 0;
+
+    fn reseed(&mut self, entropy: &[u8], additional: &[u8]) {
+        self.update(&Self::seed_material(entropy, additional));
+        self.reseed_counter = 1;
+    }
+
+    fn generate(&mut self, out: &mut [u8], additional: &[u8]) -> Result<(), ReseedRequired> {
+        check_request(out, self.reseed_counter, self.reseed_interval)?;
+
+        let additional = Self::pad(additional);
+        if additional != [0; Self::SEED_LEN] {
+            self.update(&additional);
+        }
+        self.keystream(out);
+        self.update(&additional);
+        self.reseed_counter += 1;
+        Ok(())
+    }
+}
+
+impl Drop for CtrDrbg {
+    fn drop(&mut self) {
+        self.v.zeroize();
+    }
+}
+
+/// Fills `buf` with entropy input from the OS.
+fn get_entropy(buf: &mut [u8]) {
+    getrandom::getrandom(buf).expect("failed to obtain entropy input for DRBG");
+}
+
+/// Adapter that drives a DRBG as a [`Generator`], reseeding it from [`getrandom`] when the
+/// reseed interval is reached or, with prediction resistance, before every request.
+///
+/// # Panics
+///
+/// [`Generator`] methods panic if the entropy source fails, so that no output is produced
+/// from a DRBG that could not be reseeded.
+pub struct DrbgGenerator<D> {
+    drbg: D,
+    prediction_resistance: bool,
+}
+
+impl<D: Drbg> DrbgGenerator<D> {
+    /// Wraps an instantiated DRBG.
+    pub fn new(drbg: D, prediction_resistance: bool) -> Self {
+        Self {
+            drbg,
+            prediction_resistance,
+        }
+    }
+
+    fn generate(&mut self, out: &mut [u8]) {
+        let mut entropy = [0u8; CtrDrbg::SEED_LEN];
+        let entropy = &mut entropy[..D::ENTROPY_LEN];
+        for chunk in out.chunks_mut(MAX_BYTES_PER_REQUEST) {
+            if self.prediction_resistance {
+                get_entropy(entropy);
+                self.drbg
+                    .generate_with_prediction_resistance(entropy, chunk, &[]);
+            } else if self.drbg.generate(chunk, &[]).is_err() {
+                get_entropy(entropy);
+                self.drbg.reseed(entropy, &[]);
+                self.drbg
+                    .generate(chunk, &[])
+                    .expect("DRBG must be usable right after reseed");
+            }
+        }
+        entropy.zeroize();
+    }
+}
+
+impl<D: Drbg> Generator for DrbgGenerator<D> {
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0u8; 8];
+        self.generate(&mut bytes);
+        u64::from_le_bytes(bytes)
+    }
+
+    fn fill(&mut self, buf: &mut [u64]) {
+        let mut bytes = [0u8; 8 * 512];
+        for chunk in buf.chunks_mut(512) {
+            let bytes = &mut bytes[..(chunk.len() * 8)];
+            self.generate(bytes);
+            for (e, b) in chunk.iter_mut().zip(bytes.chunks_exact(8)) {
+                *e = u64::from_le_bytes(b.try_into().unwrap());
+            }
+        }
+        bytes.zeroize();
+    }
+}
+
+impl<D> fmt::Debug for DrbgGenerator<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DrbgGenerator")
+            .field("prediction_resistance", &self.prediction_resistance)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CtrDrbg, Drbg as _, HashDrbg, HmacDrbg, ReseedRequired};
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..(i + 2)], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn reseed_counter() {
+        let mut drbg = HmacDrbg::new(&[1; 32], &[2; 16], &[]).with_reseed_interval(2);
+        let mut out = [0u8; 16];
+        assert_eq!(drbg.generate(&mut out, &[]), Ok(()));
+        assert_eq!(drbg.generate(&mut out, &[]), Ok(()));
+        assert_eq!(drbg.generate(&mut out, &[]), Err(ReseedRequired));
+        drbg.reseed(&[3; 32], &[]);
+        assert_eq!(drbg.generate(&mut out, &[]), Ok(()));
+
+        let mut drbg = HashDrbg::new(&[1; 32], &[2; 16], &[]).with_reseed_interval(0);
+        assert_eq!(drbg.generate(&mut out, &[]), Err(ReseedRequired));
+
+        let mut drbg = CtrDrbg::new(&[1; 48], &[]).with_reseed_interval(1);
+        assert_eq!(drbg.generate(&mut out, &[]), Ok(()));
+        assert_eq!(drbg.generate(&mut out, &[]), Err(ReseedRequired));
+    }
+
+    /// Runs the CAVP procedure without reseed: instantiate, generate twice and return the second
+    /// output.
+    #[test]
+    fn hmac_drbg_cavp() {
+        // HMAC_DRBG.rsp [SHA-256] [PredictionResistance = False]
+        const VECTORS: [(&str, &str, &str, &str, &str, &str); 3] = [
+            (
+                "ca851911349384bffe89de1cbdc46e6831e44d34a4fb935ee285dd14b71a7488",
+                "659ba96c601dc69fc902940805ec0ca8",
+                "",
+                "",
+                "",
+                "e528e9abf2dece54d47c7e75e5fe302149f817ea9fb4bee6f4199697d04d5b89d54fbb978a15b5c4\
+                 43c9ec21036d2460b6f73ebad0dc2aba6e624abf07745bc107694bb7547bb0995f70de25d6b29e2d\
+                 3011bb19d27676c07162c8b5ccde0668961df86803482cb37ed6d5c0bb8d50cf1f50d476aa0458bd\
+                 aba806f48be9dcb8",
+            ),
+            (
+                "79737479ba4e7642a221fcfd1b820b134e9e3540a35bb48ffae29c20f5418ea3",
+                "3593259c092bef4129bc2c6c9e19f343",
+                "",
+                "",
+                "",
+                "cf5ad5984f9e43917aa9087380dac46e410ddc8a7731859c84e9d0f31bd43655b924159413e2293b\
+                 17610f211e09f770f172b8fb693a35b85d3b9e5e63b1dc252ac0e115002e9bedfb4b5b6fd43f33b8\
+                 e0eafb2d072e1a6fee1f159df9b51e6c8da737e60d5032dd30544ec51558c6f080bdbdab1de8a939\
+                 e961e06b5f1aca37",
+            ),
+            (
+                "d3cc4d1acf3dde0c4bd2290d262337042dc632948223d3a2eaab87da44295fbd",
+                "0109b0e729f457328aa18569a9224921",
+                "",
+                "3c311848183c9a212a26f27f8c6647e40375e466a0857cc39c4e47575d53f1f6",
+                "fcb9abd19ccfbccef88c9c39bfb3dd7b1c12266c9808992e305bc3cff566e4e4",
+                "9c7b758b212cd0fcecd5daa489821712e3cdea4467b560ef5ddc24ab47749a1f1ffdbbb118f4e62f\
+                 cfca3371b8fbfc5b0646b83e06bfbbab5fac30ea09ea2bc76f1ea568c9be0444b2cc90517b20ca82\
+                 5f2d0eccd88e7175538b85d90ab390183ca6395535d34473af6b5a5b88f5a59ee7561573337ea819\
+                 da0dcc3573a22974",
+            ),
+        ];
+
+        for (entropy, nonce, pers, add1, add2, expected) in VECTORS {
+            let mut drbg = HmacDrbg::new(&hex(entropy), &hex(nonce), &hex(pers));
+            let expected = hex(expected);
+            let mut out = vec![0; expected.len()];
+            drbg.generate(&mut out, &hex(add1)).unwrap();
+            drbg.generate(&mut out, &hex(add2)).unwrap();
+            assert_eq!(out, expected, "HMAC_DRBG mismatch for entropy {}", entropy);
+        }
+    }
+
+    /// Runs the CAVP procedure with prediction resistance: instantiate, generate twice with fresh
+    /// entropy inputs and return the second output.
+    #[test]
+    fn prediction_resistance_cavp() {
+        // Hash_DRBG.rsp [SHA-256] [PredictionResistance = True] COUNT = 14
+        let mut hash_drbg = HashDrbg::new(
+            &hex("066dc8ce75b28966a685163fe2a4d427fbdb616650616ba282fc332b4e6f1220"),
+            &hex("559f7c64897083ec2d7370d9f0e5071f"),
+            &hex("886f549aad1ac63d18cbcc6685daa2c2f79eb0894cb4aef1ac544fce57f15e11"),
+        );
+        let hash_inputs = [
+            "ff80b7d26a05bc8a7abe53286b0eeb733b715a205bfa4ff63703deadb6ea0ef4",
+            "b7215f14ac7bafd0a91772ba22f719afbd20b311636c2b1e83e4a823353fc6ea",
+            "c73832534681ede37e03846d3c841767297d246c689241d2e775be7ec996293d",
+            "ced31f7e0dae5bb5c043e246b29473e2fd39512ead4569eee3e3803314aba7a3",
+        ];
+        let hash_expected = "60c234cfafb468033bf195e578ce266e1465326a96a9e03f8b893670ef62754d\
+                             5e80d553a1f84950208b9343079f2ef856e9c570618597b5dc82a2daeaa3fd9b\
+                             2fd2a0d71bc62935ccb83da0679805a0e31efee4f0e513b08317faca935e3829\
+                             48d272db763e6df32510ff1b99fff8c60eb0dd292ebcbbc80a016ed3b00e4eab";
+
+        // HMAC_DRBG.rsp [SHA-256] [PredictionResistance = True] COUNT = 0
+        let mut hmac_drbg = HmacDrbg::new(
+            &hex("ca851911349384bffe89de1cbdc46e6831e44d34a4fb935ee285dd14b71a7488"),
+            &hex("659ba96c601dc69fc902940805ec0ca8"),
+            &hex("e72dd8590d4ed5295515c35ed6199e9d211b8f069b3058caa6670b96ef1208d0"),
+        );
+        let hmac_inputs = [
+            "5cacc68165a2e2ee20812f35ec73a79dbf30fd475476ac0c44fc6174cdac2b55",
+            "793a7ef8f6f0482beac542bb785c10f8b7b406a4de92667ab168ecc2cf7573c6",
+            "8df013b4d103523073917ddf6a869793059e9943fc8654549e7ab22f7c29f122",
+            "2238cdb4e23d629fe0c2a83dd8d5144ce1a6229ef41dabe2a99ff722e510b530",
+        ];
+        let hmac_expected = "b1d17c002a7febd28412d8e58a7f32318e4ee3605a99b05b05d59356d5f0c6b4\
+                             960a4b8f963b7efa55bb6872fbeac7b99b78dea8f3531973637c946a9cab3349\
+                             744b24a0851dd47f2b3b460c2c61846e91181d62d42c60a4efda5ed57902bfd7\
+                             02b349c54952c7f644769d8ef4015ecc5f5bbd4af06134688e30050e0497fb0a";
+
+        type Generate<'a> = &'a mut dyn FnMut(&[u8], &mut [u8], &[u8]);
+        let cases: [(Generate, _, _); 2] = [
+            (
+                &mut |e, o, a| hash_drbg.generate_with_prediction_resistance(e, o, a),
+                hash_inputs,
+                hash_expected,
+            ),
+            (
+                &mut |e, o, a| hmac_drbg.generate_with_prediction_resistance(e, o, a),
+                hmac_inputs,
+                hmac_expected,
+            ),
+        ];
+        for (generate, [entropy0, add0, entropy1, add1], expected) in cases {
+            let expected = hex(expected);
+            let mut out = vec![0; expected.len()];
+            generate(&hex(entropy0), &mut out, &hex(add0));
+            generate(&hex(entropy1), &mut out, &hex(add1));
+            assert_eq!(out, expected, "mismatch for entropy input {}", entropy0);
+        }
+    }
+
+    /// Runs the CAVP procedure with reseed: instantiate, reseed, generate twice and return the
+    /// second output.
+    #[test]
+    fn ctr_drbg_cavp() {
+        // CTR_DRBG.rsp [AES-256 no df] [PredictionResistance = False]
+        const VECTORS: [[&str; 7]; 4] = [
+            [
+                "e4bc23c5089a19d86f4119cb3fa08c0a4991e0a1def17e101e4c14d9c323460a7c2fb58e0b086c6c\
+                 57b55f56cae25bad",
+                "",
+                "fd85a836bba85019881e8c6bad23c9061adc75477659acaea8e4a01dfe07a1832dad1c136f59d70f\
+                 8653a5dc118663d6",
+                "",
+                "",
+                "",
+                "b2cb8905c05e5950ca31895096be29ea3d5a3b82b269495554eb80fe07de43e193b9e7c3ece73b80\
+                 e062b1c1f68202fbb1c52a040ea2478864295282234aaada",
+            ],
+            [
+                "99903165903fea49c2db26ed675e44cc14cb2c1f28b836b203240b02771e831146ffc4335373bb34\
+                 4688c5c950670291",
+                "",
+                "b4ee99fa9e0eddaf4a3612013cd636c4af69177b43eebb3c58a305b9979b68b5cc820504f6c029aa\
+                 d78a5d29c66e84a0",
+                "2d8c5c28b05696e74774eb69a10f01c5fabc62691ddf7848a8004bb5eeb4d2c5febe1aa01f4d557b\
+                 23d7e9a0e4e90655",
+                "0dc9cde42ac6e856f01a55f219c614de90c659260948db5053d414bab0ec2e13e995120c3eb5aafc\
+                 25dc4bdcef8ace24",
+                "711be6c035013189f362211889248ca8a3268e63a7eb26836d915810a680ac4a33cd1180811a31a0\
+                 f44f08db3dd64f91",
+                "11c7a0326ea737baa7a993d510fafee5374e7bbe17ef0e3e29f50fa68aac2124b017d449768491ca\
+                 c06d136d691a4e80785739f9aaedf311bba752a3268cc531",
+            ],
+            [
+                "ffad10100025a879672ff50374b286712f457dd01441d76ac1a1cd15c7390dd93179a2f5920d198b\
+                 f34a1b76fbc21289",
+                "1d2be6f25e88fa30c4ef42e4d54efd957dec231fa00143ca47580be666a8c143a916c90b3819a0a7\
+                 ea914e3c9a2e7a3f",
+                "6c1a089cae313363bc76a780139eb4f2f2048b1f6b07896c5c412bff0385440fc43b73facbb79e3a\
+                 252fa01fe17ab391",
+                "",
+                "",
+                "",
+                "e053c7d4bd9099ef6a99f190a5fd80219437d642006672338da6e0fe73ca4d24ffa51151bfbdac78\
+                 d8a2f6255046edf57a04626e9977139c6933274299f3bdff",
+            ],
+            [
+                "ae7ebe062971f5eb32e5b21444750785de816595ad2cbe80a209c8f8ab04b5468166de8c6ae522d8\
+                 f10b56386a3b424f",
+                "55860dae57fcac297087c137efb796878a75868f6e7681114e9b73ed0c67e3c62bfc9f5d77e8caa5\
+                 9bcdb223f4ffd247",
+                "a42407931bfeca70e6ee5dd197021a129525051c07468e8b25587c5ad50abe9204e882fe847b8fd4\
+                 7cf7b4360e5aa034",
+                "ee4c88d1eb05f4853663eada501d2fc4b4984b283a88db579af2113031e03d9bc570de943dd16891\
+                 8f3ba8065581fea7",
+                "4b4b03ef19b0f259dca2b3ee3ae4cd86c3895a784b3d8eee043a2003c08289f8fffdad141e6b1ab2\
+                 174d8d5d79c1e581",
+                "3062b33f116b46e20fe3c354726ae9b2a3a4c51922c8107863cb86f1f0bdad7554075659d91c371e\
+                 2b11b1e8106a1ed5",
+                "0d270518baeafac160ff1cb28c11ef68712c764c0c01674e6c9ca2cc9c7e0e8accfd3c753635ee07\
+                 0081eee7628af6187fbc2854b3c204461a796cf3f3fcb092",
+            ],
+        ];
+
+        for [entropy, pers, entropy_reseed, add_reseed, add1, add2, expected] in VECTORS {
+            let mut drbg = CtrDrbg::new(&hex(entropy), &hex(pers));
+            drbg.reseed(&hex(entropy_reseed), &hex(add_reseed));
+            let expected = hex(expected);
+            let mut out = vec![0; expected.len()];
+            drbg.generate(&mut out, &hex(add1)).unwrap();
+            drbg.generate(&mut out, &hex(add2)).unwrap();
+            assert_eq!(out, expected, "CTR_DRBG mismatch for entropy {}", entropy);
+        }
+    }
+}
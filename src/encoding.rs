@@ -0,0 +1,500 @@
+//! Text encodings of the byte stream.
+
+use std::{cmp, fmt, io, str::FromStr};
+
+/// Number of encoded bytes buffered before they are written to the inner writer.
+const OUT_BUF_SIZE: usize = 64 * 1024;
+
+const HEX_LOWER: &[u8; 16] = b"0123456789abcdef";
+const HEX_UPPER: &[u8; 16] = b"0123456789ABCDEF";
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const BASE64_URL: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+const BASE32: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+const BASE58: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+const Z85: &[u8; 85] =
+    b"0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ.-:+=^!/*?&<>()[]{}@%$#";
+
+/// Output format of the byte stream.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum Format {
+    /// Raw bytes.
+    #[default]
+    Raw,
+    /// Lowercase hexadecimal.
+    Hex,
+    /// Uppercase hexadecimal.
+    HexUpper,
+    /// Base64 with padding (RFC 4648, section 4).
+    Base64,
+    /// URL-safe Base64 without padding (RFC 4648, section 5).
+    Base64Url,
+    /// Base32 with padding (RFC 4648, section 6).
+    Base32,
+    /// Base58 with the Bitcoin alphabet, encoded in blocks of 8 bytes as in Monero so that it can
+    /// be streamed; each block becomes 11 characters.
+    Base58,
+    /// Z85 (ZeroMQ RFC 32); a final partial block of `n` bytes is zero-padded and truncated to
+    /// `n + 1` characters as in Ascii85.
+    Z85,
+}
+
+impl Format {
+    /// All formats in the order of listing.
+    pub const ALL: [Self; 8] = [
+        Self::Raw,
+        Self::Hex,
+        Self::HexUpper,
+        Self::Base64,
+        Self::Base64Url,
+        Self::Base32,
+        Self::Base58,
+        Self::Z85,
+    ];
+
+    /// Returns the name used to select the format on the command line.
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Raw => "raw",
+            Self::Hex => "hex",
+            Self::HexUpper => "HEX",
+            Self::Base64 => "base64",
+            Self::Base64Url => "base64url",
+            Self::Base32 => "base32",
+            Self::Base58 => "base58",
+            Self::Z85 => "z85",
+        }
+    }
+
+    /// Returns the number of bytes in a block and the number of characters encoding it.
+    const fn block_len(self) -> (usize, usize) {
+        match self {
+            Self::Raw => (1, 1),
+            Self::Hex | Self::HexUpper => (1, 2),
+            Self::Base64 | Self::Base64Url => (3, 4),
+            Self::Base32 => (5, 8),
+            Self::Base58 => (8, 11),
+            Self::Z85 => (4, 5),
+        }
+    }
+
+    /// Returns the number of characters, excluding padding, encoding a final partial block of
+    /// `n` bytes.
+    const fn partial_len(self, n: usize) -> usize {
+        let table: &[usize] = match self {
+            Self::Raw | Self::Hex | Self::HexUpper => &[0],
+            Self::Base64 | Self::Base64Url => &[0, 2, 3],
+            Self::Base32 => &[0, 2, 4, 5, 7],
+            Self::Base58 => &[0, 2, 3, 5, 6, 7, 9, 10],
+            Self::Z85 => &[0, 2, 3, 4],
+        };
+        table[n]
+    }
+
+    /// Returns `true` if a final partial block is padded with `=` to the full block length.
+    const fn is_padded(self) -> bool {
+        matches!(self, Self::Base64 | Self::Base32)
+    }
+
+    /// Returns the number of characters, excluding padding and line breaks, encoding `bytes`
+    /// bytes.
+    pub const fn encoded_len(self, bytes: u64) -> u64 {
+        let (bb, bc) = self.block_len();
+        bytes / bb as u64 * bc as u64 + self.partial_len((bytes % bb as u64) as usize) as u64
+    }
+
+    /// Returns the smallest number of bytes whose encoding has at least `chars` characters.
+    pub const fn input_len(self, chars: u64) -> u64 {
+        let (bb, bc) = self.block_len();
+        let mut n = chars / bc as u64 * bb as u64;
+        let rest = (chars % bc as u64) as usize;
+        let mut i = 0;
+        // a full block if no partial block is long enough
+        while i < bb && self.partial_len(i) < rest {
+            i += 1;
+        }
+        n += i as u64;
+        n
+    }
+
+    /// Appends the encoding of `src`, whose length must be a multiple of the block length, to
+    /// `dst`.
+    fn encode_blocks(self, src: &[u8], dst: &mut Vec<u8>) {
+        match self {
+            Self::Raw => dst.extend_from_slice(src),
+            Self::Hex => encode_hex(HEX_LOWER, src, dst),
+            Self::HexUpper => encode_hex(HEX_UPPER, src, dst),
+            Self::Base64 => encode_pow2::<3, 4>(BASE64, src, dst),
+            Self::Base64Url => encode_pow2::<3, 4>(BASE64_URL, src, dst),
+            Self::Base32 => encode_pow2::<5, 8>(BASE32, src, dst),
+            Self::Base58 => encode_radix::<58, 8, 11>(BASE58, src, dst),
+            Self::Z85 => encode_radix::<85, 4, 5>(Z85, src, dst),
+        }
+    }
+
+    /// Appends the encoding of a final partial block to `dst`, padded if `pad` is `true` and the
+    /// format is padded.
+    fn encode_partial(self, src: &[u8], pad: bool, dst: &mut Vec<u8>) {
+        let (bb, bc) = self.block_len();
+        debug_assert!(src.len() < bb);
+        if src.is_empty() {
+            return;
+        }
+        let n_chars = self.partial_len(src.len());
+        if self == Self::Base58 {
+            let start = dst.len();
+            dst.resize(start + n_chars, 0);
+            encode_digits(BASE58, src, &mut dst[start..]);
+            return;
+        }
+
+        let mut block = [0u8; 8];
+        block[..src.len()].copy_from_slice(src);
+        let start = dst.len();
+        self.encode_blocks(&block[..bb], dst);
+        dst.truncate(start + n_chars);
+        if pad && self.is_padded() {
+            dst.resize(start + bc, b'=');
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Error returned when parsing an unknown format name.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseFormatError(String);
+
+impl fmt::Display for ParseFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown format '{}'", self.0)
+    }
+}
+
+impl std::error::Error for ParseFormatError {}
+
+impl FromStr for Format {
+    type Err = ParseFormatError;
+
+    /// Parses a format name, which is case-insensitive unless an exact match is found (e.g.
+    /// `HEX`).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|f| f.name() == s)
+            .or_else(|| {
+                Self::ALL
+                    .into_iter()
+                    .find(|f| f.name().eq_ignore_ascii_case(s))
+            })
+            .ok_or_else(|| ParseFormatError(s.to_owned()))
+    }
+}
+
+fn encode_hex(alphabet: &[u8; 16], src: &[u8], dst: &mut Vec<u8>) {
+    let start = dst.len();
+    dst.resize(start + src.len() * 2, 0);
+    for (pair, &b) in dst[start..].chunks_exact_mut(2).zip(src) {
+        pair[0] = alphabet[usize::from(b >> 4)];
+        pair[1] = alphabet[usize::from(b & 15)];
+    }
+}
+
+/// Encodes blocks of `B` bytes into `C` characters of `8 * B / C` bits each.
+fn encode_pow2<const B: usize, const C: usize>(alphabet: &[u8], src: &[u8], dst: &mut Vec<u8>) {
+    let bits = 8 * B / C;
+    let mask = (1u64 << bits) - 1;
+    let start = dst.len();
+    dst.resize(start + src.len() / B * C, 0);
+    for (chars, block) in dst[start..].chunks_exact_mut(C).zip(src.chunks_exact(B)) {
+        let v = block.iter().fold(0u64, |acc, &b| acc << 8 | u64::from(b));
+        for (i, c) in chars.iter_mut().enumerate() {
+            *c = alphabet[((v >> (bits * (C - 1 - i))) & mask) as usize];
+        }
+    }
+}
+
+/// Encodes blocks of `B` bytes, read as big-endian integers, into `C` digits each.
+fn encode_radix<const N: usize, const B: usize, const C: usize>(
+    alphabet: &[u8; N],
+    src: &[u8],
+    dst: &mut Vec<u8>,
+) {
+    let start = dst.len();
+    dst.resize(start + src.len() / B * C, 0);
+    for (chars, block) in dst[start..].chunks_exact_mut(C).zip(src.chunks_exact(B)) {
+        encode_digits(alphabet, block, chars);
+    }
+}
+
+/// Encodes the big-endian integer `src` of up to eight bytes into the digits filling `dst`.
+fn encode_digits<const N: usize>(alphabet: &[u8; N], src: &[u8], dst: &mut [u8]) {
+    let mut v = src.iter().fold(0u64, |acc, &b| acc << 8 | u64::from(b));
+    for c in dst.iter_mut().rev() {
+        *c = alphabet[(v % N as u64) as usize];
+        v /= N as u64;
+    }
+}
+
+/// Writer that encodes the bytes written to it in a text format.
+///
+/// Partial blocks are kept until more bytes are written, so [`Encoder::finish`] must be called to
+/// write the end of the encoding and the final line break.
+#[derive(Debug)]
+pub struct Encoder<W: io::Write> {
+    inner: W,
+    format: Format,
+    /// Line width, or zero if lines are not wrapped.
+    wrap: usize,
+    column: usize,
+    /// Number of characters left to write if the output is limited.
+    limit: Option<u64>,
+    partial: [u8; 8],
+    partial_len: usize,
+    chars: Vec<u8>,
+    out: Vec<u8>,
+}
+
+impl<W: io::Write> Encoder<W> {
+    /// Creates an encoder that writes to `inner` in `format`.
+    pub fn new(inner: W, format: Format) -> Self {
+        Self {
+            inner,
+            format,
+            wrap: 0,
+            column: 0,
+            limit: None,
+            partial: [0; 8],
+            partial_len: 0,
+            chars: Vec::new(),
+            out: Vec::with_capacity(OUT_BUF_SIZE),
+        }
+    }
+
+    /// Breaks lines after every `width` characters (or never if zero).
+    pub fn wrap(mut self, width: usize) -> Self {
+        self.wrap = width;
+        self
+    }
+
+    /// Stops writing after `chars` characters, excluding line breaks, and omits padding.
+    ///
+    /// Use [`Format::input_len`] to find the number of bytes to write to the encoder.
+    pub fn limit(mut self, chars: u64) -> Self {
+        self.limit = Some(chars);
+        self
+    }
+
+    /// Writes the encoding of the remaining partial block, terminates the last line, and returns
+    /// the inner writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.chars.clear();
+        let pad = self.limit.is_none();
+        self.format
+            .encode_partial(&self.partial[..self.partial_len], pad, &mut self.chars);
+        self.partial_len = 0;
+        self.emit();
+        if self.column > 0 {
+            self.out.push(b'\n');
+            self.column = 0;
+        }
+        self.inner.write_all(&self.out)?;
+        self.out.clear();
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+
+    /// Moves the characters encoded in `self.chars` to the output buffer, breaking lines.
+    fn emit(&mut self) {
+        let mut chars = &self.chars[..];
+        if let Some(limit) = self.limit.as_mut() {
+            let n = cmp::min(*limit, chars.len() as u64);
+            chars = &chars[..n as usize];
+            *limit -= n;
+        }
+
+        if self.wrap == 0 {
+            self.out.extend_from_slice(chars);
+            self.column = self.column.saturating_add(chars.len());
+            return;
+        }
+        while !chars.is_empty() {
+            if self.column == self.wrap {
+                self.out.push(b'\n');
+                self.column = 0;
+            }
+            let n = cmp::min(self.wrap - self.column, chars.len());
+            self.out.extend_from_slice(&chars[..n]);
+            self.column += n;
+            chars = &chars[n..];
+        }
+    }
+}
+
+impl<W: io::Write> io::Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let (bb, _) = self.format.block_len();
+        let mut src = buf;
+        self.chars.clear();
+
+        if self.partial_len > 0 {
+            let n = cmp::min(bb - self.partial_len, src.len());
+            self.partial[self.partial_len..(self.partial_len + n)].copy_from_slice(&src[..n]);
+            self.partial_len += n;
+            src = &src[n..];
+            if self.partial_len < bb {
+                return Ok(buf.len());
+            }
+            self.format
+                .encode_blocks(&self.partial[..bb], &mut self.chars);
+            self.partial_len = 0;
+        }
+
+        let n_full = src.len() / bb * bb;
+        self.format.encode_blocks(&src[..n_full], &mut self.chars);
+        let rest = &src[n_full..];
+        self.partial[..rest.len()].copy_from_slice(rest);
+        self.partial_len = rest.len();
+
+        self.emit();
+        if self.out.len() >= OUT_BUF_SIZE {
+            self.inner.write_all(&self.out)?;
+            self.out.clear();
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.write_all(&self.out)?;
+        self.out.clear();
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write as _;
+
+    use super::{Encoder, Format};
+
+    fn encode(format: Format, src: &[u8]) -> String {
+        let mut e = Encoder::new(Vec::new(), format);
+        e.write_all(src).unwrap();
+        String::from_utf8(e.finish().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn known_answer() {
+        // RFC 4648 section 10
+        let cases = [
+            ("", "", "", "", ""),
+            ("f", "66", "Zg==", "Zg", "MY======"),
+            ("fo", "666f", "Zm8=", "Zm8", "MZXQ===="),
+            ("foo", "666f6f", "Zm9v", "Zm9v", "MZXW6==="),
+            ("foob", "666f6f62", "Zm9vYg==", "Zm9vYg", "MZXW6YQ="),
+            ("fooba", "666f6f6261", "Zm9vYmE=", "Zm9vYmE", "MZXW6YTB"),
+            (
+                "foobar",
+                "666f6f626172",
+                "Zm9vYmFy",
+                "Zm9vYmFy",
+                "MZXW6YTBOI======",
+            ),
+        ];
+        for (src, hex, base64, base64url, base32) in cases {
+            let nl = if src.is_empty() { "" } else { "\n" };
+            assert_eq!(encode(Format::Hex, src.as_bytes()), hex.to_owned() + nl);
+            assert_eq!(
+                encode(Format::Base64, src.as_bytes()),
+                base64.to_owned() + nl
+            );
+            assert_eq!(
+                encode(Format::Base64Url, src.as_bytes()),
+                base64url.to_owned() + nl
+            );
+            assert_eq!(
+                encode(Format::Base32, src.as_bytes()),
+                base32.to_owned() + nl
+            );
+        }
+        assert_eq!(encode(Format::HexUpper, &[0xab, 0x0f]), "AB0F\n");
+        assert_eq!(encode(Format::Base64Url, &[0xfb, 0xff]), "-_8\n");
+
+        // ZeroMQ RFC 32
+        let src = [0x86, 0x4f, 0xd2, 0x6f, 0xb5, 0x59, 0xf7, 0x5b];
+        assert_eq!(encode(Format::Z85, &src), "HelloWorld\n");
+        assert_eq!(encode(Format::Z85, &src[..6]), "HelloWoi\n");
+
+        // Monero base58 block encoding
+        assert_eq!(encode(Format::Base58, &[0xff]), "5Q\n");
+        assert_eq!(encode(Format::Base58, &[0x01, 0x00]), "15R\n");
+        assert_eq!(encode(Format::Base58, &[0xff; 3]), "2UzHL\n");
+        assert_eq!(
+            encode(Format::Base58, &[0xff; 8]),
+            "jpXCZedGfVQ\n".to_owned()
+        );
+        assert_eq!(
+            encode(
+                Format::Base58,
+                &[0x06, 0x15, 0x60, 0x13, 0x76, 0x28, 0x79, 0xf7, 0xff]
+            ),
+            "222222222225Q\n"
+        );
+    }
+
+    #[test]
+    fn split_writes_and_wrap() {
+        let src: Vec<u8> = (0..=255).cycle().take(1000).collect();
+        for format in Format::ALL.into_iter().filter(|&f| f != Format::Raw) {
+            let whole = encode(format, &src);
+            let chars = whole.trim_end();
+            assert!(
+                chars.len() as u64 >= format.encoded_len(src.len() as u64),
+                "{}",
+                format
+            );
+
+            let mut e = Encoder::new(Vec::new(), format).wrap(7);
+            for chunk in src.chunks(13) {
+                e.write_all(chunk).unwrap();
+            }
+            let wrapped = String::from_utf8(e.finish().unwrap()).unwrap();
+            assert!(wrapped.lines().all(|line| line.len() <= 7), "{}", format);
+            assert!(wrapped.ends_with('\n') && !wrapped.ends_with("\n\n"));
+            assert_eq!(wrapped.replace('\n', ""), chars, "{}", format);
+        }
+    }
+
+    #[test]
+    fn limit_chars() {
+        let src = [0x5a; 64];
+        for format in Format::ALL.into_iter().filter(|&f| f != Format::Raw) {
+            for n in [0, 1, 5, 11, 17, 30] {
+                let len = format.input_len(n) as usize;
+                assert!(format.encoded_len(len as u64) >= n);
+                assert!(len == 0 || format.encoded_len(len as u64 - 1) < n);
+
+                let mut e = Encoder::new(Vec::new(), format).limit(n);
+                e.write_all(&src[..len]).unwrap();
+                let out = e.finish().unwrap();
+                let expected = encode(format, &src[..len]);
+                assert_eq!(out.len() as u64, n + u64::from(n > 0), "{}", format);
+                assert_eq!(out[..n as usize], expected.as_bytes()[..n as usize]);
+            }
+        }
+    }
+
+    #[test]
+    fn format_names() {
+        for f in Format::ALL {
+            assert_eq!(f.name().parse(), Ok(f));
+        }
+        assert_eq!("HEX".parse(), Ok(Format::HexUpper));
+        assert_eq!("Hex".parse(), Ok(Format::Hex));
+        assert_eq!("BASE64URL".parse(), Ok(Format::Base64Url));
+        assert!("base16".parse::<Format>().is_err());
+    }
+}
@@ -0,0 +1,314 @@
+//! Pseudorandom number generators that produce the stream.
+
+use std::{fmt, str::FromStr};
+
+use crate::drbg::{CtrDrbg, DrbgGenerator, DrbgOptions, HashDrbg, HmacDrbg};
+
+mod aes_ctr;
+mod chacha;
+mod multilane;
+mod pcg64dxsm;
+mod sfc64;
+mod splitmix64;
+mod wyrand;
+mod xoroshiro128plusplus;
+mod xorshift64star;
+mod xoshiro256starstar;
+
+pub use aes_ctr::{Aes128Ctr, Aes256Ctr};
+pub use chacha::{ChaCha12, ChaCha20, ChaCha8};
+pub use multilane::{Xorshift64StarX8, Xoshiro256StarStarX8, LANES};
+pub use pcg64dxsm::Pcg64Dxsm;
+pub use sfc64::Sfc64;
+pub use splitmix64::SplitMix64;
+pub use wyrand::WyRand;
+pub use xoroshiro128plusplus::Xoroshiro128PlusPlus;
+pub use xorshift64star::Xorshift64Star;
+pub use xoshiro256starstar::Xoshiro256StarStar;
+
+/// Generator of random 64-bit words.
+pub trait Generator {
+    /// Returns the next random word.
+    fn next_u64(&mut self) -> u64;
+
+    /// Fills `buf` with random words.
+    fn fill(&mut self, buf: &mut [u64]) {
+        for e in buf.iter_mut() {
+            *e = self.next_u64();
+        }
+    }
+}
+
+impl<T: Generator + ?Sized> Generator for Box<T> {
+    fn next_u64(&mut self) -> u64 {
+        (**self).next_u64()
+    }
+
+    fn fill(&mut self, buf: &mut [u64]) {
+        (**self).fill(buf)
+    }
+}
+
+/// Generator algorithm selectable at runtime.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum Algorithm {
+    /// [`Xorshift64Star`]
+    #[default]
+    Xorshift64Star,
+    /// [`Xoshiro256StarStar`]
+    Xoshiro256StarStar,
+    /// [`Xoroshiro128PlusPlus`]
+    Xoroshiro128PlusPlus,
+    /// [`SplitMix64`]
+    SplitMix64,
+    /// [`Pcg64Dxsm`]
+    Pcg64Dxsm,
+    /// [`WyRand`]
+    WyRand,
+    /// [`Sfc64`]
+    Sfc64,
+    /// [`Xorshift64StarX8`]
+    Xorshift64StarX8,
+    /// [`Xoshiro256StarStarX8`]
+    Xoshiro256StarStarX8,
+    /// [`ChaCha20`]
+    ChaCha20,
+    /// [`ChaCha12`]
+    ChaCha12,
+    /// [`ChaCha8`]
+    ChaCha8,
+    /// [`Aes128Ctr`]
+    Aes128Ctr,
+    /// [`Aes256Ctr`]
+    Aes256Ctr,
+    /// [`HashDrbg`]
+    HashDrbg,
+    /// [`HmacDrbg`]
+    HmacDrbg,
+    /// [`CtrDrbg`]
+    CtrDrbg,
+}
+
+impl Algorithm {
+    /// All algorithms in the order of listing.
+    pub const ALL: [Self; 17] = [
+        Self::Xorshift64Star,
+        Self::Xoshiro256StarStar,
+        Self::Xoroshiro128PlusPlus,
+        Self::SplitMix64,
+        Self::Pcg64Dxsm,
+        Self::WyRand,
+        Self::Sfc64,
+        Self::Xorshift64StarX8,
+        Self::Xoshiro256StarStarX8,
+        Self::ChaCha20,
+        Self::ChaCha12,
+        Self::ChaCha8,
+        Self::Aes128Ctr,
+        Self::Aes256Ctr,
+        Self::HashDrbg,
+        Self::HmacDrbg,
+        Self::CtrDrbg,
+    ];
+
+    /// Returns the name used to select the algorithm on the command line.
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Xorshift64Star => "xorshift64star",
+            Self::Xoshiro256StarStar => "xoshiro256starstar",
+            Self::Xoroshiro128PlusPlus => "xoroshiro128plusplus",
+            Self::SplitMix64 => "splitmix64",
+            Self::Pcg64Dxsm => "pcg64dxsm",
+            Self::WyRand => "wyrand",
+            Self::Sfc64 => "sfc64",
+            Self::Xorshift64StarX8 => "xorshift64star-x8",
+            Self::Xoshiro256StarStarX8 => "xoshiro256starstar-x8",
+            Self::ChaCha20 => "chacha20",
+            Self::ChaCha12 => "chacha12",
+            Self::ChaCha8 => "chacha8",
+            Self::Aes128Ctr => "aes128ctr",
+            Self::Aes256Ctr => "aes256ctr",
+            Self::HashDrbg => "hashdrbg",
+            Self::HmacDrbg => "hmacdrbg",
+            Self::CtrDrbg => "ctrdrbg",
+        }
+    }
+
+    /// Returns `true` if the algorithm is a cryptographically secure generator.
+    pub const fn is_secure(self) -> bool {
+        matches!(
+            self,
+            Self::ChaCha20 | Self::ChaCha12 | Self::ChaCha8 | Self::Aes128Ctr | Self::Aes256Ctr
+        ) || self.is_drbg()
+    }
+
+    /// Returns `true` if the algorithm is an SP 800-90A DRBG, which reseeds itself from the OS
+    /// instead of being replaced by a freshly seeded instance periodically.
+    pub const fn is_drbg(self) -> bool {
+        matches!(self, Self::HashDrbg | Self::HmacDrbg | Self::CtrDrbg)
+    }
+
+    /// Returns the number of seed bytes consumed by [`Algorithm::new_generator`].
+    pub const fn seed_len(self) -> usize {
+        match self {
+            Self::Xorshift64Star | Self::SplitMix64 | Self::WyRand => 8,
+            Self::Xoroshiro128PlusPlus | Self::Aes128Ctr => 16,
+            Self::Sfc64 => 24,
+            Self::Xoshiro256StarStar | Self::Pcg64Dxsm => 32,
+            // a seed of the scalar generator per lane
+            Self::Xorshift64StarX8 => 8 * LANES,
+            Self::Xoshiro256StarStarX8 => 32 * LANES,
+            Self::ChaCha20 | Self::ChaCha12 | Self::ChaCha8 | Self::Aes256Ctr => 32,
+            // entropy input and nonce, or entropy input of the seed length without df
+            Self::HashDrbg | Self::HmacDrbg | Self::CtrDrbg => 48,
+        }
+    }
+
+    /// Creates a generator from a seed of [`Algorithm::seed_len`] bytes, read as little-endian
+    /// words, or returns `None` if the seed is invalid for the algorithm (e.g. all zeros).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of `seed` is not [`Algorithm::seed_len`].
+    pub fn new_generator(self, seed: &[u8]) -> Option<Box<dyn Generator + Send>> {
+        self.new_generator_with(seed, &DrbgOptions::default())
+    }
+
+    /// Creates a generator like [`Algorithm::new_generator`], applying `options` if the algorithm
+    /// is a DRBG.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of `seed` is not [`Algorithm::seed_len`].
+    pub fn new_generator_with(
+        self,
+        seed: &[u8],
+        options: &DrbgOptions,
+    ) -> Option<Box<dyn Generator + Send>> {
+        assert_eq!(seed.len(), self.seed_len(), "invalid seed length");
+        let w = |i: usize| u64::from_le_bytes(seed[i * 8..(i + 1) * 8].try_into().unwrap());
+        Some(match self {
+            Self::Xorshift64Star => Box::new(Xorshift64Star::new(w(0))?),
+            Self::Xoshiro256StarStar => {
+                Box::new(Xoshiro256StarStar::new([w(0), w(1), w(2), w(3)])?)
+            }
+            Self::Xoroshiro128PlusPlus => Box::new(Xoroshiro128PlusPlus::new([w(0), w(1)])?),
+            Self::SplitMix64 => Box::new(SplitMix64::new(w(0))),
+            Self::Pcg64Dxsm => Box::new(Pcg64Dxsm::new(
+                u128::from(w(0)) | u128::from(w(1)) << 64,
+                u128::from(w(2)) | u128::from(w(3)) << 64,
+            )),
+            Self::WyRand => Box::new(WyRand::new(w(0))),
+            Self::Sfc64 => Box::new(Sfc64::new([w(0), w(1), w(2)])),
+            Self::Xorshift64StarX8 => Box::new(Xorshift64StarX8::new(std::array::from_fn(w))?),
+            Self::Xoshiro256StarStarX8 => {
+                Box::new(Xoshiro256StarStarX8::new(std::array::from_fn(|j| {
+                    std::array::from_fn(|i| w(4 * j + i))
+                }))?)
+            }
+            Self::ChaCha20 => Box::new(ChaCha20::new(seed.try_into().unwrap())),
+            Self::ChaCha12 => Box::new(ChaCha12::new(seed.try_into().unwrap())),
+            Self::ChaCha8 => Box::new(ChaCha8::new(seed.try_into().unwrap())),
+            Self::Aes128Ctr => Box::new(Aes128Ctr::new(seed.try_into().unwrap())),
+            Self::Aes256Ctr => Box::new(Aes256Ctr::new(seed.try_into().unwrap())),
+            Self::HashDrbg => Box::new(DrbgGenerator::new(
+                HashDrbg::new(&seed[..32], &seed[32..], &[])
+                    .with_reseed_interval(options.reseed_interval),
+                options.prediction_resistance,
+            )),
+            Self::HmacDrbg => Box::new(DrbgGenerator::new(
+                HmacDrbg::new(&seed[..32], &seed[32..], &[])
+                    .with_reseed_interval(options.reseed_interval),
+                options.prediction_resistance,
+            )),
+            Self::CtrDrbg => Box::new(DrbgGenerator::new(
+                CtrDrbg::new(seed, &[]).with_reseed_interval(options.reseed_interval),
+                options.prediction_resistance,
+            )),
+        })
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Error returned when parsing an unknown algorithm name.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseAlgorithmError(String);
+
+impl fmt::Display for ParseAlgorithmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown algorithm '{}'", self.0)
+    }
+}
+
+impl std::error::Error for ParseAlgorithmError {}
+
+impl FromStr for Algorithm {
+    type Err = ParseAlgorithmError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|a| a.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| ParseAlgorithmError(s.to_owned()))
+    }
+}
+
+/// Implements [`rand_core::RngCore`] through [`Generator::next_u64`].
+macro_rules! impl_rng_core {
+    ($($t:ty),*) => {$(
+        #[cfg(feature = "rand_core")]
+        impl rand_core::RngCore for $t {
+            fn next_u32(&mut self) -> u32 {
+                // the upper bits are of higher quality than the lower bits in some generators
+                (Generator::next_u64(self) >> 32) as u32
+            }
+
+            fn next_u64(&mut self) -> u64 {
+                Generator::next_u64(self)
+            }
+
+            fn fill_bytes(&mut self, dst: &mut [u8]) {
+                rand_core::impls::fill_bytes_via_next(self, dst)
+            }
+        }
+    )*};
+}
+
+impl_rng_core!(
+    Aes128Ctr,
+    Aes256Ctr,
+    ChaCha12,
+    ChaCha20,
+    ChaCha8,
+    Pcg64Dxsm,
+    Sfc64,
+    SplitMix64,
+    WyRand,
+    Xoroshiro128PlusPlus,
+    Xorshift64Star,
+    Xorshift64StarX8,
+    Xoshiro256StarStar,
+    Xoshiro256StarStarX8
+);
+
+#[cfg(test)]
+mod tests {
+    use super::Algorithm;
+
+    #[test]
+    fn algorithm_names() {
+        for a in Algorithm::ALL {
+            assert_eq!(a.name().parse(), Ok(a));
+            assert_eq!(a.to_string().to_uppercase().parse(), Ok(a));
+            assert!(a.new_generator(&vec![1; a.seed_len()]).is_some(), "{}", a);
+        }
+        assert!("xorshift".parse::<Algorithm>().is_err());
+        assert_eq!(Algorithm::default(), Algorithm::Xorshift64Star);
+        assert!(Algorithm::Xorshift64Star.new_generator(&[0; 8]).is_none());
+    }
+}
@@ -0,0 +1,171 @@
+use std::{fmt, mem};
+
+use aes::cipher::{consts::U16, generic_array::GenericArray, BlockEncrypt, BlockSizeUser, KeyInit};
+use zeroize::Zeroize as _;
+
+use super::Generator;
+
+/// Number of blocks encrypted at a time to let the cipher process them in parallel.
+const CHUNK_BLOCKS: usize = 64;
+
+/// AES in counter mode with a 128-bit big-endian counter starting at zero.
+struct Ctr<C> {
+    cipher: C,
+    counter: u128,
+    /// Second half of the last block when an odd number of words has been requested.
+    spare: Option<u64>,
+}
+
+impl<C: BlockEncrypt + BlockSizeUser<BlockSize = U16>> Ctr<C> {
+    fn new(cipher: C) -> Self {
+        Self {
+            cipher,
+            counter: 0,
+            spare: None,
+        }
+    }
+
+    fn fill(&mut self, mut buf: &mut [u64]) {
+        if buf.is_empty() {
+            return;
+        }
+        if let Some(spare) = self.spare.take() {
+            let (head, rest) = mem::take(&mut buf).split_first_mut().unwrap();
+            *head = spare;
+            buf = rest;
+        }
+
+        let mut blocks = [GenericArray::default(); CHUNK_BLOCKS];
+        let mut chunks = buf.chunks_exact_mut(CHUNK_BLOCKS * 2);
+        for chunk in &mut chunks {
+            self.encrypt_counters(&mut blocks);
+            for (pair, block) in chunk.chunks_exact_mut(2).zip(blocks.iter()) {
+                pair[0] = u64::from_le_bytes(block[..8].try_into().unwrap());
+                pair[1] = u64::from_le_bytes(block[8..].try_into().unwrap());
+            }
+        }
+
+        let rest = chunks.into_remainder();
+        if !rest.is_empty() {
+            let n_blocks = rest.len().div_ceil(2);
+            let blocks = &mut blocks[..n_blocks];
+            self.encrypt_counters(blocks);
+            let mut words = blocks.iter().flat_map(|block| {
+                [
+                    u64::from_le_bytes(block[..8].try_into().unwrap()),
+                    u64::from_le_bytes(block[8..].try_into().unwrap()),
+                ]
+            });
+            for e in rest.iter_mut() {
+                *e = words.next().unwrap();
+            }
+            self.spare = words.next();
+        }
+        // do not leave keystream on the stack
+        for block in &mut blocks {
+            block.as_mut_slice().zeroize();
+        }
+    }
+
+    fn encrypt_counters(&mut self, blocks: &mut [aes::Block]) {
+        for block in blocks.iter_mut() {
+            block.copy_from_slice(&self.counter.to_be_bytes());
+            self.counter = self.counter.wrapping_add(1);
+        }
+        self.cipher.encrypt_blocks(blocks);
+    }
+}
+
+impl<C> Drop for Ctr<C> {
+    fn drop(&mut self) {
+        self.spare.zeroize();
+    }
+}
+
+macro_rules! define_aes_ctr {
+    ($(#[$attr:meta])* $name:ident, $cipher:ty, $key_len:literal) => {
+        $(#[$attr])*
+        ///
+        /// Encrypts a 128-bit big-endian counter starting at zero, and reads words from the
+        /// keystream in little-endian order. AES-NI (or the ARMv8 crypto extension) is used when
+        /// detected at runtime; otherwise a constant-time bitsliced software implementation is
+        /// used. The round keys and unread keystream are wiped from memory on drop.
+        pub struct $name(Ctr<$cipher>);
+
+        impl $name {
+            #[doc = concat!("Creates a generator from a ", $key_len, "-byte key.")]
+            pub fn new(key: [u8; $key_len]) -> Self {
+                Self(Ctr::new(<$cipher>::new(&key.into())))
+            }
+        }
+
+        impl Generator for $name {
+            fn next_u64(&mut self) -> u64 {
+                let mut buf = [0u64; 1];
+                self.0.fill(&mut buf);
+                let word = buf[0];
+                buf.zeroize();
+                word
+            }
+
+            fn fill(&mut self, buf: &mut [u64]) {
+                self.0.fill(buf)
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_struct(stringify!($name)).finish_non_exhaustive()
+            }
+        }
+    };
+}
+
+define_aes_ctr!(
+    /// AES-128 in counter mode used as a CSPRNG.
+    Aes128Ctr,
+    aes::Aes128,
+    16
+);
+
+define_aes_ctr!(
+    /// AES-256 in counter mode used as a CSPRNG.
+    Aes256Ctr,
+    aes::Aes256,
+    32
+);
+
+#[cfg(test)]
+mod tests {
+    use super::{Aes128Ctr, Aes256Ctr, Generator as _};
+
+    #[test]
+    fn known_answer() {
+        // AES of the all-zero block under the all-zero key
+        let mut g = Aes128Ctr::new([0; 16]);
+        let expected = 0x66e94bd4ef8a2c3b884cfa59ca342b2eu128.to_be_bytes();
+        assert_eq!(g.next_u64().to_le_bytes(), expected[..8]);
+        assert_eq!(g.next_u64().to_le_bytes(), expected[8..]);
+
+        let mut g = Aes256Ctr::new([0; 32]);
+        let expected = 0xdc95c078a2408989ad48a21492842087u128.to_be_bytes();
+        assert_eq!(g.next_u64().to_le_bytes(), expected[..8]);
+        assert_eq!(g.next_u64().to_le_bytes(), expected[8..]);
+    }
+
+    #[test]
+    fn split_fills() {
+        let mut whole = [0u64; 301];
+        Aes128Ctr::new([3; 16]).fill(&mut whole);
+
+        let mut parts = [0u64; 301];
+        let mut g = Aes128Ctr::new([3; 16]);
+        let (a, rest) = parts.split_at_mut(1);
+        let (b, rest) = rest.split_at_mut(131);
+        let (c, d) = rest.split_at_mut(128);
+        for part in [a, b, c, d] {
+            g.fill(part);
+        }
+        assert_eq!(parts, whole);
+    }
+}
@@ -0,0 +1,169 @@
+use std::{cmp, fmt};
+
+use chacha20::cipher::{KeyIvInit, StreamCipher};
+use zerocopy::AsBytes as _;
+use zeroize::{Zeroize as _, Zeroizing};
+
+use super::Generator;
+
+/// Number of keystream bytes available per nonce with the 32-bit block counter.
+const BYTES_PER_NONCE: u64 = 64 << 32;
+
+/// ChaCha keystream with a 96-bit nonce that is incremented whenever the block counter is
+/// exhausted, so that a key can produce practically unlimited output.
+struct Keystream<C> {
+    key: Zeroizing<[u8; 32]>,
+    nonce: u64,
+    cipher: C,
+    left: u64,
+}
+
+impl<C: KeyIvInit + StreamCipher> Keystream<C> {
+    fn new(key: [u8; 32]) -> Self {
+        let key = Zeroizing::new(key);
+        let cipher = Self::new_cipher(&key, 0);
+        Self {
+            key,
+            nonce: 0,
+            cipher,
+            left: BYTES_PER_NONCE,
+        }
+    }
+
+    fn new_cipher(key: &[u8; 32], nonce: u64) -> C {
+        let mut iv = [0u8; 12];
+        iv[..8].copy_from_slice(&nonce.to_le_bytes());
+        C::new_from_slices(key, &iv).expect("key and nonce must have valid lengths")
+    }
+
+    fn fill(&mut self, buf: &mut [u64]) {
+        buf.fill(0);
+        let mut bytes = buf.as_bytes_mut();
+        while !bytes.is_empty() {
+            if self.left == 0 {
+                self.nonce += 1;
+                self.cipher = Self::new_cipher(&self.key, self.nonce);
+                self.left = BYTES_PER_NONCE;
+            }
+
+            let n = cmp::min(self.left, bytes.len() as u64) as usize;
+            self.cipher.apply_keystream(&mut bytes[..n]);
+            self.left -= n as u64;
+            bytes = &mut bytes[n..];
+        }
+    }
+}
+
+macro_rules! define_chacha {
+    ($(#[$attr:meta])* $name:ident, $cipher:ty) => {
+        $(#[$attr])*
+        ///
+        /// Keyed by a 256-bit seed; the nonce starts at zero and is incremented every 256 GiB of
+        /// output. Words are read from the keystream in little-endian order. The key and the
+        /// cipher state are wiped from memory on drop and when the nonce is incremented.
+        pub struct $name(Keystream<$cipher>);
+
+        impl $name {
+            /// Creates a generator from a 256-bit key.
+            pub fn new(key: [u8; 32]) -> Self {
+                Self(Keystream::new(key))
+            }
+        }
+
+        impl Generator for $name {
+            fn next_u64(&mut self) -> u64 {
+                let mut buf = [0u64; 1];
+                self.0.fill(&mut buf);
+                let word = u64::from_le(buf[0]);
+                buf.zeroize();
+                word
+            }
+
+            fn fill(&mut self, buf: &mut [u64]) {
+                self.0.fill(buf);
+                if cfg!(target_endian = "big") {
+                    for e in buf.iter_mut() {
+                        *e = u64::from_le(*e);
+                    }
+                }
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_struct(stringify!($name)).finish_non_exhaustive()
+            }
+        }
+    };
+}
+
+define_chacha!(
+    /// ChaCha20 stream cipher (Bernstein 2008; RFC 8439) used as a CSPRNG.
+    ChaCha20,
+    chacha20::ChaCha20
+);
+
+define_chacha!(
+    /// ChaCha stream cipher reduced to 12 rounds used as a CSPRNG.
+    ChaCha12,
+    chacha20::ChaCha12
+);
+
+define_chacha!(
+    /// ChaCha stream cipher reduced to 8 rounds used as a CSPRNG.
+    ChaCha8,
+    chacha20::ChaCha8
+);
+
+#[cfg(test)]
+mod tests {
+    use zerocopy::AsBytes as _;
+
+    use super::{ChaCha12, ChaCha20, ChaCha8, Generator as _};
+
+    #[test]
+    fn known_answer() {
+        // RFC 8439 A.1 test vector #1
+        const EXPECTED: [u8; 64] = [
+            0x76, 0xb8, 0xe0, 0xad, 0xa0, 0xf1, 0x3d, 0x90, 0x40, 0x5d, 0x6a, 0xe5, 0x53, 0x86,
+            0xbd, 0x28, 0xbd, 0xd2, 0x19, 0xb8, 0xa0, 0x8d, 0xed, 0x1a, 0xa8, 0x36, 0xef, 0xcc,
+            0x8b, 0x77, 0x0d, 0xc7, 0xda, 0x41, 0x59, 0x7c, 0x51, 0x57, 0x48, 0x8d, 0x77, 0x24,
+            0xe0, 0x3f, 0xb8, 0xd8, 0x4a, 0x37, 0x6a, 0x43, 0xb8, 0xf4, 0x15, 0x18, 0xa1, 0x1c,
+            0xc3, 0x87, 0xb6, 0x69, 0xb2, 0xee, 0x65, 0x86,
+        ];
+
+        let mut buf = [0u64; 8];
+        ChaCha20::new([0; 32]).fill(&mut buf);
+        assert_eq!(buf.map(u64::to_le).as_bytes(), EXPECTED);
+
+        let mut g = ChaCha20::new([0; 32]);
+        assert_eq!(
+            g.next_u64(),
+            u64::from_le_bytes(EXPECTED[..8].try_into().unwrap())
+        );
+        assert_eq!(
+            g.next_u64(),
+            u64::from_le_bytes(EXPECTED[8..16].try_into().unwrap())
+        );
+
+        let (mut g8, mut g12) = (ChaCha8::new([0; 32]), ChaCha12::new([0; 32]));
+        let (x8, x12, x20) = (g8.next_u64(), g12.next_u64(), buf[0]);
+        assert!(x8 != x12 && x12 != x20 && x20 != x8);
+    }
+
+    #[test]
+    fn nonce_rollover() {
+        let mut g = ChaCha20::new([7; 32]);
+        g.0.left = 16;
+        let mut buf = [0u64; 6];
+        g.fill(&mut buf);
+
+        let mut expected = [0u64; 4];
+        let mut h = ChaCha20::new([7; 32]);
+        h.0.nonce = 1;
+        h.0.cipher = super::Keystream::new_cipher(&h.0.key, 1);
+        h.fill(&mut expected);
+        assert_eq!(buf[2..], expected);
+        assert_eq!(g.0.nonce, 1);
+    }
+}
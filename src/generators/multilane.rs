@@ -0,0 +1,265 @@
+//! Generators that step several independent states at once so that the compiler can keep them
+//! in vector registers.
+//!
+//! The kernels are compiled for AVX-512 and AVX2 and selected at runtime on x86-64, falling back
+//! to the baseline SSE2. Other architectures get only the portable kernel, vectorized as far as
+//! the compiler manages for their baseline target features. The output does not depend on the
+//! kernel.
+
+use super::Generator;
+
+/// Number of lanes of the multi-lane generators.
+pub const LANES: usize = 8;
+
+/// Defines `$name`, which runs `$kernel` with the best instruction set of the CPU.
+macro_rules! dispatch {
+    ($name:ident, $kernel:ident, $state:ty) => {
+        fn $name(state: &mut $state, out: &mut [u64]) {
+            #[cfg(target_arch = "x86_64")]
+            {
+                #[target_feature(enable = "avx512f,avx512dq")]
+                unsafe fn avx512(state: &mut $state, out: &mut [u64]) {
+                    $kernel(state, out)
+                }
+
+                #[target_feature(enable = "avx2")]
+                unsafe fn avx2(state: &mut $state, out: &mut [u64]) {
+                    $kernel(state, out)
+                }
+
+                if is_x86_feature_detected!("avx512f") && is_x86_feature_detected!("avx512dq") {
+                    // SAFETY: the CPU supports the enabled features
+                    return unsafe { avx512(state, out) };
+                }
+                if is_x86_feature_detected!("avx2") {
+                    // SAFETY: the CPU supports the enabled features
+                    return unsafe { avx2(state, out) };
+                }
+            }
+            $kernel(state, out)
+        }
+    };
+}
+
+/// Outputs of the last step that have not been returned yet.
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Spare {
+    words: [u64; LANES],
+    pos: usize,
+}
+
+impl Spare {
+    const fn new() -> Self {
+        Self {
+            words: [0; LANES],
+            pos: LANES,
+        }
+    }
+
+    /// Fills `buf` from whole steps of `step`, starting with the spare words and keeping the
+    /// unused words of the last step.
+    fn fill<S>(&mut self, state: &mut S, buf: &mut [u64], step: fn(&mut S, &mut [u64])) {
+        let n = buf.len().min(LANES - self.pos);
+        let (head, buf) = buf.split_at_mut(n);
+        head.copy_from_slice(&self.words[self.pos..self.pos + n]);
+        self.pos += n;
+
+        let whole = buf.len() - buf.len() % LANES;
+        let (body, tail) = buf.split_at_mut(whole);
+        step(state, body);
+        if !tail.is_empty() {
+            step(state, &mut self.words);
+            tail.copy_from_slice(&self.words[..tail.len()]);
+            self.pos = tail.len();
+        }
+    }
+
+    fn next<S>(&mut self, state: &mut S, step: fn(&mut S, &mut [u64])) -> u64 {
+        if self.pos == LANES {
+            step(state, &mut self.words);
+            self.pos = 0;
+        }
+        self.pos += 1;
+        self.words[self.pos - 1]
+    }
+}
+
+/// [`Xorshift64Star`](super::Xorshift64Star) with eight interleaved lanes.
+///
+/// Every lane is an independent xorshift64* generator, and word `8 * k + j` of the output is the
+/// `k`-th output of lane `j`. The state of every lane must be nonzero.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Xorshift64StarX8 {
+    s: [u64; LANES],
+    spare: Spare,
+}
+
+impl Xorshift64StarX8 {
+    /// Creates a generator from the seeds of the lanes, or returns `None` if any seed is zero.
+    pub fn new(seeds: [u64; LANES]) -> Option<Self> {
+        if seeds.contains(&0) {
+            None
+        } else {
+            Some(Self {
+                s: seeds,
+                spare: Spare::new(),
+            })
+        }
+    }
+}
+
+#[inline(always)]
+fn xorshift64star_kernel(state: &mut [u64; LANES], out: &mut [u64]) {
+    let mut s = *state;
+    for chunk in out.chunks_exact_mut(LANES) {
+        for (x, e) in s.iter_mut().zip(chunk) {
+            *x ^= *x >> 12;
+            *x ^= *x << 25;
+            *x ^= *x >> 27;
+            *e = x.wrapping_mul(2685821657736338717);
+        }
+    }
+    *state = s;
+}
+
+dispatch!(xorshift64star_step, xorshift64star_kernel, [u64; LANES]);
+
+impl Generator for Xorshift64StarX8 {
+    fn next_u64(&mut self) -> u64 {
+        self.spare.next(&mut self.s, xorshift64star_step)
+    }
+
+    fn fill(&mut self, buf: &mut [u64]) {
+        self.spare.fill(&mut self.s, buf, xorshift64star_step)
+    }
+}
+
+/// [`Xoshiro256StarStar`](super::Xoshiro256StarStar) with eight interleaved lanes.
+///
+/// Every lane is an independent xoshiro256** generator, and word `8 * k + j` of the output is
+/// the `k`-th output of lane `j`. The state of every lane must not be all zeros.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Xoshiro256StarStarX8 {
+    /// The `i`-th state word of every lane.
+    s: [[u64; LANES]; 4],
+    spare: Spare,
+}
+
+impl Xoshiro256StarStarX8 {
+    /// Creates a generator from the seeds of the lanes, or returns `None` if any seed is all
+    /// zeros.
+    pub fn new(seeds: [[u64; 4]; LANES]) -> Option<Self> {
+        if seeds.contains(&[0; 4]) {
+            return None;
+        }
+        let s = std::array::from_fn(|i| std::array::from_fn(|j| seeds[j][i]));
+        Some(Self {
+            s,
+            spare: Spare::new(),
+        })
+    }
+}
+
+#[inline(always)]
+fn xoshiro256starstar_kernel(state: &mut [[u64; LANES]; 4], out: &mut [u64]) {
+    let [mut s0, mut s1, mut s2, mut s3] = *state;
+    for chunk in out.chunks_exact_mut(LANES) {
+        for j in 0..LANES {
+            chunk[j] = s1[j].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+            let t = s1[j] << 17;
+            s2[j] ^= s0[j];
+            s3[j] ^= s1[j];
+            s1[j] ^= s2[j];
+            s0[j] ^= s3[j];
+            s2[j] ^= t;
+            s3[j] = s3[j].rotate_left(45);
+        }
+    }
+    *state = [s0, s1, s2, s3];
+}
+
+dispatch!(
+    xoshiro256starstar_step,
+    xoshiro256starstar_kernel,
+    [[u64; LANES]; 4]
+);
+
+impl Generator for Xoshiro256StarStarX8 {
+    fn next_u64(&mut self) -> u64 {
+        self.spare.next(&mut self.s, xoshiro256starstar_step)
+    }
+
+    fn fill(&mut self, buf: &mut [u64]) {
+        self.spare.fill(&mut self.s, buf, xoshiro256starstar_step)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Generator, Xorshift64StarX8, Xoshiro256StarStarX8, LANES};
+    use crate::generators::{Xorshift64Star, Xoshiro256StarStar};
+
+    /// Checks that `g` interleaves the outputs of `lanes` through every way of reading them.
+    fn assert_interleaves(mut g: impl Generator + Clone, mut lanes: Vec<impl Generator>) {
+        let expected: Vec<u64> = (0..1000).map(|i| lanes[i % LANES].next_u64()).collect();
+
+        let mut buf = vec![0; expected.len()];
+        g.clone().fill(&mut buf);
+        assert_eq!(buf, expected);
+
+        // odd sizes leave spare words of a step
+        buf.fill(0);
+        let mut pos = 0;
+        for (i, n) in [3, 1, 0, 13, 8, 2, 100, 5].into_iter().cycle().enumerate() {
+            if pos + n > buf.len() {
+                break;
+            }
+            if i % 3 == 0 && n > 0 {
+                buf[pos] = g.next_u64();
+                pos += 1;
+            } else {
+                g.fill(&mut buf[pos..pos + n]);
+                pos += n;
+            }
+        }
+        assert_eq!(buf[..pos], expected[..pos]);
+    }
+
+    #[test]
+    fn kernels_agree() {
+        let mut a = std::array::from_fn(|j| j as u64 + 1);
+        let mut b = a;
+        let (mut x, mut y) = ([0; 10 * LANES], [0; 10 * LANES]);
+        super::xorshift64star_kernel(&mut a, &mut x);
+        super::xorshift64star_step(&mut b, &mut y);
+        assert_eq!((a, x), (b, y));
+
+        let mut a = [[1; LANES], [2; LANES], [3; LANES], [4; LANES]];
+        let mut b = a;
+        super::xoshiro256starstar_kernel(&mut a, &mut x);
+        super::xoshiro256starstar_step(&mut b, &mut y);
+        assert_eq!((a, x), (b, y));
+    }
+
+    #[test]
+    fn xorshift64star_lanes() {
+        let seeds = std::array::from_fn(|j| j as u64 + 1);
+        let lanes = seeds.map(|s| Xorshift64Star::new(s).unwrap());
+        assert_interleaves(Xorshift64StarX8::new(seeds).unwrap(), lanes.to_vec());
+
+        let mut seeds = seeds;
+        seeds[5] = 0;
+        assert!(Xorshift64StarX8::new(seeds).is_none());
+    }
+
+    #[test]
+    fn xoshiro256starstar_lanes() {
+        let seeds = std::array::from_fn(|j| [1, 2, 3, 4].map(|i| i * 10 + j as u64));
+        let lanes = seeds.map(|s| Xoshiro256StarStar::new(s).unwrap());
+        assert_interleaves(Xoshiro256StarStarX8::new(seeds).unwrap(), lanes.to_vec());
+
+        let mut seeds = seeds;
+        seeds[7] = [0; 4];
+        assert!(Xoshiro256StarStarX8::new(seeds).is_none());
+    }
+}
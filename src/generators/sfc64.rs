@@ -0,0 +1,59 @@
+use super::Generator;
+
+/// SFC64 "small fast chaotic" generator (Doty-Humphrey, from PractRand).
+///
+/// Any seed is valid; the built-in counter guarantees a minimum period of 2^64.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Sfc64 {
+    a: u64,
+    b: u64,
+    c: u64,
+    counter: u64,
+}
+
+impl Sfc64 {
+    /// Creates a generator from a seed, discarding the first outputs as PractRand does.
+    pub fn new(seed: [u64; 3]) -> Self {
+        let mut g = Self::from_state(seed);
+        for _ in 0..12 {
+            g.next_u64();
+        }
+        g
+    }
+
+    /// Creates a generator from a raw state without discarding any output.
+    pub const fn from_state([a, b, c]: [u64; 3]) -> Self {
+        Self {
+            a,
+            b,
+            c,
+            counter: 1,
+        }
+    }
+}
+
+impl Generator for Sfc64 {
+    #[inline]
+    fn next_u64(&mut self) -> u64 {
+        let result = self.a.wrapping_add(self.b).wrapping_add(self.counter);
+        self.counter = self.counter.wrapping_add(1);
+        self.a = self.b ^ (self.b >> 11);
+        self.b = self.c.wrapping_add(self.c << 3);
+        self.c = self.c.rotate_left(24).wrapping_add(result);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Generator as _, Sfc64};
+
+    #[test]
+    fn known_answer() {
+        let mut g = Sfc64::from_state([1, 2, 3]);
+        assert_eq!(
+            [g.next_u64(), g.next_u64(), g.next_u64()],
+            [4, 31, 452984898]
+        );
+    }
+}
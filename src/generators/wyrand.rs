@@ -0,0 +1,43 @@
+use super::Generator;
+
+/// WyRand generator (Wang Yi 2019).
+///
+/// Any seed is valid.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct WyRand {
+    state: u64,
+}
+
+impl WyRand {
+    /// Creates a generator from a seed.
+    pub const fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+}
+
+impl Generator for WyRand {
+    #[inline]
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0xa0761d6478bd642f);
+        let t = u128::from(self.state) * u128::from(self.state ^ 0xe7037ed1a0b428db);
+        (t >> 64) as u64 ^ t as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Generator as _, WyRand};
+
+    #[test]
+    fn known_answer() {
+        let mut g = WyRand::new(0);
+        assert_eq!(
+            [g.next_u64(), g.next_u64(), g.next_u64()],
+            [
+                1233057930238600590,
+                14892235431655409005,
+                7060326114132480676
+            ]
+        );
+    }
+}
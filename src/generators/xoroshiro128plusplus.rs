@@ -0,0 +1,47 @@
+use super::Generator;
+
+/// xoroshiro128++ generator (Blackman and Vigna 2019).
+///
+/// The state must not be all zeros.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Xoroshiro128PlusPlus {
+    s: [u64; 2],
+}
+
+impl Xoroshiro128PlusPlus {
+    /// Creates a generator from a seed, or returns `None` if the seed is all zeros.
+    pub const fn new(seed: [u64; 2]) -> Option<Self> {
+        if seed[0] | seed[1] == 0 {
+            None
+        } else {
+            Some(Self { s: seed })
+        }
+    }
+}
+
+impl Generator for Xoroshiro128PlusPlus {
+    #[inline]
+    fn next_u64(&mut self) -> u64 {
+        let [s0, mut s1] = self.s;
+        let result = s0.wrapping_add(s1).rotate_left(17).wrapping_add(s0);
+        s1 ^= s0;
+        self.s = [s0.rotate_left(49) ^ s1 ^ (s1 << 21), s1.rotate_left(28)];
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Generator as _, Xoroshiro128PlusPlus};
+
+    #[test]
+    fn known_answer() {
+        assert!(Xoroshiro128PlusPlus::new([0; 2]).is_none());
+
+        let mut g = Xoroshiro128PlusPlus::new([1, 2]).unwrap();
+        assert_eq!(
+            [g.next_u64(), g.next_u64(), g.next_u64()],
+            [393217, 669327710093319, 1732421326133921491]
+        );
+    }
+}
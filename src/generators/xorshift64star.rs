@@ -0,0 +1,110 @@
+use super::Generator;
+
+/// xorshift64* generator (Vigna 2016).
+///
+/// The state must be nonzero because a zero state produces only zeros.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Xorshift64Star {
+    state: u64,
+}
+
+impl Xorshift64Star {
+    /// Creates a generator from a seed, or returns `None` if the seed is zero.
+    pub const fn new(seed: u64) -> Option<Self> {
+        if seed == 0 {
+            None
+        } else {
+            Some(Self { state: seed })
+        }
+    }
+}
+
+impl Generator for Xorshift64Star {
+    #[inline]
+    fn next_u64(&mut self) -> u64 {
+        let mut s = self.state;
+        s ^= s >> 12;
+        s ^= s << 25;
+        s ^= s >> 27;
+        self.state = s;
+        s.wrapping_mul(2685821657736338717)
+    }
+
+    fn fill(&mut self, buf: &mut [u64]) {
+        let mut s = self.state;
+        for e in buf.iter_mut() {
+            s ^= s >> 12;
+            s ^= s << 25;
+            s ^= s >> 27;
+            *e = s.wrapping_mul(2685821657736338717);
+        }
+        self.state = s;
+    }
+}
+
+#[cfg(feature = "rand_core")]
+impl rand_core::SeedableRng for Xorshift64Star {
+    type Seed = [u8; 8];
+
+    /// Creates a generator from a little-endian seed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the seed is all zeros; use [`Xorshift64Star::new`] to handle it gracefully.
+    fn from_seed(seed: Self::Seed) -> Self {
+        Self::new(u64::from_le_bytes(seed)).expect("xorshift64* seed must not be all zeros")
+    }
+
+    /// Creates a generator from a `u64` scrambled by SplitMix64, skipping the zero state.
+    fn seed_from_u64(state: u64) -> Self {
+        let mut sm = super::SplitMix64::new(state);
+        loop {
+            if let Some(g) = Self::new(sm.next_u64()) {
+                return g;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Generator as _, Xorshift64Star};
+
+    const EXPECTED: [u64; 3] = [
+        5180492295206395165,
+        12380297144915551517,
+        13389498078930870103,
+    ];
+
+    #[test]
+    fn known_answer() {
+        assert!(Xorshift64Star::new(0).is_none());
+
+        let mut g = Xorshift64Star::new(1).unwrap();
+        assert_eq!([g.next_u64(), g.next_u64(), g.next_u64()], EXPECTED);
+
+        let mut buf = [0u64; 3];
+        Xorshift64Star::new(1).unwrap().fill(&mut buf);
+        assert_eq!(buf, EXPECTED);
+    }
+
+    #[cfg(feature = "rand_core")]
+    #[test]
+    fn rand_core_traits() {
+        use rand_core::{RngCore as _, SeedableRng as _};
+
+        let mut g = Xorshift64Star::from_seed(1u64.to_le_bytes());
+        assert_eq!(g.next_u32(), (EXPECTED[0] >> 32) as u32);
+
+        let mut bytes = [0u8; 12];
+        g.fill_bytes(&mut bytes);
+        assert_eq!(bytes[..8], EXPECTED[1].to_le_bytes());
+        assert_eq!(bytes[8..], ((EXPECTED[2] >> 32) as u32).to_le_bytes());
+
+        assert!(std::panic::catch_unwind(|| Xorshift64Star::from_seed([0; 8])).is_err());
+        assert_ne!(
+            Xorshift64Star::seed_from_u64(0),
+            Xorshift64Star::seed_from_u64(1)
+        );
+    }
+}
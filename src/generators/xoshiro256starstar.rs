@@ -0,0 +1,52 @@
+use super::Generator;
+
+/// xoshiro256** generator (Blackman and Vigna 2018).
+///
+/// The state must not be all zeros.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Xoshiro256StarStar {
+    s: [u64; 4],
+}
+
+impl Xoshiro256StarStar {
+    /// Creates a generator from a seed, or returns `None` if the seed is all zeros.
+    pub const fn new(seed: [u64; 4]) -> Option<Self> {
+        if seed[0] | seed[1] | seed[2] | seed[3] == 0 {
+            None
+        } else {
+            Some(Self { s: seed })
+        }
+    }
+}
+
+impl Generator for Xoshiro256StarStar {
+    #[inline]
+    fn next_u64(&mut self) -> u64 {
+        let s = &mut self.s;
+        let result = s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = s[1] << 17;
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Generator as _, Xoshiro256StarStar};
+
+    #[test]
+    fn known_answer() {
+        assert!(Xoshiro256StarStar::new([0; 4]).is_none());
+
+        let mut g = Xoshiro256StarStar::new([1, 2, 3, 4]).unwrap();
+        assert_eq!(
+            [g.next_u64(), g.next_u64(), g.next_u64()],
+            [11520, 0, 1509978240]
+        );
+    }
+}
@@ -0,0 +1,349 @@
+//! Random identifiers: UUIDs, ULIDs, nanoids and Snowflake-like IDs.
+
+use std::{
+    fmt, io,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{range::lemire_u64, RandomStream};
+
+/// Kind of identifier selectable at runtime.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum IdKind {
+    /// RFC 9562 UUID version 4, 122 random bits.
+    #[default]
+    UuidV4,
+    /// RFC 9562 UUID version 7, a millisecond timestamp followed by a 42-bit counter that starts
+    /// at a random value every millisecond and 32 random bits.
+    UuidV7,
+    /// ULID, a millisecond timestamp and 80 random bits incremented within a millisecond, in
+    /// Crockford's Base32.
+    Ulid,
+    /// Nanoid, random characters of an alphabet (by default 21 of `A-Za-z0-9_-`).
+    Nanoid,
+    /// Snowflake-like ID, a decimal `u64` of a 41-bit millisecond timestamp since
+    /// [`SNOWFLAKE_EPOCH`], a 10-bit worker ID (random unless given) and a 12-bit sequence.
+    Snowflake,
+}
+
+impl IdKind {
+    /// All kinds in the order of listing.
+    pub const ALL: [Self; 5] = [
+        Self::UuidV4,
+        Self::UuidV7,
+        Self::Ulid,
+        Self::Nanoid,
+        Self::Snowflake,
+    ];
+
+    /// Returns the name used to select the kind on the command line.
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::UuidV4 => "uuid4",
+            Self::UuidV7 => "uuid7",
+            Self::Ulid => "ulid",
+            Self::Nanoid => "nanoid",
+            Self::Snowflake => "snowflake",
+        }
+    }
+}
+
+impl fmt::Display for IdKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Error returned when parsing an unknown identifier kind.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseIdKindError(String);
+
+impl fmt::Display for ParseIdKindError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown ID kind '{}'", self.0)
+    }
+}
+
+impl std::error::Error for ParseIdKindError {}
+
+impl FromStr for IdKind {
+    type Err = ParseIdKindError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|k| k.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| ParseIdKindError(s.to_owned()))
+    }
+}
+
+/// Default alphabet of nanoids.
+pub const NANOID_ALPHABET: &str =
+    "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789_-";
+
+/// Default length of nanoids.
+pub const NANOID_LEN: usize = 21;
+
+/// Start of the timestamps of Snowflake-like IDs in milliseconds since the Unix epoch
+/// (2010-11-04T01:42:54.657Z, as used by Twitter).
+pub const SNOWFLAKE_EPOCH: u64 = 1_288_834_974_657;
+
+/// Largest worker ID of Snowflake-like IDs.
+pub const MAX_WORKER: u16 = (1 << 10) - 1;
+
+const CROCKFORD: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/// Generator of identifiers of one kind, monotonic within the generator for the time-based
+/// kinds even if the clock goes backwards.
+#[derive(Clone, Debug)]
+pub struct IdGenerator {
+    kind: IdKind,
+    alphabet: Vec<char>,
+    len: usize,
+    worker: Option<u16>,
+    /// Timestamp of the last ID in milliseconds since the Unix epoch, and its counter or random
+    /// part that is incremented within a millisecond.
+    last: Option<(u64, u128)>,
+}
+
+impl IdGenerator {
+    /// Creates a generator of IDs of `kind`.
+    pub fn new(kind: IdKind) -> Self {
+        Self {
+            kind,
+            alphabet: NANOID_ALPHABET.chars().collect(),
+            len: NANOID_LEN,
+            worker: None,
+            last: None,
+        }
+    }
+
+    /// Sets the alphabet of nanoids.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `alphabet` is empty.
+    pub fn alphabet(mut self, alphabet: &str) -> Self {
+        assert!(!alphabet.is_empty(), "empty alphabet");
+        self.alphabet = alphabet.chars().collect();
+        self
+    }
+
+    /// Sets the length of nanoids.
+    pub fn length(mut self, len: usize) -> Self {
+        self.len = len;
+        self
+    }
+
+    /// Sets the worker ID of Snowflake-like IDs instead of drawing a random one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `worker` is greater than [`MAX_WORKER`].
+    pub fn worker(mut self, worker: u16) -> Self {
+        assert!(worker <= MAX_WORKER, "worker ID out of range");
+        self.worker = Some(worker);
+        self
+    }
+
+    /// Generates an ID with the current time.
+    pub fn generate(&mut self, stream: &mut RandomStream) -> io::Result<String> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u64);
+        self.generate_at(stream, now)
+    }
+
+    /// Generates an ID with the time `now` in milliseconds since the Unix epoch.
+    pub fn generate_at(&mut self, stream: &mut RandomStream, now: u64) -> io::Result<String> {
+        Ok(match self.kind {
+            IdKind::UuidV4 => {
+                let bits = next_u128(stream)?;
+                format_uuid(bits & !(0xf << 76 | 0x3 << 62) | 0x4 << 76 | 0x2 << 62)
+            }
+            IdKind::UuidV7 => {
+                // the top bit of a fresh counter is clear to leave room for increments
+                let fresh = u128::from(stream.next_u64()? >> 23);
+                let (ms, counter) = self.advance(now, fresh, (1 << 42) - 1);
+                let rand = u128::from(stream.next_u64()? as u32);
+                let bits = u128::from(ms & 0xffff_ffff_ffff) << 80
+                    | 0x7 << 76
+                    | (counter >> 30) << 64
+                    | 0x2 << 62
+                    | (counter & 0x3fff_ffff) << 32
+                    | rand;
+                format_uuid(bits)
+            }
+            IdKind::Ulid => {
+                let fresh = next_u128(stream)? >> 48;
+                let (ms, counter) = self.advance(now, fresh, (1 << 80) - 1);
+                let bits = u128::from(ms & 0xffff_ffff_ffff) << 80 | counter;
+                (0..26)
+                    .rev()
+                    .map(|i| char::from(CROCKFORD[(bits >> (i * 5)) as usize & 31]))
+                    .collect()
+            }
+            IdKind::Nanoid => {
+                let mut id = String::with_capacity(self.len);
+                for _ in 0..self.len {
+                    let i = lemire_u64(self.alphabet.len() as u64, || stream.next_u64())?;
+                    id.push(self.alphabet[i as usize]);
+                }
+                id
+            }
+            IdKind::Snowflake => {
+                let worker = match self.worker {
+                    Some(worker) => worker,
+                    None => *self
+                        .worker
+                        .insert((stream.next_u64()? >> 54) as u16 & MAX_WORKER),
+                };
+                let (ms, sequence) = self.advance(now, 0, (1 << 12) - 1);
+                let time = ms.saturating_sub(SNOWFLAKE_EPOCH) & ((1 << 41) - 1);
+                (time << 22 | u64::from(worker) << 12 | sequence as u64).to_string()
+            }
+        })
+    }
+
+    /// Returns the timestamp and counter of the next time-based ID, restarting the counter at
+    /// `fresh` in a new millisecond and moving on to the next millisecond when it would exceed
+    /// `max`.
+    fn advance(&mut self, now: u64, fresh: u128, max: u128) -> (u64, u128) {
+        let next = match self.last {
+            Some((ms, counter)) if now <= ms && counter < max => (ms, counter + 1),
+            Some((ms, _)) if now <= ms => (ms + 1, fresh),
+            _ => (now, fresh),
+        };
+        *self.last.insert(next)
+    }
+}
+
+fn next_u128(stream: &mut RandomStream) -> io::Result<u128> {
+    Ok(u128::from(stream.next_u64()?) << 64 | u128::from(stream.next_u64()?))
+}
+
+/// Formats a UUID in the hyphenated lower-case form.
+fn format_uuid(bits: u128) -> String {
+    let hex = format!("{:032x}", bits);
+    let mut uuid = String::with_capacity(36);
+    for (i, range) in [0..8, 8..12, 12..16, 16..20, 20..32]
+        .into_iter()
+        .enumerate()
+    {
+        if i > 0 {
+            uuid.push('-');
+        }
+        uuid.push_str(&hex[range]);
+    }
+    uuid
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{IdGenerator, IdKind, SNOWFLAKE_EPOCH};
+    use crate::{generators::Algorithm, Builder, RandomStream};
+
+    fn stream() -> RandomStream {
+        Builder::new()
+            .algorithm(Algorithm::ChaCha20)
+            .seed(b"ids")
+            .build()
+    }
+
+    fn hex_digit(uuid: &str, i: usize) -> u32 {
+        uuid.replace('-', "")
+            .chars()
+            .nth(i)
+            .unwrap()
+            .to_digit(16)
+            .unwrap()
+    }
+
+    #[test]
+    fn kind_names() {
+        for k in IdKind::ALL {
+            assert_eq!(k.name().parse(), Ok(k));
+            assert_eq!(k.to_string().to_uppercase().parse(), Ok(k));
+        }
+        assert!("uuid".parse::<IdKind>().is_err());
+    }
+
+    #[test]
+    fn uuid_v4() {
+        let mut gen = IdGenerator::new(IdKind::UuidV4);
+        let mut stream = stream();
+        for _ in 0..100 {
+            let uuid = gen.generate(&mut stream).unwrap();
+            assert_eq!(uuid.len(), 36);
+            assert_eq!(uuid.matches('-').count(), 4);
+            assert_eq!(&uuid[14..15], "4");
+            assert!((8..=0xb).contains(&hex_digit(&uuid, 16)), "{}", uuid);
+        }
+    }
+
+    #[test]
+    fn uuid_v7_is_monotonic() {
+        let mut gen = IdGenerator::new(IdKind::UuidV7);
+        let mut stream = stream();
+        let now = 0x0123_4567_89ab;
+        let first = gen.generate_at(&mut stream, now).unwrap();
+        assert_eq!(&first[..13], "01234567-89ab");
+        assert_eq!(&first[14..15], "7");
+        assert!((8..=0xb).contains(&hex_digit(&first, 16)), "{}", first);
+
+        let mut last = first;
+        // same millisecond, then the clock going backwards
+        for now in [now; 1000].into_iter().chain([now - 5; 10]) {
+            let uuid = gen.generate_at(&mut stream, now).unwrap();
+            assert!(uuid > last, "{} <= {}", uuid, last);
+            assert_eq!(&uuid[..13], "01234567-89ab");
+            last = uuid;
+        }
+    }
+
+    #[test]
+    fn ulid_encoding() {
+        let mut gen = IdGenerator::new(IdKind::Ulid);
+        let mut stream = stream();
+        let first = gen.generate_at(&mut stream, 1_469_918_176_385).unwrap();
+        assert_eq!(first.len(), 26);
+        // from the ULID specification
+        assert_eq!(&first[..10], "01ARYZ6S41");
+        let mut last = first;
+        for _ in 0..100 {
+            let ulid = gen.generate_at(&mut stream, 1_469_918_176_385).unwrap();
+            assert!(ulid > last, "{} <= {}", ulid, last);
+            last = ulid;
+        }
+    }
+
+    #[test]
+    fn counter_overflow_moves_to_next_millisecond() {
+        let mut gen = IdGenerator::new(IdKind::Snowflake).worker(5);
+        let mut stream = stream();
+        let now = SNOWFLAKE_EPOCH + 1000;
+        let ids: Vec<u64> = (0..4097)
+            .map(|_| gen.generate_at(&mut stream, now).unwrap().parse().unwrap())
+            .collect();
+        assert_eq!(ids[0], 1000 << 22 | 5 << 12);
+        assert_eq!(ids[4095], 1000 << 22 | 5 << 12 | 4095);
+        assert_eq!(ids[4096], 1001 << 22 | 5 << 12);
+    }
+
+    #[test]
+    fn nanoids() {
+        let mut gen = IdGenerator::new(IdKind::Nanoid);
+        let mut stream = stream();
+        let id = gen.generate(&mut stream).unwrap();
+        assert_eq!(id.len(), 21);
+        assert!(id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-'));
+
+        let mut gen = IdGenerator::new(IdKind::Nanoid).alphabet("ab").length(8);
+        let id = gen.generate(&mut stream).unwrap();
+        assert_eq!(id.len(), 8);
+        assert!(id.chars().all(|c| c == 'a' || c == 'b'));
+    }
+}
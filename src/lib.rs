@@ -0,0 +1,841 @@
+//! Random byte stream generator behind the `gen-random` command.
+//!
+//! ```no_run
+//! let mut stream = gen_random::Builder::new().build();
+//! stream.write_to(std::io::stdout().lock(), Some(4096))?;
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use std::{
+    cmp,
+    collections::BTreeMap,
+    fmt, io, mem,
+    sync::{mpsc, Mutex},
+    thread,
+};
+
+use sha2::{Digest as _, Sha256};
+use zerocopy::AsBytes as _;
+use zeroize::{Zeroize as _, Zeroizing};
+
+pub mod dist;
+pub mod drbg;
+pub mod encoding;
+pub mod generators;
+pub mod ids;
+pub mod numbers;
+pub mod output;
+#[cfg(target_os = "linux")]
+pub mod pipe;
+pub mod range;
+pub mod secret;
+pub mod stats;
+pub mod wipe;
+
+use drbg::DrbgOptions;
+use generators::{Algorithm, ChaCha20, Generator};
+
+/// Size in bytes of the internal buffers.
+pub const BUF_SIZE: usize = 32 * 1024;
+
+/// Default number of bytes generated from a seed before the generator is reseeded.
+pub const RESEED_INTERVAL: usize = 512 * 1024;
+
+const BUF_WORDS: usize = BUF_SIZE / mem::size_of::<u64>();
+
+/// Maximum number of words generated by a worker of [`RandomStream::write_to_threads`] at a time.
+const JOB_WORDS: usize = 1024 * 1024 / mem::size_of::<u64>();
+
+/// Maximum number of generators in a job of [`RandomStream::write_to_threads`], which are all
+/// seeded on the calling thread.
+const JOB_RNGS: usize = 256;
+
+#[allow(clippy::manual_is_multiple_of)]
+const _: () = assert!(BUF_SIZE % mem::size_of::<u64>() == 0);
+
+/// Builder of [`RandomStream`].
+#[derive(Clone, Debug)]
+pub struct Builder {
+    algorithm: Algorithm,
+    reseed_interval: usize,
+    drbg_options: DrbgOptions,
+    seed: Option<[u8; 32]>,
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Builder {
+    /// Creates a builder with the default configuration.
+    pub const fn new() -> Self {
+        Self {
+            algorithm: Algorithm::Xorshift64Star,
+            reseed_interval: RESEED_INTERVAL,
+            drbg_options: DrbgOptions {
+                prediction_resistance: false,
+                reseed_interval: drbg::MAX_RESEED_INTERVAL,
+            },
+            seed: None,
+        }
+    }
+
+    /// Sets the generator algorithm.
+    pub fn algorithm(mut self, algorithm: Algorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Sets the number of bytes generated from a seed before the generator is reseeded.
+    ///
+    /// Ignored by DRBG algorithms, which follow the reseed policy set by
+    /// [`Builder::drbg_options`] instead.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bytes` is zero or not a multiple of eight.
+    pub fn reseed_interval(mut self, bytes: usize) -> Self {
+        assert!(
+            bytes > 0 && bytes.is_multiple_of(mem::size_of::<u64>()),
+            "reseed interval must be a positive multiple of 8: {}",
+            bytes
+        );
+        self.reseed_interval = bytes;
+        self
+    }
+
+    /// Sets the prediction resistance and reseed interval of DRBG algorithms.
+    pub fn drbg_options(mut self, options: DrbgOptions) -> Self {
+        self.drbg_options = options;
+        self
+    }
+
+    /// Makes the stream reproducible by deriving the seeds of the generator from `seed` instead of
+    /// drawing them from the OS.
+    ///
+    /// The seeds are read from the ChaCha20 keystream keyed by the SHA-256 digest of `seed`, and the
+    /// stream is made of the little-endian bytes of the words of the generators, so the same seed
+    /// and configuration produce the same stream on every platform. DRBG algorithms
+    /// still draw entropy from the OS when they reseed, so their output is reproducible only
+    /// without prediction resistance and within the DRBG reseed interval.
+    pub fn seed(mut self, seed: &[u8]) -> Self {
+        self.seed = Some(Sha256::digest(seed).into());
+        self
+    }
+
+    /// Creates a stream with the current configuration.
+    pub fn build(&self) -> RandomStream {
+        RandomStream {
+            algorithm: self.algorithm,
+            drbg_options: self.drbg_options,
+            reseed_words: self.reseed_interval / mem::size_of::<u64>(),
+            seed_source: self.seed.map(ChaCha20::new),
+            buf_seeds: vec![0; BUF_SIZE].into_boxed_slice(),
+            seed_pos: BUF_SIZE,
+            rng: None,
+            words_left: 0,
+            buf_rands: vec![0; BUF_WORDS].into_boxed_slice(),
+            pos: BUF_SIZE,
+        }
+    }
+}
+
+/// Infinite stream of random bytes produced by a generator (xorshift64* by default) periodically
+/// reseeded from [`getrandom`].
+///
+/// Seeds are drawn from the OS in batches; seeds invalid for the algorithm (e.g. zero seeds of
+/// xorshift64*, which would produce only zeros) are skipped. All methods consume the same
+/// underlying byte stream. The buffered output and the unused seeds are wiped from memory on drop,
+/// and used seeds as soon as their generator is created.
+pub struct RandomStream {
+    algorithm: Algorithm,
+    drbg_options: DrbgOptions,
+    reseed_words: usize,
+    /// Generator of seeds used instead of the OS if the stream is seeded by the user.
+    seed_source: Option<ChaCha20>,
+    buf_seeds: Box<[u8]>,
+    seed_pos: usize,
+    rng: Option<Box<dyn Generator + Send>>,
+    words_left: usize,
+    buf_rands: Box<[u64]>,
+    /// Offset in bytes of the unconsumed part of `buf_rands`.
+    pos: usize,
+}
+
+impl fmt::Debug for RandomStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RandomStream")
+            .field("algorithm", &self.algorithm)
+            .field(
+                "reseed_interval",
+                &(self.reseed_words * mem::size_of::<u64>()),
+            )
+            .field("seeded", &self.seed_source.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for RandomStream {
+    fn drop(&mut self) {
+        self.buf_rands.zeroize();
+        self.buf_seeds.zeroize();
+    }
+}
+
+impl RandomStream {
+    /// Fills `dest` with the next bytes of the stream.
+    pub fn fill_bytes(&mut self, dest: &mut [u8]) -> io::Result<()> {
+        let mut dest = dest;
+        while !dest.is_empty() {
+            if self.pos == BUF_SIZE {
+                self.refill()?;
+            }
+            let src = &self.buf_rands.as_bytes()[self.pos..];
+            let n = cmp::min(src.len(), dest.len());
+            dest[..n].copy_from_slice(&src[..n]);
+            self.pos += n;
+            dest = &mut dest[n..];
+        }
+        Ok(())
+    }
+
+    /// Fills `dest` with the next bytes of the stream like [`RandomStream::fill_bytes`], so that
+    /// the words hold the bytes in memory order, i.e. they are the words of the generators only on
+    /// little-endian platforms.
+    ///
+    /// Unlike [`RandomStream::fill_bytes`], this generates the words directly into `dest` once the
+    /// internal buffer is used up, unless the stream has been read up to the middle of a word.
+    pub fn fill_words(&mut self, dest: &mut [u64]) -> io::Result<()> {
+        if !self.pos.is_multiple_of(mem::size_of::<u64>()) {
+            return self.fill_bytes(dest.as_bytes_mut());
+        }
+        let buffered = &self.buf_rands[self.pos / mem::size_of::<u64>()..];
+        let n = cmp::min(buffered.len(), dest.len());
+        dest[..n].copy_from_slice(&buffered[..n]);
+        self.pos += n * mem::size_of::<u64>();
+        self.generate(&mut dest[n..])
+    }
+
+    /// Returns the next eight bytes of the stream as a little-endian integer.
+    pub fn next_u64(&mut self) -> io::Result<u64> {
+        let mut bytes = [0u8; 8];
+        self.fill_bytes(&mut bytes)?;
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    /// Writes the stream to `out` until `count` bytes are written (or infinitely if `None`).
+    ///
+    /// Returns `Ok(())` also when `out` reports [`io::ErrorKind::BrokenPipe`], i.e. when the
+    /// reader has gone away.
+    pub fn write_to(&mut self, mut out: impl io::Write, mut count: Option<u64>) -> io::Result<()> {
+        if count == Some(0) {
+            return Ok(());
+        }
+
+        loop {
+            if self.pos == BUF_SIZE {
+                self.refill()?;
+            }
+
+            let mut bytes = &self.buf_rands.as_bytes()[self.pos..];
+            if let Some(n) = count.as_mut() {
+                if *n < bytes.len() as u64 {
+                    bytes = &bytes[..*n as usize];
+                }
+                *n -= bytes.len() as u64;
+            }
+            self.pos += bytes.len();
+
+            match out.write_all(bytes) {
+                Err(e) if e.kind() == io::ErrorKind::BrokenPipe => return Ok(()),
+                ret => ret?,
+            }
+
+            if count == Some(0) {
+                return match out.flush() {
+                    Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+                    ret => ret,
+                };
+            }
+        }
+    }
+
+    /// Writes the stream to `out` like [`RandomStream::write_to`], generating it on `threads`
+    /// worker threads.
+    ///
+    /// Each worker fills a buffer of up to 1 MiB with the output of generators, which are seeded
+    /// in order on the calling thread, and the buffers are written in order, so the output is the
+    /// same as that of [`RandomStream::write_to`] for any number of threads. A generator whose
+    /// reseed interval is longer than a buffer is continued by consecutive jobs one at a time, so
+    /// memory stays bounded at two buffers per thread whatever the interval. As the workers run
+    /// ahead of the writer, the stream is consumed. DRBG algorithms, which never replace their
+    /// generator, are generated on the calling thread.
+    pub fn write_to_threads(
+        mut self,
+        out: impl io::Write,
+        count: Option<u64>,
+        threads: usize,
+    ) -> io::Result<()> {
+        if threads <= 1 || self.algorithm.is_drbg() {
+            return self.write_to(out, count);
+        }
+        let mut out = CountedWriter { out, left: count };
+
+        // the rest of the buffer comes first
+        if !out.write(&self.buf_rands.as_bytes()[self.pos..])? {
+            return out.finish();
+        }
+
+        let reseed_words = self.reseed_words;
+        // whole generators when they fit, so that consecutive jobs do not share one
+        let job_words = match JOB_WORDS / reseed_words {
+            0 => JOB_WORDS,
+            n => cmp::min(n, JOB_RNGS) * reseed_words,
+        };
+        // generator of the next job and the words left in its interval; it is out on a worker
+        // while `None` with words left
+        let mut current = self.rng.take();
+        let mut current_left = self.words_left;
+        let (job_tx, job_rx) = mpsc::channel::<Job>();
+        let job_rx = Mutex::new(job_rx);
+        let (done_tx, done_rx) = mpsc::channel::<Job>();
+
+        thread::scope(|scope| {
+            // dropped on return so that the workers stop
+            let job_tx = job_tx;
+            for _ in 0..threads {
+                let (job_rx, done_tx) = (&job_rx, done_tx.clone());
+                scope.spawn(move || loop {
+                    let job = job_rx.lock().expect("worker panicked").recv();
+                    let Ok(mut job) = job else { break };
+                    let mut start = 0;
+                    for (rng, n) in &mut job.rngs {
+                        rng.fill(&mut job.buf[start..(start + *n)]);
+                        start += *n;
+                    }
+                    to_le(&mut job.buf[..start]);
+                    if job.continued {
+                        job.carry = job.rngs.pop().map(|(rng, _)| rng);
+                    }
+                    job.rngs.clear();
+                    if done_tx.send(job).is_err() {
+                        break;
+                    }
+                });
+            }
+            drop(done_tx);
+
+            // a ring of two buffers per worker, so that they keep generating while one is written
+            let mut free: Vec<_> = (0..2 * threads)
+                .map(|_| Zeroizing::new(vec![0; job_words].into_boxed_slice()))
+                .collect();
+            let words_needed = out.left.map(|n| n.div_ceil(8));
+            let mut done = BTreeMap::new();
+            let (mut sent, mut written) = (0u64, 0u64);
+            loop {
+                while !free.is_empty()
+                    && (current.is_some() || current_left == 0)
+                    && words_needed.is_none_or(|n| sent * (job_words as u64) < n)
+                {
+                    let mut rngs = Vec::new();
+                    let mut filled = 0;
+                    while filled < job_words {
+                        if current_left == 0 {
+                            current = Some(self.next_generator()?);
+                            current_left = reseed_words;
+                        }
+                        let n = cmp::min(current_left, job_words - filled);
+                        rngs.push((current.take().expect("generator must be seeded"), n));
+                        current_left -= n;
+                        filled += n;
+                    }
+                    let job = Job {
+                        index: sent,
+                        rngs,
+                        continued: current_left > 0,
+                        carry: None,
+                        buf: free.pop().unwrap(),
+                    };
+                    job_tx.send(job).expect("workers must be running");
+                    sent += 1;
+                }
+                if written == sent {
+                    return out.finish();
+                }
+                let Some(job) = done.remove(&written) else {
+                    let mut job = done_rx.recv().expect("worker panicked");
+                    if let Some(rng) = job.carry.take() {
+                        current = Some(rng);
+                    }
+                    done.insert(job.index, job);
+                    continue;
+                };
+                written += 1;
+                if !out.write(job.buf.as_bytes())? {
+                    return out.finish();
+                }
+                free.push(job.buf);
+            }
+        })
+    }
+
+    /// Regenerates the whole buffer.
+    fn refill(&mut self) -> io::Result<()> {
+        let mut buf = mem::take(&mut self.buf_rands);
+        let ret = self.generate(&mut buf);
+        self.buf_rands = buf;
+        ret?;
+        self.pos = 0;
+        Ok(())
+    }
+
+    /// Fills `dest` with the next words of the generators, reseeding them as scheduled.
+    fn generate(&mut self, dest: &mut [u64]) -> io::Result<()> {
+        let mut filled = 0;
+        while filled < dest.len() {
+            if self.words_left == 0 {
+                self.rng = Some(self.next_generator()?);
+                self.words_left = if self.algorithm.is_drbg() {
+                    usize::MAX
+                } else {
+                    self.reseed_words
+                };
+            }
+
+            let n = cmp::min(self.words_left, dest.len() - filled);
+            let rng = self.rng.as_mut().expect("generator must be seeded");
+            rng.fill(&mut dest[filled..(filled + n)]);
+            to_le(&mut dest[filled..(filled + n)]);
+            self.words_left -= n;
+            filled += n;
+        }
+        Ok(())
+    }
+
+    /// Creates a generator from the next valid seed, fetching a new batch from the OS (or from the
+    /// seed source) when needed.
+    fn next_generator(&mut self) -> io::Result<Box<dyn Generator + Send>> {
+        let seed_len = self.algorithm.seed_len();
+        loop {
+            if self.buf_seeds.len() - self.seed_pos < seed_len {
+                match self.seed_source.as_mut() {
+                    Some(source) => {
+                        for chunk in self.buf_seeds.chunks_exact_mut(8) {
+                            chunk.copy_from_slice(&source.next_u64().to_le_bytes());
+                        }
+                    }
+                    None => getrandom::getrandom(&mut self.buf_seeds)?,
+                }
+                self.seed_pos = 0;
+            }
+
+            let seed = &mut self.buf_seeds[self.seed_pos..(self.seed_pos + seed_len)];
+            self.seed_pos += seed_len;
+            let rng = self.algorithm.new_generator_with(seed, &self.drbg_options);
+            // do not leave used keys of secure generators in memory
+            seed.zeroize();
+            if let Some(rng) = rng {
+                return Ok(rng);
+            }
+        }
+    }
+}
+
+/// Generators and the buffer they fill on a worker of [`RandomStream::write_to_threads`].
+struct Job {
+    index: u64,
+    /// Generators and the number of words each fills, in order.
+    rngs: Vec<(Box<dyn Generator + Send>, usize)>,
+    /// Whether the last generator continues in the next job.
+    continued: bool,
+    /// Last generator handed back by the worker if it continues.
+    carry: Option<Box<dyn Generator + Send>>,
+    buf: Zeroizing<Box<[u64]>>,
+}
+
+/// Converts words of the generators to the little-endian byte order of the stream in place.
+fn to_le(words: &mut [u64]) {
+    if cfg!(target_endian = "big") {
+        for w in words {
+            *w = w.to_le();
+        }
+    }
+}
+
+/// Writer that stops after a number of bytes, or when the reader has gone away.
+struct CountedWriter<W> {
+    out: W,
+    left: Option<u64>,
+}
+
+impl<W: io::Write> CountedWriter<W> {
+    /// Writes `bytes` up to the limit, returning `false` if no more bytes are wanted.
+    fn write(&mut self, mut bytes: &[u8]) -> io::Result<bool> {
+        if let Some(n) = self.left.as_mut() {
+            if *n < bytes.len() as u64 {
+                bytes = &bytes[..*n as usize];
+            }
+            *n -= bytes.len() as u64;
+        }
+        match self.out.write_all(bytes) {
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(false),
+            ret => ret.map(|()| self.left != Some(0)),
+        }
+    }
+
+    fn finish(mut self) -> io::Result<()> {
+        match self.out.flush() {
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+            ret => ret,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use zerocopy::AsBytes as _;
+
+    use super::{stats::Suite, Algorithm, Builder, BUF_SIZE, RESEED_INTERVAL};
+
+    /// Seed of the statistical checks of every algorithm.
+    const CHECK_SEED: &[u8] = b"statistical checks";
+
+    /// Sink that counts the set bits and the pairs of equal adjacent bits of the bytes written to
+    /// it, up to `limit` bytes.
+    struct BitCounter {
+        limit: usize,
+        n_bytes: usize,
+        n_ones: usize,
+        carry: u8,
+        n_twins: usize,
+    }
+
+    impl BitCounter {
+        fn new(limit: usize) -> Self {
+            Self {
+                limit,
+                n_bytes: 0,
+                n_ones: 0,
+                carry: 0,
+                n_twins: 0,
+            }
+        }
+
+        /// Asserts that the proportions of set bits and twin bits are within their 99.999%
+        /// confidence intervals, naming `source` in the failure messages.
+        fn assert_balanced(&self, source: &str) {
+            let n_samples = self.n_bytes as f64 * 8.0;
+            let p_ones = self.n_ones as f64 / n_samples;
+            let p_twins = self.n_twins as f64 / n_samples;
+
+            // set margin based on binom dist 99.999% confidence interval
+            let margin = 4.417173 * (0.5 * 0.5 / n_samples).sqrt();
+
+            assert!(
+                (p_ones - 0.5).abs() < margin,
+                "{}: % of set bits: {}% ({}/{}; 99.999% CI: {}%-{}%)",
+                source,
+                p_ones * 100.0,
+                self.n_ones,
+                self.n_bytes * 8,
+                (0.5 - margin) * 100.0,
+                (0.5 + margin) * 100.0,
+            );
+            assert!(
+                (p_twins - 0.5).abs() < margin,
+                "{}: % of twin (00/11) bits: {}% ({}/{}; 99.999% CI: {}%-{}%)",
+                source,
+                p_twins * 100.0,
+                self.n_twins,
+                self.n_bytes * 8,
+                (0.5 - margin) * 100.0,
+                (0.5 + margin) * 100.0,
+            );
+        }
+    }
+
+    impl io::Write for BitCounter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.n_bytes >= self.limit {
+                return Err(io::ErrorKind::BrokenPipe.into());
+            }
+
+            for &e in buf {
+                self.n_ones += e.count_ones() as usize;
+
+                let shifted = self.carry | e >> 1;
+                self.carry = e << 7;
+                self.n_twins += (e ^ shifted).count_zeros() as usize;
+            }
+
+            self.n_bytes += buf.len();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Returns the command that reproduces a check of `algorithm` on `n` bytes of [`CHECK_SEED`].
+    fn reproduce(algorithm: Algorithm, n: u64) -> String {
+        let seed: String = CHECK_SEED.iter().map(|b| format!("{:02x}", b)).collect();
+        format!(
+            "reproduce with `gen-random test -a {} --seed 0x{} -n {}`",
+            algorithm, seed, n
+        )
+    }
+
+    #[test]
+    fn seeded_streams_have_balanced_bits() {
+        const N: usize = 1024 * 1024;
+
+        for a in Algorithm::ALL {
+            let mut w = BitCounter::new(N);
+            let mut stream = Builder::new().algorithm(a).seed(CHECK_SEED).build();
+            stream.write_to(&mut w, Some(N as u64)).unwrap();
+            assert_eq!(w.n_bytes, N, "{}: short output", a);
+            w.assert_balanced(&format!("{} ({})", a, reproduce(a, N as u64)));
+        }
+    }
+
+    #[test]
+    fn seeded_streams_pass_suite() {
+        const N: u64 = 1024 * 1024;
+
+        // fixed seeds make the p-values deterministic, so a failure is a change of the output
+        // or of the tests, never bad luck
+        for a in Algorithm::ALL {
+            let mut suite = Suite::new();
+            let mut stream = Builder::new().algorithm(a).seed(CHECK_SEED).build();
+            stream.write_to(&mut suite, Some(N)).unwrap();
+            for result in suite.finish() {
+                assert!(
+                    result.passed(1e-4) == Some(true),
+                    "{}: {}, expected p >= 0.0001 ({})",
+                    a,
+                    result,
+                    reproduce(a, N)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn seeds_select_streams() {
+        for a in Algorithm::ALL {
+            let output = |builder: Builder| {
+                let mut w = Vec::new();
+                builder
+                    .algorithm(a)
+                    .build()
+                    .write_to(&mut w, Some(64))
+                    .unwrap();
+                w
+            };
+            assert_eq!(
+                output(Builder::new().seed(b"same")),
+                output(Builder::new().seed(b"same")),
+                "{}: the same seed must give the same output",
+                a
+            );
+            assert_ne!(
+                output(Builder::new().seed(b"one")),
+                output(Builder::new().seed(b"two")),
+                "{}: different seeds must give different outputs",
+                a
+            );
+            assert_ne!(
+                output(Builder::new()),
+                output(Builder::new()),
+                "{}: streams seeded from the OS must differ",
+                a
+            );
+        }
+    }
+
+    /// Takes minutes without optimizations; run with `cargo test --release -- --ignored`.
+    #[test]
+    #[ignore]
+    fn unseeded_stream_has_balanced_bits() {
+        const N: usize = 1024 * 1024 * 1024;
+
+        let mut w = BitCounter::new(N);
+        assert!(Builder::new().build().write_to(&mut w, None).is_ok() && w.n_bytes >= N);
+        w.assert_balanced("1 GiB of the default algorithm seeded from the OS");
+    }
+
+    #[test]
+    fn write_to_with_count() {
+        for a in Algorithm::ALL {
+            for n in [0, 1, 4095, BUF_SIZE as u64, RESEED_INTERVAL as u64 * 3 + 7] {
+                let mut w = Vec::new();
+                let mut stream = Builder::new().algorithm(a).build();
+                assert!(stream.write_to(&mut w, Some(n)).is_ok());
+                assert_eq!(
+                    w.len() as u64,
+                    n,
+                    "{}: requested {} bytes, got {}",
+                    a,
+                    n,
+                    w.len()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn methods_share_stream() {
+        let mut stream = Builder::new().reseed_interval(24).build();
+        let mut bytes = [0u8; 13];
+        stream.fill_bytes(&mut bytes).unwrap();
+        stream.next_u64().unwrap();
+
+        // the rest of the buffer continues from the middle of a word
+        let mut w = Vec::new();
+        stream.write_to(&mut w, Some(BUF_SIZE as u64)).unwrap();
+        assert_eq!(w.len(), BUF_SIZE);
+        assert_eq!(stream.pos, 21);
+        assert!(w.iter().any(|&e| e != 0), "stream must not be all zeros");
+    }
+
+    #[test]
+    fn fill_words_continues_stream() {
+        for skip in [0, 8, 13, BUF_SIZE] {
+            let builder = Builder::new().reseed_interval(4096).seed(b"words");
+            let mut expected = vec![0; skip + 3 * BUF_SIZE];
+            builder.build().fill_bytes(&mut expected).unwrap();
+
+            let mut stream = builder.build();
+            stream.fill_bytes(&mut vec![0; skip]).unwrap();
+            let mut words = vec![0u64; 3 * BUF_SIZE / 8];
+            let (head, tail) = words.split_at_mut(100);
+            stream.fill_words(head).unwrap();
+            stream.fill_words(tail).unwrap();
+            assert!(
+                words.as_bytes() == &expected[skip..],
+                "skipped {} bytes",
+                skip
+            );
+        }
+    }
+
+    #[test]
+    fn threads_do_not_change_output() {
+        for a in [
+            Algorithm::Xorshift64Star,
+            Algorithm::ChaCha8,
+            Algorithm::Aes128Ctr,
+            Algorithm::HmacDrbg,
+        ] {
+            // a tiny interval fills jobs of JOB_RNGS generators, and a large one is split across
+            // jobs of JOB_WORDS words
+            for (reseed_interval, n) in [
+                (RESEED_INTERVAL, 5 << 20),
+                (24, 100_003),
+                (4096, 7),
+                (8, (1 << 20) + 5),
+                ((3 << 20) + 8, 10 << 20),
+            ] {
+                let builder = Builder::new()
+                    .algorithm(a)
+                    .reseed_interval(reseed_interval)
+                    .seed(b"threads");
+                let mut expected = Vec::new();
+                let mut stream = builder.build();
+                stream.fill_bytes(&mut [0; 13]).unwrap();
+                stream.write_to(&mut expected, Some(n)).unwrap();
+
+                for threads in [1, 2, 3, 8] {
+                    let mut w = Vec::new();
+                    let mut stream = builder.build();
+                    stream.fill_bytes(&mut [0; 13]).unwrap();
+                    stream.write_to_threads(&mut w, Some(n), threads).unwrap();
+                    assert!(w == expected, "{}, {} threads, {} bytes", a, threads, n);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn seeded_golden_output() {
+        use sha2::{Digest as _, Sha256};
+
+        use super::{ChaCha20, Generator as _};
+
+        // first eight bytes of the SHA-256 digest of the first 2 MiB, which span four reseeds
+        const GOLDEN: [(Algorithm, u64); 17] = [
+            (Algorithm::Xorshift64Star, 0xcc4da4d3ce30c334),
+            (Algorithm::Xoshiro256StarStar, 0x68bb7e76b7fe1150),
+            (Algorithm::Xoroshiro128PlusPlus, 0x51219e48eff9d580),
+            (Algorithm::SplitMix64, 0xb4d7321dfcfde66c),
+            (Algorithm::Pcg64Dxsm, 0x7ff58894d30c79f9),
+            (Algorithm::WyRand, 0xbc3fcb3a7772efa6),
+            (Algorithm::Sfc64, 0xebb1083931852f5e),
+            (Algorithm::Xorshift64StarX8, 0xa158cbe31e8b493d),
+            (Algorithm::Xoshiro256StarStarX8, 0x3adb0e9c62a5bc87),
+            (Algorithm::ChaCha20, 0x9a96db6e9c9b693c),
+            (Algorithm::ChaCha12, 0xb579f32f02982b1e),
+            (Algorithm::ChaCha8, 0x10b9c193f8907b77),
+            (Algorithm::Aes128Ctr, 0x32812dd7754a9afd),
+            (Algorithm::Aes256Ctr, 0xb2b065e01d9f30f7),
+            (Algorithm::HashDrbg, 0x199d6b6adf01a495),
+            (Algorithm::HmacDrbg, 0xdb14035ca2d6dde8),
+            (Algorithm::CtrDrbg, 0xe5b1651b5a33260e),
+        ];
+
+        for (a, expected) in GOLDEN {
+            let mut w = Vec::new();
+            let mut stream = Builder::new().algorithm(a).seed(b"gen-random").build();
+            stream.write_to(&mut w, Some(2 << 20)).unwrap();
+            let digest = Sha256::digest(&w);
+            let actual = u64::from_be_bytes(digest[..8].try_into().unwrap());
+            assert_eq!(actual, expected, "{}", a);
+        }
+
+        // the words of the first generator, seeded with the first bytes of the keystream, in
+        // little-endian order
+        for a in [
+            Algorithm::Xorshift64Star,
+            Algorithm::Sfc64,
+            Algorithm::ChaCha20,
+        ] {
+            let mut source = ChaCha20::new(Sha256::digest(b"bytes").into());
+            let seed: Vec<u8> = (0..a.seed_len().div_ceil(8))
+                .flat_map(|_| source.next_u64().to_le_bytes())
+                .collect();
+            let mut words = [0; 512];
+            a.new_generator(&seed[..a.seed_len()])
+                .unwrap()
+                .fill(&mut words);
+            let expected: Vec<u8> = words.iter().flat_map(|w| w.to_le_bytes()).collect();
+
+            let mut w = Vec::new();
+            let mut stream = Builder::new().algorithm(a).seed(b"bytes").build();
+            stream.write_to(&mut w, Some(4096)).unwrap();
+            assert!(w == expected, "{}", a);
+            let mut stream = Builder::new().algorithm(a).seed(b"bytes").build();
+            assert_eq!(
+                stream.next_u64().unwrap(),
+                u64::from_le_bytes(expected[..8].try_into().unwrap()),
+                "{}",
+                a
+            );
+        }
+
+        let mut w = Vec::new();
+        let mut stream = Builder::new().seed(&42u64.to_le_bytes()).build();
+        stream.write_to(&mut w, Some(16)).unwrap();
+        assert_eq!(
+            w,
+            [
+                0xa1, 0x11, 0x78, 0xd8, 0x9e, 0xc0, 0x3c, 0x84, 0x15, 0xe4, 0x29, 0x17, 0x35, 0x28,
+                0x06, 0xe3
+            ]
+        );
+    }
+}
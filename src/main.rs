@@ -1,108 +1,1554 @@
-use std::{io, mem};
+use std::{
+    env, fs, io,
+    io::{Read as _, Write as _},
+    process,
+};
 
-use zerocopy::AsBytes as _;
+use gen_random::{
+    dist::{self, Distribution},
+    drbg::{self, DrbgOptions},
+    encoding::{Encoder, Format},
+    generators::Algorithm,
+    ids::{self, IdGenerator, IdKind},
+    numbers::{self, ignore_broken_pipe, Endian, NumberFormat, TextWriter},
+    output::{FileOptions, FileOutput},
+    range::{self, IntRange},
+    secret::{self, PasswordGenerator, Wordlist},
+    stats::{sp800_22, sp800_90b, stress, Suite},
+    wipe::{Pass, Wipe},
+};
 
-const BUF_SIZE: usize = 32 * 1024;
-const RESEED_INTERVAL: usize = 512 * 1024;
+const USAGE: &str = "\
+Usage: gen-random [OPTIONS]
+       gen-random password [OPTIONS]
+       gen-random passphrase --wordlist PATH [OPTIONS]
+       gen-random ids [KIND] [OPTIONS]
+       gen-random wipe PATH [OPTIONS]
+       gen-random test [OPTIONS]
+       gen-random entropy [FILE] [OPTIONS]
+       gen-random stress [OPTIONS]
+
+Print random bytes to stdout infinitely or up to the specified length. The subcommands print
+passwords (password), passphrases (passphrase) and IDs (ids), overwrite files (wipe), test the
+randomness of the generators (test, and stress at growing lengths), or estimate the entropy of
+noise sources (entropy); see 'gen-random <SUBCOMMAND> --help' for their options.
+
+Options:
+  -n, --count SIZE  Stop after writing SIZE bytes (e.g. 4096, 10G, 4KiB), or SIZE values with
+                    numeric formats, --range or --dist [default: infinite, or the size of a
+                    block device given to --output with the raw format]
+      --count-unit UNIT
+                    Count source bytes or output characters, excluding padding and line
+                    breaks, with --count: bytes, chars [default: bytes]
+  -f, --format NAME Write the bytes in the format NAME [default: raw]
+  -w, --wrap N      Break lines of text formats after every N characters
+      --separator SEP
+                    Separate values of numeric formats by SEP [default: newline]
+  -r, --range RANGE Write integers uniformly distributed in RANGE (LOW..HIGH or LOW..=HIGH,
+                    within i128 or u128) in decimal, or in binary with --format raw
+  -d, --dist DIST   Write samples of the distribution DIST in decimal, or in binary (f64, or
+                    u64 for discrete distributions) with --format raw
+      --endian ORDER
+                    Write binary values of --range or --dist in the byte order ORDER: little,
+                    big [default: little]
+  -o, --output PATH Write to the file or block device PATH instead of stdout
+      --direct      Bypass the page cache with O_DIRECT when writing to --output (Linux only)
+      --fsync-every SIZE
+                    Flush the data written to --output to the disk after every SIZE bytes
+      --fdatasync-at-end
+                    Flush the data written to --output to the disk at the end
+  -a, --algo NAME   Use the generator algorithm NAME [default: xorshift64star]
+  -t, --threads N   Generate bytes on N threads; the output of a seed does not depend on N, and
+                    DRBG algorithms always use one thread [default: 1]
+      --vmsplice    Write raw output to a pipe by handing pages over with vmsplice(2) instead
+                    of copying them with write(2) (Linux only); the reader must copy the data
+                    out of the pipe rather than splice it elsewhere, or it may see it overwritten
+  -s, --secure      Use a cryptographically secure algorithm [default: chacha20]
+      --prediction-resistance
+                    Reseed DRBG algorithms from the OS before every request
+      --drbg-reseed-interval N
+                    Reseed DRBG algorithms after N requests of up to 64 KiB each
+      --seed SEED   Derive all seeds from SEED, a decimal u64 or a hex string, to make the
+                    output reproducible
+      --seed-file PATH
+                    Derive all seeds from the contents of the file PATH
+      --print-seed  Print the seed in hex to stderr so that the output can be reproduced with
+                    --seed; a random seed is drawn from the OS unless one is given
+      --seed-log PATH
+                    Write the seed in hex to the file PATH like --print-seed
+  -h, --help        Print this help and exit
+
+Algorithms:
+  xorshift64star, xoshiro256starstar, xoroshiro128plusplus, splitmix64, pcg64dxsm, wyrand,
+  sfc64, xorshift64star-x8 (vectorized), xoshiro256starstar-x8 (vectorized), chacha20 (secure),
+  chacha12 (secure), chacha8 (secure), aes128ctr (secure), aes256ctr (secure),
+  hashdrbg (secure), hmacdrbg (secure), ctrdrbg (secure)
+
+The -x8 algorithms interleave the words of eight independently seeded generators.
+
+Distributions:
+  normal:MU,SIGMA, exp:LAMBDA, poisson:LAMBDA, binomial:N,P, gamma:K,THETA, beta:ALPHA,BETA,
+  lognormal:MU,SIGMA, zipf:N,S
+
+Formats:
+  raw, hex, HEX, base64, base64url, base32, base58, z85, and numeric formats u8, u16, u32, u64,
+  i32, i64, f32, f64 (uniform in [0, 1)) printed in decimal
+";
+
+const PASSWORD_USAGE: &str = "\
+Usage: gen-random password [OPTIONS]
+
+Print passwords with at least one character of every selected class, drawn with chacha20, and
+their entropy to stderr.
+
+Options:
+  -l, --length N    Make passwords N characters long [default: 20]
+      --upper       Use upper-case letters
+      --lower       Use lower-case letters
+      --digits      Use digits
+      --symbols     Use ASCII symbols
+      --alphabet CHARS
+                    Use the characters CHARS as another class (at most once)
+      --exclude-ambiguous
+                    Leave out characters that are easily confused: 0 O o I l 1 | ` ' \"
+  -n, --count N     Print N passwords [default: 1]
+  -h, --help        Print this help and exit
+
+Without class options, upper- and lower-case letters, digits and symbols are used.
+";
+
+const PASSPHRASE_USAGE: &str = "\
+Usage: gen-random passphrase --wordlist PATH [OPTIONS]
+
+Print passphrases of words drawn with chacha20, and their entropy to stderr.
+
+Options:
+      --wordlist PATH
+                    Draw words from the file PATH, one per line or in diceware format like the
+                    EFF large wordlist (e.g. '11111 abacus')
+  -w, --words N     Use N words [default: 6]
+      --separator SEP
+                    Separate words by SEP [default: space]
+  -n, --count N     Print N passphrases [default: 1]
+  -h, --help        Print this help and exit
+";
+
+const IDS_USAGE: &str = "\
+Usage: gen-random ids [KIND] [OPTIONS]
+
+Print identifiers of KIND one per line [default: uuid4]. The time-based kinds are monotonic
+within a run.
+
+Options:
+  -n, --count N     Print N identifiers [default: 1]
+      --alphabet CHARS
+                    Draw nanoids from the characters CHARS [default: A-Za-z0-9_-]
+      --length N    Make nanoids N characters long [default: 21]
+      --worker N    Use the worker ID N (0 to 1023) in snowflake IDs [default: random]
+  -a, --algo NAME   Draw the random parts with the generator algorithm NAME [default: chacha20]
+  -h, --help        Print this help and exit
+
+Kinds:
+  uuid4        RFC 9562 UUID version 4
+  uuid7        RFC 9562 UUID version 7 with a 42-bit counter within a millisecond
+  ulid         ULID, incremented within a millisecond
+  nanoid       Random characters of an alphabet
+  snowflake    Decimal 64-bit ID of a 41-bit millisecond timestamp, a 10-bit worker ID and a
+               12-bit sequence
+";
+
+const WIPE_USAGE: &str = "\
+Usage: gen-random wipe PATH [OPTIONS]
+
+Overwrite the existing file or block device PATH in passes, syncing it to the disk and reading
+it back to verify it after every pass. Mismatching offsets are reported to stderr and make the
+exit status 1.
+
+Options:
+  -p, --pass PASS   Add a pass writing PASS, in the order given [default: random]
+      --seed SEED   Derive the streams of random passes from SEED, a decimal u64 or a hex
+                    string [default: random]
+  -a, --algo NAME   Use the generator algorithm NAME for random passes [default: chacha20]
+      --direct      Write and read with O_DIRECT (Linux only)
+      --no-verify   Do not read the target back
+  -h, --help        Print this help and exit
+
+Passes:
+  random            Bytes of the generator, regenerated for the verification
+  zeros             Zero bytes
+  ones              0xff bytes
+  pattern:HEX       The bytes HEX repeated, e.g. pattern:55aa
+";
+
+const TEST_USAGE: &str = "\
+Usage: gen-random test [OPTIONS]
+
+Run statistical tests on bytes of a generator or stdin and print their p-values. The exit
+status is 1 if any test fails.
+
+Options:
+  -n, --count SIZE  Test SIZE bytes (e.g. 1M, 1GiB), or at most SIZE bytes of stdin
+                    [default: 1MiB]
+  -a, --algo NAME   Test the generator algorithm NAME [default: xorshift64star]
+      --seed SEED   Seed the generator with SEED, a decimal u64 or a hex string
+      --stdin       Test the bytes read from stdin instead of a generator
+      --alpha A     Fail tests with p-values below the significance level A [default: 0.01]
+      --sp800-22    Run all 15 tests of NIST SP 800-22 with the parameters of the reference
+                    implementation on several sequences, and check the proportion of passing
+                    sequences and the uniformity of the p-values of every test
+      --sequences N Test N sequences with --sp800-22 [default: 100]
+      --sequence-len BITS
+                    Test sequences of BITS bits with --sp800-22 [default: 1000000]
+  -h, --help        Print this help and exit
+
+Tests (NIST SP 800-22 unless noted):
+  monobit, block frequency (128-bit blocks), runs, longest run of ones, serial (patterns of up
+  to 16 bits), approximate entropy (up to 10 bits), cumulative sums, and a chi-square test of
+  the byte distribution. Tests are skipped if the input is too short for them. The input is
+  kept in memory with one byte per bit, like the reference implementation, so testing SIZE
+  bytes takes 8 times SIZE bytes of memory.
+";
+
+const ENTROPY_USAGE: &str = "\
+Usage: gen-random entropy [FILE] [OPTIONS]
+
+Estimate the min-entropy of samples with the non-IID estimators of NIST SP 800-90B, reading one
+sample per byte from FILE, or from stdin if FILE is omitted or '-'. SP 800-90B requires at
+least 1000000 samples.
+
+Options:
+  -b, --bits N      Take the low N bits (1 to 8) of every byte as a sample [default: 8]
+  -n, --count N     Estimate at most N samples [default: all, or 1000000 with --getrandom]
+      --getrandom   Sample bytes of getrandom(2) instead of reading them
+      --truncate    Estimate the bitstring of non-binary samples on its first 1000000 bits
+      --iid         Also run the IID permutation tests, which compare statistics of the
+                    samples with those of 10000 shuffles; the exit status is 1 if they reject
+                    the IID assumption. The compression statistic is computed only if
+                    gen-random is built with the 'bzip2' feature
+      --seed SEED   Seed the shuffles of --iid with SEED, a decimal u64 or a hex string
+  -h, --help        Print this help and exit
+
+Estimators:
+  most common value, collision, Markov and compression (binary samples only), t-tuple, longest
+  repeated substring (LRS), and the MultiMCW, lag, MultiMMC and LZ78Y predictors. Non-binary
+  samples are also estimated as the bitstring of their bits, and the min-entropy is the lower of
+  the lowest estimate of the samples and N times that of the bitstring.
+";
+
+const STRESS_USAGE: &str = "\
+Usage: gen-random stress [OPTIONS]
+
+Test a generator at doubling lengths (1 MiB, 2 MiB, 4 MiB, ...) like PractRand's RNG_test,
+feeding its output to the tests without a pipe, and print the anomalous p-values found at each
+length. The exit status is 1 once a test fails, i.e. its p-value is within 1e-12 of 0 or 1.
+
+Options:
+  -n, --count SIZE  Stop after testing SIZE bytes (e.g. 64GiB, 1T) [default: 1TiB]
+  -a, --algo NAME   Test the generator algorithm NAME [default: xorshift64star]
+      --seed SEED   Seed the generator with SEED, a decimal u64 or a hex string; a random seed
+                    is drawn from the OS and printed unless one is given
+      --reseed-interval SIZE
+                    Reseed the generator every SIZE bytes, a multiple of 8, like the other
+                    subcommands do every 512KiB; not for DRBG algorithms [default: never]
+  -h, --help        Print this help and exit
+
+Tests:
+  FPF (exponents and significands of 64-bit floats), birthday spacings (high and low 32 bits),
+  gap (high and low bytes), BCFN (bit counts of blocks of 2^6 to 2^20 bits) and DC6 (Hamming
+  weights of 4-byte tuples). Anomalies are rated unusual (p within 1e-3 of 0 or 1), mildly
+  suspicious (1e-4), suspicious (1e-6), very suspicious (1e-8), VERY SUSPICIOUS (1e-10) or FAIL
+  (1e-12).
+";
 
 fn main() -> io::Result<()> {
-    run(&mut io::stdout().lock())
+    let mut args = env::args().skip(1).peekable();
+    match args.peek().map(String::as_str) {
+        Some("password") => return password(args.skip(1)),
+        Some("passphrase") => return passphrase(args.skip(1)),
+        Some("ids") => return identifiers(args.skip(1)),
+        Some("wipe") => return wipe(args.skip(1)),
+        Some("test") => return test(args.skip(1)),
+        Some("entropy") => return entropy(args.skip(1)),
+        Some("stress") => return stress(args.skip(1)),
+        _ => {}
+    }
+
+    let mut count = None;
+    let mut count_chars = false;
+    let mut format = None;
+    let mut number_format = None;
+    let mut wrap = None;
+    let mut separator = String::from("\n");
+    let mut int_range = None;
+    let mut dist = None;
+    let mut endian = None;
+    let mut algorithm = None;
+    let mut secure = false;
+    let mut drbg_options = DrbgOptions::default();
+    let mut seed = None;
+    let mut print_seed = false;
+    let mut seed_log = None;
+    let mut threads = 1;
+    let mut vmsplice = false;
+    let mut output = None;
+    let mut file_options = FileOptions::new();
+    let mut file_options_given = false;
+
+    while let Some(arg) = args.next() {
+        let (name, inline) = split_arg(&arg);
+        match name {
+            "-h" | "--help" => {
+                print!("{}", USAGE);
+                return Ok(());
+            }
+            "-n" | "--count" => {
+                let value = take_value(USAGE, name, inline, &mut args);
+                count = Some(parse_size(&value).unwrap_or_else(|| {
+                    exit_with_usage(USAGE, &format!("invalid size for '{}': '{}'", name, value))
+                }));
+            }
+            "--count-unit" => {
+                let value = take_value(USAGE, name, inline, &mut args);
+                count_chars = match value.as_str() {
+                    "bytes" => false,
+                    "chars" => true,
+                    _ => {
+                        exit_with_usage(USAGE, &format!("invalid unit for '{}': '{}'", name, value))
+                    }
+                };
+            }
+            "-f" | "--format" => {
+                let value = take_value(USAGE, name, inline, &mut args);
+                match value.parse() {
+                    Ok(f) => (format, number_format) = (Some(f), None),
+                    Err(e) => {
+                        format = None;
+                        number_format = Some(value.parse::<NumberFormat>().unwrap_or_else(|_| {
+                            exit_with_usage(USAGE, &format!("{}", e));
+                        }))
+                    }
+                }
+            }
+            "--separator" => separator = take_value(USAGE, name, inline, &mut args),
+            "-r" | "--range" => {
+                let value = take_value(USAGE, name, inline, &mut args);
+                int_range = Some(
+                    value
+                        .parse::<IntRange>()
+                        .unwrap_or_else(|e| exit_with_usage(USAGE, &format!("{}", e))),
+                );
+            }
+            "-w" | "--wrap" => {
+                let value = take_value(USAGE, name, inline, &mut args);
+                wrap = Some(value.parse().unwrap_or_else(|_| {
+                    exit_with_usage(USAGE, &format!("invalid width for '{}': '{}'", name, value))
+                }));
+            }
+            "-d" | "--dist" => {
+                let value = take_value(USAGE, name, inline, &mut args);
+                dist = Some(
+                    value
+                        .parse::<Distribution>()
+                        .unwrap_or_else(|e| exit_with_usage(USAGE, &format!("{}", e))),
+                );
+            }
+            "--endian" => {
+                let value = take_value(USAGE, name, inline, &mut args);
+                endian = Some(
+                    value
+                        .parse::<Endian>()
+                        .unwrap_or_else(|e| exit_with_usage(USAGE, &format!("{}", e))),
+                );
+            }
+            "-a" | "--algo" => {
+                let value = take_value(USAGE, name, inline, &mut args);
+                algorithm = Some(
+                    value
+                        .parse::<Algorithm>()
+                        .unwrap_or_else(|e| exit_with_usage(USAGE, &format!("{}", e))),
+                );
+            }
+            "-t" | "--threads" => {
+                threads = parse_number(USAGE, name, inline, &mut args);
+                if threads == 0 {
+                    exit_with_usage(USAGE, &format!("invalid number for '{}': '0'", name));
+                }
+            }
+            "--vmsplice" => vmsplice = true,
+            "-o" | "--output" => output = Some(take_value(USAGE, name, inline, &mut args)),
+            "--direct" => {
+                file_options = file_options.direct(true);
+                file_options_given = true;
+            }
+            "--fsync-every" => {
+                let value = take_value(USAGE, name, inline, &mut args);
+                let bytes = parse_size(&value).filter(|&n| n > 0).unwrap_or_else(|| {
+                    exit_with_usage(USAGE, &format!("invalid size for '{}': '{}'", name, value))
+                });
+                file_options = file_options.fsync_every(bytes);
+                file_options_given = true;
+            }
+            "--fdatasync-at-end" => {
+                file_options = file_options.fdatasync_at_end(true);
+                file_options_given = true;
+            }
+            "-s" | "--secure" => secure = true,
+            "--prediction-resistance" => drbg_options.prediction_resistance = true,
+            "--drbg-reseed-interval" => {
+                let value = take_value(USAGE, name, inline, &mut args);
+                drbg_options.reseed_interval = value
+                    .parse()
+                    .ok()
+                    .filter(|n| (1..=drbg::MAX_RESEED_INTERVAL).contains(n))
+                    .unwrap_or_else(|| {
+                        exit_with_usage(
+                            USAGE,
+                            &format!("invalid interval for '{}': '{}'", name, value),
+                        )
+                    });
+            }
+            "--seed" => {
+                let value = take_value(USAGE, name, inline, &mut args);
+                seed = Some(parse_seed(&value).unwrap_or_else(|| {
+                    exit_with_usage(USAGE, &format!("invalid seed for '{}': '{}'", name, value))
+                }));
+            }
+            "--seed-file" => {
+                let value = take_value(USAGE, name, inline, &mut args);
+                let bytes = fs::read(&value).unwrap_or_else(|e| {
+                    eprintln!("gen-random: cannot read '{}': {}", value, e);
+                    process::exit(1);
+                });
+                if bytes.is_empty() {
+                    exit_with_usage(USAGE, &format!("seed file '{}' is empty", value));
+                }
+                seed = Some(bytes);
+            }
+            "--print-seed" => print_seed = true,
+            "--seed-log" => seed_log = Some(take_value(USAGE, name, inline, &mut args)),
+            _ => exit_with_usage(USAGE, &format!("unrecognized argument '{}'", arg)),
+        }
+    }
+
+    let algorithm = match algorithm {
+        Some(a) if secure && !a.is_secure() => {
+            exit_with_usage(USAGE, &format!("'{}' is not a secure algorithm", a))
+        }
+        Some(a) => a,
+        None if secure => Algorithm::ChaCha20,
+        None => Algorithm::default(),
+    };
+    let replayable = seed.is_some() || print_seed || seed_log.is_some();
+    if replayable && algorithm.is_drbg() && drbg_options != DrbgOptions::default() {
+        // reseeds draw entropy from the OS
+        exit_with_usage(USAGE, "DRBG reseed options cannot be combined with a seed");
+    }
+
+    if print_seed || seed_log.is_some() {
+        if seed.is_none() {
+            let mut bytes = vec![0; 32];
+            getrandom::getrandom(&mut bytes)?;
+            seed = Some(bytes);
+        }
+        // prefixed so that it is not taken as a decimal number when replayed
+        let mut hex = String::from("0x");
+        hex.extend(seed.iter().flatten().map(|b| format!("{:02x}", b)));
+        if print_seed {
+            eprintln!("gen-random: seed {}", hex);
+        }
+        if let Some(path) = seed_log {
+            fs::write(&path, hex + "\n").unwrap_or_else(|e| {
+                eprintln!("gen-random: cannot write '{}': {}", path, e);
+                process::exit(1);
+            });
+        }
+    }
+
+    if file_options_given && output.is_none() {
+        exit_with_usage(
+            USAGE,
+            "'--direct', '--fsync-every' and '--fdatasync-at-end' require '--output'",
+        );
+    }
+    let mut builder = gen_random::Builder::new()
+        .algorithm(algorithm)
+        .drbg_options(drbg_options);
+    if let Some(seed) = seed {
+        builder = builder.seed(&seed);
+    }
+    let mut stream = builder.build();
+
+    let sampled = int_range.is_some() || dist.is_some();
+    if (number_format.is_some() || sampled) && (wrap.is_some() || count_chars) {
+        exit_with_usage(USAGE, "'--wrap' and '--count-unit' require a text encoding");
+    }
+    if int_range.is_some() && dist.is_some() {
+        exit_with_usage(USAGE, "'--range' and '--dist' cannot be combined");
+    }
+    let binary = match (format, number_format) {
+        _ if !sampled => false,
+        (None, None) => false,
+        (Some(Format::Raw), None) => true,
+        _ => exit_with_usage(USAGE, "'--range' and '--dist' support only the raw format"),
+    };
+    if endian.is_some() && !binary {
+        exit_with_usage(
+            USAGE,
+            "'--endian' requires '--range' or '--dist' with the raw format",
+        );
+    }
+    let endian = endian.unwrap_or_default();
+    if threads > 1 && (sampled || number_format.is_some()) {
+        exit_with_usage(USAGE, "'--threads' requires a byte format");
+    }
+    if threads > 1 && vmsplice {
+        exit_with_usage(USAGE, "'--vmsplice' cannot be used with '--threads'");
+    }
+    if wrap.is_some() && format.unwrap_or_default() == Format::Raw {
+        exit_with_usage(USAGE, "'--wrap' requires a text format");
+    }
+
+    let mut out = match output {
+        Some(path) => Output::File(file_options.open(&path).unwrap_or_else(|e| {
+            eprintln!("gen-random: cannot open '{}': {}", path, e);
+            process::exit(1);
+        })),
+        None => Output::Stdout(io::stdout().lock()),
+    };
+
+    if binary {
+        match (int_range, dist) {
+            (Some(r), _) => range::write_binary(&mut stream, r, &mut out, endian, count)?,
+            (_, Some(d)) => dist::write_binary(&mut stream, &d, &mut out, endian, count)?,
+            _ => unreachable!(),
+        }
+        return ignore_broken_pipe(out.finish());
+    }
+
+    if sampled || number_format.is_some() {
+        let mut writer = TextWriter::new(out, separator.as_bytes());
+        match (int_range, dist, number_format) {
+            (Some(r), _, _) => range::write_text(&mut stream, r, &mut writer, count)?,
+            (_, Some(d), _) => dist::write_text(&mut stream, &d, &mut writer, count)?,
+            (_, _, Some(f)) => numbers::write_numbers(&mut stream, f, &mut writer, count)?,
+            _ => unreachable!(),
+        }
+        return ignore_broken_pipe(writer.finish().and_then(Output::finish));
+    }
+
+    let format = format.unwrap_or_default();
+    if format == Format::Raw {
+        let count = match &out {
+            Output::File(file) if count.is_none() => file.device_size(),
+            _ => count,
+        };
+        #[cfg(target_os = "linux")]
+        if let Output::Stdout(stdout) = &out {
+            use std::os::fd::AsFd as _;
+
+            if vmsplice
+                && threads == 1
+                && gen_random::pipe::vmsplice_to(&mut stream, stdout.as_fd(), count)?
+            {
+                return Ok(());
+            }
+        }
+        stream.write_to_threads(&mut out, count, threads)?;
+        return ignore_broken_pipe(out.finish());
+    }
+
+    let mut encoder = Encoder::new(out, format).wrap(wrap.unwrap_or(0));
+    let count = match count {
+        Some(n) if count_chars => {
+            encoder = encoder.limit(n);
+            Some(format.input_len(n))
+        }
+        _ => count,
+    };
+    stream.write_to_threads(&mut encoder, count, threads)?;
+    ignore_broken_pipe(encoder.finish().and_then(Output::finish))
 }
 
-fn run(out: &mut impl io::Write) -> io::Result<()> {
-    const _: () = assert!(BUF_SIZE % mem::size_of::<u64>() == 0);
-    let mut buf_seeds = [0u64; BUF_SIZE / mem::size_of::<u64>()];
-    let mut buf_rands = [0u64; BUF_SIZE / mem::size_of::<u64>()];
+/// Destination of the byte stream: stdout or the file given with `--output`.
+enum Output {
+    Stdout(io::StdoutLock<'static>),
+    File(FileOutput),
+}
 
-    loop {
-        getrandom::getrandom(buf_seeds.as_bytes_mut())?;
+impl Output {
+    /// Writes the rest of the buffered bytes.
+    fn finish(self) -> io::Result<()> {
+        match self {
+            Self::Stdout(mut stdout) => stdout.flush(),
+            Self::File(file) => file.finish(),
+        }
+    }
+}
 
-        for mut s in buf_seeds {
-            if s == 0 {
-                continue;
+impl io::Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Stdout(stdout) => stdout.write(buf),
+            Self::File(file) => file.write(buf),
+        }
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        match self {
+            Self::Stdout(stdout) => stdout.write_all(buf),
+            Self::File(file) => file.write_all(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Stdout(stdout) => stdout.flush(),
+            Self::File(file) => file.flush(),
+        }
+    }
+}
+
+/// Runs the `password` subcommand.
+fn password(mut args: impl Iterator<Item = String>) -> io::Result<()> {
+    let usage = PASSWORD_USAGE;
+    let mut length = 20;
+    let mut classes = Vec::new();
+    let mut alphabet = None;
+    let mut exclude_ambiguous = false;
+    let mut count = 1;
+
+    while let Some(arg) = args.next() {
+        let (name, inline) = split_arg(&arg);
+        match name {
+            "-h" | "--help" => {
+                print!("{}", usage);
+                return Ok(());
+            }
+            "-l" | "--length" => length = parse_number(usage, name, inline, &mut args),
+            "--upper" => classes.push(secret::UPPER),
+            "--lower" => classes.push(secret::LOWER),
+            "--digits" => classes.push(secret::DIGITS),
+            "--symbols" => classes.push(secret::SYMBOLS),
+            "--alphabet" => {
+                if alphabet.is_some() {
+                    exit_with_usage(usage, &format!("'{}' cannot be given twice", name));
+                }
+                alphabet = Some(take_value(usage, name, inline, &mut args));
             }
+            "--exclude-ambiguous" => exclude_ambiguous = true,
+            "-n" | "--count" => count = parse_number(usage, name, inline, &mut args),
+            _ => exit_with_usage(usage, &format!("unrecognized argument '{}'", arg)),
+        }
+    }
+
+    if classes.is_empty() && alphabet.is_none() {
+        classes = vec![
+            secret::UPPER,
+            secret::LOWER,
+            secret::DIGITS,
+            secret::SYMBOLS,
+        ];
+    }
+    classes.sort_unstable();
+    classes.dedup();
+    classes.extend(alphabet.as_deref());
+    let generator = PasswordGenerator::new(&classes, length, exclude_ambiguous)
+        .unwrap_or_else(|e| exit_with_usage(usage, &format!("{}", e)));
 
-            const _: () = assert!(RESEED_INTERVAL % BUF_SIZE == 0);
-            for _ in 0..(RESEED_INTERVAL / BUF_SIZE) {
-                for e in buf_rands.iter_mut() {
-                    // xorshift64* (Vigna 2016)
-                    s ^= s >> 12;
-                    s ^= s << 25;
-                    s ^= s >> 27;
-                    *e = s.wrapping_mul(2685821657736338717);
+    let mut stream = secure_stream();
+    let mut out = io::stdout().lock();
+    eprintln!(
+        "gen-random: {:.1} bits of entropy",
+        generator.entropy_bits()
+    );
+    ignore_broken_pipe(
+        (0..count).try_for_each(|_| writeln!(out, "{}", generator.generate(&mut stream)?)),
+    )
+}
+
+/// Runs the `passphrase` subcommand.
+fn passphrase(mut args: impl Iterator<Item = String>) -> io::Result<()> {
+    let usage = PASSPHRASE_USAGE;
+    let mut wordlist = None;
+    let mut words = 6;
+    let mut separator = String::from(" ");
+    let mut count = 1;
+
+    while let Some(arg) = args.next() {
+        let (name, inline) = split_arg(&arg);
+        match name {
+            "-h" | "--help" => {
+                print!("{}", usage);
+                return Ok(());
+            }
+            "--wordlist" => wordlist = Some(take_value(usage, name, inline, &mut args)),
+            "-w" | "--words" => words = parse_number(usage, name, inline, &mut args),
+            "--separator" => separator = take_value(usage, name, inline, &mut args),
+            "-n" | "--count" => count = parse_number(usage, name, inline, &mut args),
+            _ => exit_with_usage(usage, &format!("unrecognized argument '{}'", arg)),
+        }
+    }
+
+    let path = wordlist.unwrap_or_else(|| exit_with_usage(usage, "'--wordlist' is required"));
+    let text = fs::read_to_string(&path).unwrap_or_else(|e| {
+        eprintln!("gen-random: cannot read '{}': {}", path, e);
+        process::exit(1);
+    });
+    let wordlist = Wordlist::parse(&text)
+        .unwrap_or_else(|e| exit_with_usage(usage, &format!("'{}': {}", path, e)));
+
+    let mut stream = secure_stream();
+    let mut out = io::stdout().lock();
+    eprintln!(
+        "gen-random: {:.1} bits of entropy ({} words from a list of {})",
+        wordlist.entropy_bits(words),
+        words,
+        wordlist.len()
+    );
+    ignore_broken_pipe((0..count).try_for_each(|_| {
+        writeln!(
+            out,
+            "{}",
+            wordlist.passphrase(&mut stream, words, &separator)?
+        )
+    }))
+}
+
+/// Runs the `ids` subcommand.
+fn identifiers(mut args: impl Iterator<Item = String>) -> io::Result<()> {
+    let usage = IDS_USAGE;
+    let mut kind = None;
+    let mut count = 1;
+    let mut alphabet = None;
+    let mut length = None;
+    let mut worker = None;
+    let mut algorithm = Algorithm::ChaCha20;
+
+    while let Some(arg) = args.next() {
+        let (name, inline) = split_arg(&arg);
+        match name {
+            "-h" | "--help" => {
+                print!("{}", usage);
+                return Ok(());
+            }
+            "-n" | "--count" => count = parse_number(usage, name, inline, &mut args),
+            "--alphabet" => {
+                let value = take_value(usage, name, inline, &mut args);
+                if value.is_empty() {
+                    exit_with_usage(usage, &format!("empty alphabet for '{}'", name));
                 }
+                alphabet = Some(value);
+            }
+            "--length" => length = Some(parse_number(usage, name, inline, &mut args)),
+            "--worker" => {
+                let value = take_value(usage, name, inline, &mut args);
+                worker = Some(
+                    value
+                        .parse()
+                        .ok()
+                        .filter(|&n| n <= ids::MAX_WORKER)
+                        .unwrap_or_else(|| {
+                            exit_with_usage(
+                                usage,
+                                &format!("invalid worker ID for '{}': '{}'", name, value),
+                            )
+                        }),
+                );
+            }
+            "-a" | "--algo" => {
+                let value = take_value(usage, name, inline, &mut args);
+                algorithm = value
+                    .parse()
+                    .unwrap_or_else(|e| exit_with_usage(usage, &format!("{}", e)));
+            }
+            _ if kind.is_none() && !arg.starts_with('-') => {
+                kind = Some(
+                    arg.parse::<IdKind>()
+                        .unwrap_or_else(|e| exit_with_usage(usage, &format!("{}", e))),
+                );
+            }
+            _ => exit_with_usage(usage, &format!("unrecognized argument '{}'", arg)),
+        }
+    }
+
+    let kind = kind.unwrap_or_default();
+    if (alphabet.is_some() || length.is_some()) && kind != IdKind::Nanoid {
+        exit_with_usage(usage, "'--alphabet' and '--length' require nanoid");
+    }
+    if worker.is_some() && kind != IdKind::Snowflake {
+        exit_with_usage(usage, "'--worker' requires snowflake");
+    }
+    let mut generator = IdGenerator::new(kind);
+    if let Some(alphabet) = alphabet {
+        generator = generator.alphabet(&alphabet);
+    }
+    if let Some(length) = length {
+        generator = generator.length(length);
+    }
+    if let Some(worker) = worker {
+        generator = generator.worker(worker);
+    }
+
+    let mut stream = gen_random::Builder::new().algorithm(algorithm).build();
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    let ret = (0..count).try_for_each(|_| writeln!(out, "{}", generator.generate(&mut stream)?));
+    ignore_broken_pipe(ret.and_then(|()| out.flush()))
+}
+
+/// Runs the `wipe` subcommand.
+fn wipe(mut args: impl Iterator<Item = String>) -> io::Result<()> {
+    let usage = WIPE_USAGE;
+    let mut path = None;
+    let mut passes = Vec::new();
+    let mut seed = None;
+    let mut algorithm = Algorithm::ChaCha20;
+    let mut direct = false;
+    let mut verify = true;
+
+    while let Some(arg) = args.next() {
+        let (name, inline) = split_arg(&arg);
+        match name {
+            "-h" | "--help" => {
+                print!("{}", usage);
+                return Ok(());
+            }
+            "-p" | "--pass" => {
+                let value = take_value(usage, name, inline, &mut args);
+                passes.push(
+                    value
+                        .parse::<Pass>()
+                        .unwrap_or_else(|e| exit_with_usage(usage, &format!("{}", e))),
+                );
+            }
+            "--seed" => {
+                let value = take_value(usage, name, inline, &mut args);
+                seed = Some(parse_seed(&value).unwrap_or_else(|| {
+                    exit_with_usage(usage, &format!("invalid seed for '{}': '{}'", name, value))
+                }));
+            }
+            "-a" | "--algo" => {
+                let value = take_value(usage, name, inline, &mut args);
+                algorithm = value
+                    .parse()
+                    .unwrap_or_else(|e| exit_with_usage(usage, &format!("{}", e)));
+            }
+            "--direct" => direct = true,
+            "--no-verify" => verify = false,
+            _ if path.is_none() && !arg.starts_with('-') => path = Some(arg),
+            _ => exit_with_usage(usage, &format!("unrecognized argument '{}'", arg)),
+        }
+    }
+
+    let Some(path) = path else {
+        exit_with_usage(usage, "missing PATH");
+    };
+    if passes.is_empty() {
+        passes.push(Pass::Random);
+    }
+    let count = passes.len();
+    let mut wipe = Wipe::new(passes)
+        .algorithm(algorithm)
+        .direct(direct)
+        .verify(verify);
+    if let Some(seed) = seed {
+        wipe = wipe.seed(&seed);
+    }
 
-                match out.write_all(buf_rands.as_bytes()) {
-                    Err(e) if e.kind() == io::ErrorKind::BrokenPipe => return Ok(()),
-                    ret => ret?,
+    let mut failed = false;
+    wipe.run(&path, |report| {
+        let status = match (report.verified, report.mismatched_bytes) {
+            (false, _) => "written".to_owned(),
+            (true, 0) => "verified".to_owned(),
+            (true, n) => format!("{} bytes mismatched", n),
+        };
+        eprintln!(
+            "pass {}/{} ({}): {} bytes, {}",
+            report.index + 1,
+            count,
+            report.pass,
+            report.bytes,
+            status
+        );
+        for range in &report.mismatches {
+            eprintln!("  mismatch at {}..{}", range.start, range.end);
+        }
+        let listed: u64 = report.mismatches.iter().map(|r| r.end - r.start).sum();
+        if listed < report.mismatched_bytes {
+            eprintln!("  and {} more bytes", report.mismatched_bytes - listed);
+        }
+        failed |= report.mismatched_bytes > 0;
+    })
+    .unwrap_or_else(|e| {
+        eprintln!("gen-random: cannot wipe '{}': {}", path, e);
+        process::exit(1);
+    });
+    if failed {
+        process::exit(1);
+    }
+    Ok(())
+}
+
+/// Runs the `test` subcommand.
+fn test(mut args: impl Iterator<Item = String>) -> io::Result<()> {
+    let usage = TEST_USAGE;
+    let mut count = None;
+    let mut algorithm = Algorithm::default();
+    let mut seed = None;
+    let mut stdin = false;
+    let mut alpha = 0.01;
+    let mut sp800_22 = false;
+    let mut sequences = None;
+    let mut sequence_len = None;
+
+    while let Some(arg) = args.next() {
+        let (name, inline) = split_arg(&arg);
+        match name {
+            "-h" | "--help" => {
+                print!("{}", usage);
+                return Ok(());
+            }
+            "-n" | "--count" => {
+                let value = take_value(usage, name, inline, &mut args);
+                count = Some(parse_size(&value).unwrap_or_else(|| {
+                    exit_with_usage(usage, &format!("invalid size for '{}': '{}'", name, value))
+                }));
+            }
+            "-a" | "--algo" => {
+                let value = take_value(usage, name, inline, &mut args);
+                algorithm = value
+                    .parse()
+                    .unwrap_or_else(|e| exit_with_usage(usage, &format!("{}", e)));
+            }
+            "--seed" => {
+                let value = take_value(usage, name, inline, &mut args);
+                seed = Some(parse_seed(&value).unwrap_or_else(|| {
+                    exit_with_usage(usage, &format!("invalid seed for '{}': '{}'", name, value))
+                }));
+            }
+            "--stdin" => stdin = true,
+            "--alpha" => {
+                let value = take_value(usage, name, inline, &mut args);
+                alpha = value
+                    .parse()
+                    .ok()
+                    .filter(|a| (0.0..1.0).contains(a))
+                    .unwrap_or_else(|| {
+                        exit_with_usage(
+                            usage,
+                            &format!("invalid significance level for '{}': '{}'", name, value),
+                        )
+                    });
+            }
+            "--sp800-22" => sp800_22 = true,
+            "--sequences" => {
+                sequences = Some(parse_number(usage, name, inline, &mut args));
+                if sequences == Some(0) {
+                    exit_with_usage(usage, &format!("invalid number for '{}': '0'", name));
+                }
+            }
+            "--sequence-len" => {
+                sequence_len = Some(parse_number(usage, name, inline, &mut args));
+                if sequence_len == Some(0) {
+                    exit_with_usage(usage, &format!("invalid number for '{}': '0'", name));
                 }
             }
+            _ => exit_with_usage(usage, &format!("unrecognized argument '{}'", arg)),
+        }
+    }
+    if stdin && seed.is_some() {
+        exit_with_usage(usage, "'--seed' cannot be used with '--stdin'");
+    }
+    if !sp800_22 && (sequences.is_some() || sequence_len.is_some()) {
+        exit_with_usage(
+            usage,
+            "'--sequences' and '--sequence-len' require '--sp800-22'",
+        );
+    }
+    if sp800_22 && count.is_some() {
+        exit_with_usage(usage, "'--count' cannot be used with '--sp800-22'");
+    }
+
+    // writes the bytes to test to `sink`
+    let feed = |sink: &mut dyn io::Write, count: Option<u64>| -> io::Result<()> {
+        if stdin {
+            // the tests keep the input in memory, so an endless stdin must not be read whole
+            let input = io::Read::take(io::stdin().lock(), count.unwrap_or(1 << 20));
+            io::copy(&mut { input }, sink)?;
+            Ok(())
+        } else {
+            let mut builder = gen_random::Builder::new().algorithm(algorithm);
+            if let Some(seed) = &seed {
+                builder = builder.seed(seed);
+            }
+            builder
+                .build()
+                .write_to(sink, Some(count.unwrap_or(1 << 20)))
+        }
+    };
+
+    if sp800_22 {
+        let sequences = sequences.unwrap_or(100);
+        let sequence_len = sequence_len.unwrap_or(1_000_000);
+        let mut battery = sp800_22::Battery::new(sequence_len);
+        let bits = (sequences as u64)
+            .checked_mul(sequence_len as u64)
+            .unwrap_or_else(|| {
+                exit_with_usage(usage, "'--sequences' times '--sequence-len' is too large")
+            });
+        feed(&mut battery, Some(bits.div_ceil(8)))?;
+        let report = battery.finish();
+        if report.sequences == 0 {
+            eprintln!(
+                "gen-random: input shorter than a sequence of {} bits",
+                sequence_len
+            );
+            process::exit(1);
         }
+        return print_sp800_22_report(&report, alpha);
     }
+
+    let mut suite = Suite::new();
+    feed(&mut suite, count)?;
+
+    let bytes = suite.bits() / 8;
+    let results = suite.finish();
+    let mut out = io::stdout().lock();
+    writeln!(out, "{:<28} {:>10}  Result", "Test", "P-value")?;
+    let mut failed = false;
+    for result in &results {
+        let (p_value, verdict) = match (result.p_value, result.passed(alpha)) {
+            (Some(p), Some(passed)) => {
+                failed |= !passed;
+                (format!("{:.6}", p), if passed { "pass" } else { "FAIL" })
+            }
+            _ => ("-".to_owned(), "skipped"),
+        };
+        writeln!(out, "{:<28} {:>10}  {}", result.name, p_value, verdict)?;
+    }
+    writeln!(
+        out,
+        "\n{} bytes tested, significance level {}",
+        bytes, alpha
+    )?;
+    if failed {
+        out.flush()?;
+        process::exit(1);
+    }
+    Ok(())
 }
 
-#[cfg(test)]
-#[test]
-fn quick_randomness_test() {
-    const N: usize = 1024 * 1024 * 1024;
+/// Prints `report` like the final analysis report of the NIST reference implementation, exiting
+/// with status 1 if any test fails.
+fn print_sp800_22_report(report: &sp800_22::Report, alpha: f64) -> io::Result<()> {
+    let mut out = io::stdout().lock();
+    writeln!(
+        out,
+        " C1  C2  C3  C4  C5  C6  C7  C8  C9 C10   P-VALUE  PROPORTION  STATISTICAL TEST"
+    )?;
+    writeln!(out, "{}", "-".repeat(86))?;
+    let mut failed = false;
+    for row in &report.rows {
+        for bin in row.histogram() {
+            write!(out, "{:3} ", bin)?;
+        }
+        match (row.uniformity(), row.uniformity_passed()) {
+            (Some(p), Some(passed)) => {
+                failed |= !passed;
+                write!(out, " {:.6} {}", p, if passed { ' ' } else { '*' })?;
+            }
+            _ => write!(out, " {:>8}  ", "----")?,
+        }
+        if row.p_values.is_empty() {
+            write!(out, "  {:>9}  ", "----")?;
+        } else {
+            let passed = row.proportion_passed(alpha);
+            failed |= !passed;
+            let proportion = format!("{}/{}", row.passed(alpha), row.p_values.len());
+            write!(
+                out,
+                "  {:>9} {}",
+                proportion,
+                if passed { ' ' } else { '*' }
+            )?;
+        }
+        writeln!(out, " {}", row.name)?;
+    }
+    writeln!(out, "{}", "-".repeat(86))?;
+    writeln!(
+        out,
+        "{} sequences tested. A test is marked with * if the proportion of its k p-values of at\n\
+         least {a} is below {p} - 3 * sqrt({p} * {a} / k), or if the uniformity of at least {}\n\
+         p-values has a p-value below {}.",
+        report.sequences,
+        sp800_22::MIN_UNIFORMITY_SAMPLES,
+        sp800_22::UNIFORMITY_ALPHA,
+        a = alpha,
+        p = 1.0 - alpha,
+    )?;
+    if failed {
+        out.flush()?;
+        process::exit(1);
+    }
+    Ok(())
+}
+
+/// Runs the `entropy` subcommand.
+fn entropy(mut args: impl Iterator<Item = String>) -> io::Result<()> {
+    let usage = ENTROPY_USAGE;
+    let mut path = None;
+    let mut bits = 8;
+    let mut count = None;
+    let mut sample_getrandom = false;
+    let mut truncate = false;
+    let mut iid = false;
+    let mut seed = None;
 
-    #[derive(Default)]
-    struct Logger {
-        n_bytes: usize,
-        n_ones: usize,
-        carry: u8,
-        n_twins: usize,
+    while let Some(arg) = args.next() {
+        let (name, inline) = split_arg(&arg);
+        match name {
+            "-h" | "--help" => {
+                print!("{}", usage);
+                return Ok(());
+            }
+            "-b" | "--bits" => {
+                bits = parse_number(usage, name, inline, &mut args);
+                if !(1..=8).contains(&bits) {
+                    exit_with_usage(usage, &format!("invalid number for '{}': '{}'", name, bits));
+                }
+            }
+            "-n" | "--count" => count = Some(parse_number(usage, name, inline, &mut args)),
+            "--getrandom" => sample_getrandom = true,
+            "--truncate" => truncate = true,
+            "--iid" => iid = true,
+            "--seed" => {
+                let value = take_value(usage, name, inline, &mut args);
+                seed = Some(parse_seed(&value).unwrap_or_else(|| {
+                    exit_with_usage(usage, &format!("invalid seed for '{}': '{}'", name, value))
+                }));
+            }
+            _ if path.is_none() && (arg == "-" || !arg.starts_with('-')) => path = Some(arg),
+            _ => exit_with_usage(usage, &format!("unrecognized argument '{}'", arg)),
+        }
+    }
+    if sample_getrandom && path.is_some() {
+        exit_with_usage(usage, "FILE cannot be used with '--getrandom'");
+    }
+    if seed.is_some() && !iid {
+        exit_with_usage(usage, "'--seed' requires '--iid'");
     }
 
-    impl io::Write for Logger {
-        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-            if self.n_bytes >= N {
-                return Err(io::ErrorKind::BrokenPipe.into());
+    let mut samples = Vec::new();
+    if sample_getrandom {
+        samples.resize(count.unwrap_or(1_000_000), 0);
+        getrandom::getrandom(&mut samples)?;
+    } else {
+        let limit = count.map_or(u64::MAX, |n| n as u64);
+        let ret = match path.as_deref() {
+            None | Some("-") => io::stdin().lock().take(limit).read_to_end(&mut samples),
+            Some(path) => {
+                fs::File::open(path).and_then(|file| file.take(limit).read_to_end(&mut samples))
             }
+        };
+        if let Err(e) = ret {
+            eprintln!(
+                "gen-random: cannot read '{}': {}",
+                path.as_deref().unwrap_or("-"),
+                e
+            );
+            process::exit(1);
+        }
+    }
+    let mask = ((1u32 << bits) - 1) as u8;
+    samples.iter_mut().for_each(|s| *s &= mask);
+    if samples.len() < 2 {
+        eprintln!("gen-random: too few samples to estimate");
+        process::exit(1);
+    }
+    if samples.len() < 1_000_000 {
+        eprintln!(
+            "gen-random: SP 800-90B requires at least 1000000 samples, got {}",
+            samples.len()
+        );
+    }
 
-            for &e in buf {
-                self.n_ones += e.count_ones() as usize;
+    let assessment = sp800_90b::NonIid::new(bits as u32)
+        .truncate_bitstring(truncate)
+        .assess(&samples);
+    let mut out = io::stdout().lock();
+    let print_estimates = |out: &mut io::StdoutLock, estimates: &[sp800_90b::Estimate]| {
+        estimates.iter().try_for_each(|e| match e.min_entropy {
+            Some(h) => writeln!(out, "{:<24} {:>10.6}", e.name, h),
+            None => writeln!(out, "{:<24} {:>10}", e.name, "-"),
+        })
+    };
+    let format_estimate = |h: Option<f64>| h.map_or("-".to_owned(), |h| format!("{:.6}", h));
+    writeln!(
+        out,
+        "{} samples of {} bits, min-entropy per sample:",
+        samples.len(),
+        bits
+    )?;
+    print_estimates(&mut out, &assessment.estimates)?;
+    if bits > 1 {
+        writeln!(
+            out,
+            "\nBitstring of {} bits, min-entropy per bit:",
+            assessment.bitstring_len
+        )?;
+        print_estimates(&mut out, &assessment.bitstring)?;
+        writeln!(
+            out,
+            "\nH_original: {}\nH_bitstring: {}",
+            format_estimate(assessment.original()),
+            format_estimate(assessment.bitstring())
+        )?;
+    }
+    writeln!(
+        out,
+        "\nMin-entropy: {} bits per sample",
+        format_estimate(assessment.min_entropy())
+    )?;
 
-                let shifted = self.carry | e >> 1;
-                self.carry = e << 7;
-                self.n_twins += (e ^ shifted).count_zeros() as usize;
+    if iid {
+        let mut builder = gen_random::Builder::new();
+        if let Some(seed) = &seed {
+            builder = builder.seed(seed);
+        }
+        out.flush()?;
+        let tests = sp800_90b::permutation_tests(
+            &samples,
+            bits as u32,
+            sp800_90b::PERMUTATIONS,
+            &mut builder.build(),
+        )?;
+        writeln!(
+            out,
+            "\n{:<36} {:>8} {:>8}  Result",
+            "Permutation test", "Greater", "Equal"
+        )?;
+        let mut rejected = false;
+        for test in &tests {
+            rejected |= !test.passed();
+            writeln!(
+                out,
+                "{:<36} {:>8} {:>8}  {}",
+                test.name,
+                test.greater,
+                test.equal,
+                if test.passed() { "pass" } else { "FAIL" }
+            )?;
+        }
+        writeln!(
+            out,
+            "\nThe IID assumption is {}: a test fails if the statistic of the samples ranks among the\n\
+             five highest or lowest of {} shuffles.",
+            if rejected { "rejected" } else { "not rejected" },
+            sp800_90b::PERMUTATIONS
+        )?;
+        if !cfg!(feature = "bzip2") && !rejected {
+            writeln!(
+                out,
+                "The verdict is partial: the compression statistic of SP 800-90B needs the 'bzip2'\n\
+                 feature and was not computed."
+            )?;
+        }
+        if rejected {
+            out.flush()?;
+            process::exit(1);
+        }
+    }
+    Ok(())
+}
+
+/// Runs the `stress` subcommand.
+fn stress(mut args: impl Iterator<Item = String>) -> io::Result<()> {
+    let usage = STRESS_USAGE;
+    let mut count = 1 << 40;
+    let mut algorithm = Algorithm::default();
+    let mut seed = None;
+    let mut reseed_interval = None;
+
+    while let Some(arg) = args.next() {
+        let (name, inline) = split_arg(&arg);
+        match name {
+            "-h" | "--help" => {
+                print!("{}", usage);
+                return Ok(());
+            }
+            "-n" | "--count" => {
+                let value = take_value(usage, name, inline, &mut args);
+                count = parse_size(&value).filter(|&n| n > 0).unwrap_or_else(|| {
+                    exit_with_usage(usage, &format!("invalid size for '{}': '{}'", name, value))
+                });
             }
+            "--reseed-interval" => {
+                let value = take_value(usage, name, inline, &mut args);
+                reseed_interval = Some(
+                    parse_size(&value)
+                        .filter(|&n| n > 0 && n.is_multiple_of(8))
+                        .and_then(|n| usize::try_from(n).ok())
+                        .unwrap_or_else(|| {
+                            exit_with_usage(
+                                usage,
+                                &format!("invalid size for '{}': '{}'", name, value),
+                            )
+                        }),
+                );
+            }
+            "-a" | "--algo" => {
+                let value = take_value(usage, name, inline, &mut args);
+                algorithm = value
+                    .parse()
+                    .unwrap_or_else(|e| exit_with_usage(usage, &format!("{}", e)));
+            }
+            "--seed" => {
+                let value = take_value(usage, name, inline, &mut args);
+                seed = Some(parse_seed(&value).unwrap_or_else(|| {
+                    exit_with_usage(usage, &format!("invalid seed for '{}': '{}'", name, value))
+                }));
+            }
+            _ => exit_with_usage(usage, &format!("unrecognized argument '{}'", arg)),
+        }
+    }
+    if algorithm.is_drbg() && reseed_interval.is_some() {
+        exit_with_usage(
+            usage,
+            "'--reseed-interval' cannot be used with DRBG algorithms",
+        );
+    }
+    let seed = match seed {
+        Some(seed) => seed,
+        None => {
+            let mut bytes = vec![0; 16];
+            getrandom::getrandom(&mut bytes)?;
+            bytes
+        }
+    };
+    let mut hex = String::from("0x");
+    hex.extend(seed.iter().map(|b| format!("{:02x}", b)));
 
-            self.n_bytes += buf.len();
-            Ok(buf.len())
+    // a generator that is never reseeded is tested on its whole period, not on short pieces of
+    // many; DRBG algorithms reseed only after 2^48 requests
+    let (reseed, interval) = match reseed_interval {
+        Some(bytes) => (describe_length(bytes as u64), bytes),
+        None => {
+            let bytes = count
+                .checked_next_multiple_of(8)
+                .and_then(|n| usize::try_from(n).ok())
+                .unwrap_or(usize::MAX & !7);
+            ("none".to_owned(), bytes)
         }
+    };
+    let mut stream = gen_random::Builder::new()
+        .algorithm(algorithm)
+        .seed(&seed)
+        .reseed_interval(interval)
+        .build();
+    let mut battery = stress::Battery::new();
+    let mut out = io::stdout().lock();
+    writeln!(out, "rng={}, seed={}, reseed={}", algorithm, hex, reseed)?;
 
-        fn flush(&mut self) -> io::Result<()> {
-            Ok(())
+    let start = std::time::Instant::now();
+    let mut length = 1 << 20;
+    loop {
+        let target = length.min(count);
+        let remaining = target - battery.bytes();
+        stream.write_to(&mut battery, Some(remaining))?;
+        let results = battery.results();
+
+        let mut anomalies: Vec<_> = results
+            .iter()
+            .filter_map(|result| {
+                let p = result.p_value?;
+                Some((result, p, stress::Evaluation::of(p)?))
+            })
+            .collect();
+        anomalies.sort_by_key(|&(_, _, evaluation)| std::cmp::Reverse(evaluation));
+        let tested = results.iter().filter(|r| r.p_value.is_some()).count();
+
+        writeln!(
+            out,
+            "length= {}, time= {:.1} seconds",
+            describe_length(target),
+            start.elapsed().as_secs_f64()
+        )?;
+        if anomalies.is_empty() {
+            writeln!(out, "  no anomalies in {} test result(s)", tested)?;
+        } else {
+            writeln!(out, "  {:<32} {:>12}   Evaluation", "Test Name", "P-value")?;
+            for (result, p, evaluation) in &anomalies {
+                let p_value = if *p > 0.5 {
+                    format!("1-{:.1e}", 1.0 - p)
+                } else {
+                    format!("{:.1e}", p)
+                };
+                writeln!(
+                    out,
+                    "  {:<32} {:>12}   {}",
+                    result.name, p_value, evaluation
+                )?;
+            }
+            writeln!(
+                out,
+                "  ...and {} test result(s) without anomalies",
+                tested - anomalies.len()
+            )?;
+        }
+        out.flush()?;
+
+        if anomalies
+            .iter()
+            .any(|&(_, _, evaluation)| evaluation == stress::Evaluation::Fail)
+        {
+            process::exit(1);
+        }
+        if target == count {
+            return Ok(());
         }
+        length *= 2;
     }
+}
+
+/// Describes a tested length like PractRand, e.g. `64 MiB (2^26 bytes)`.
+fn describe_length(bytes: u64) -> String {
+    if !bytes.is_power_of_two() {
+        return format!("{} bytes", bytes);
+    }
+    let exp = bytes.trailing_zeros();
+    let unit = ["bytes", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"][exp as usize / 10];
+    format!("{} {} (2^{} bytes)", 1u64 << (exp % 10), unit, exp)
+}
 
-    let mut w = Logger::default();
-    assert!(run(&mut w).is_ok() && w.n_bytes >= N);
+/// Returns a stream of the secure generator used for passwords and passphrases.
+fn secure_stream() -> gen_random::RandomStream {
+    gen_random::Builder::new()
+        .algorithm(Algorithm::ChaCha20)
+        .build()
+}
+
+/// Splits `--name=value` into the option name and its inline value.
+fn split_arg(arg: &str) -> (&str, Option<String>) {
+    match arg.split_once('=') {
+        Some((name, value)) if name.starts_with("--") => (name, Some(value.to_owned())),
+        _ => (arg, None),
+    }
+}
 
-    let n_samples = w.n_bytes as f64 * 8.0;
-    let p_ones = w.n_ones as f64 / n_samples;
-    let p_twins = w.n_twins as f64 / n_samples;
+/// Returns the value of option `name` parsed as a count.
+fn parse_number(
+    usage: &str,
+    name: &str,
+    inline: Option<String>,
+    args: &mut impl Iterator<Item = String>,
+) -> usize {
+    let value = take_value(usage, name, inline, args);
+    value.parse().unwrap_or_else(|_| {
+        exit_with_usage(
+            usage,
+            &format!("invalid number for '{}': '{}'", name, value),
+        )
+    })
+}
 
-    // set margin based on binom dist 99.999% confidence interval
-    let margin = 4.417173 * (0.5 * 0.5 / n_samples).sqrt();
+/// Returns the value of option `name` given inline (`--name=value`) or as the next argument.
+fn take_value(
+    usage: &str,
+    name: &str,
+    inline: Option<String>,
+    args: &mut impl Iterator<Item = String>,
+) -> String {
+    inline
+        .or_else(|| args.next())
+        .unwrap_or_else(|| exit_with_usage(usage, &format!("option '{}' requires a value", name)))
+}
 
-    assert!(
-        (p_ones - 0.5).abs() < margin,
-        "% of set bits: {}% ({}/{}; 99.999% CI: {}%-{}%)",
-        p_ones * 100.0,
-        w.n_ones,
-        w.n_bytes * 8,
-        (0.5 - margin) * 100.0,
-        (0.5 + margin) * 100.0,
-    );
-    assert!(
-        (p_twins - 0.5).abs() < margin,
-        "% of twin (00/11) bits: {}% ({}/{}; 99.999% CI: {}%-{}%)",
-        p_twins * 100.0,
-        w.n_twins,
-        w.n_bytes * 8,
-        (0.5 - margin) * 100.0,
-        (0.5 + margin) * 100.0,
+fn exit_with_usage(usage: &str, message: &str) -> ! {
+    eprint!("gen-random: {}\n\n{}", message, usage);
+    process::exit(2);
+}
+
+/// Parses a byte count with an optional SI (`k`, `M`, `G`, ...; powers of 1000) or IEC (`Ki`,
+/// `Mi`, `Gi`, ...; powers of 1024) suffix, optionally followed by `B`.
+fn parse_size(src: &str) -> Option<u64> {
+    let digits = src.find(|c: char| !c.is_ascii_digit()).unwrap_or(src.len());
+    let (num, suffix) = src.split_at(digits);
+    let num: u64 = num.parse().ok()?;
+
+    let suffix = suffix.strip_suffix('B').unwrap_or(suffix);
+    let (prefix, base) = match suffix.strip_suffix('i') {
+        Some(prefix) if !prefix.is_empty() => (prefix, 1024u64),
+        _ => (suffix, 1000u64),
+    };
+    let exp = match prefix {
+        "" => 0,
+        "k" | "K" => 1,
+        "M" => 2,
+        "G" => 3,
+        "T" => 4,
+        "P" => 5,
+        "E" => 6,
+        _ => return None,
+    };
+    if exp == 0 && base == 1024 {
+        return None;
+    }
+    num.checked_mul(base.checked_pow(exp)?)
+}
+
+/// Parses a seed given as a decimal `u64`, which is taken as its eight little-endian bytes, or as
+/// a hex string of bytes, optionally prefixed with `0x`.
+///
+/// Strings of only decimal digits are always decimal, so that a number too large for `u64` is
+/// rejected rather than taken as hex.
+fn parse_seed(src: &str) -> Option<Vec<u8>> {
+    if src.bytes().all(|c| c.is_ascii_digit()) {
+        return src.parse::<u64>().ok().map(|n| n.to_le_bytes().to_vec());
+    }
+    let hex = src.strip_prefix("0x").unwrap_or(src).as_bytes();
+    if hex.is_empty() || !hex.len().is_multiple_of(2) || !hex.iter().all(u8::is_ascii_hexdigit) {
+        return None;
+    }
+    hex.chunks_exact(2)
+        .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+#[test]
+fn parse_seed_forms() {
+    assert_eq!(parse_seed("42"), Some(vec![42, 0, 0, 0, 0, 0, 0, 0]));
+    assert_eq!(parse_seed("0x0042"), Some(vec![0x00, 0x42]));
+    assert_eq!(parse_seed("deadBEEF"), Some(vec![0xde, 0xad, 0xbe, 0xef]));
+    assert_eq!(parse_seed("18446744073709551616"), None);
+    assert_eq!(
+        parse_seed("0x18446744073709551616"),
+        Some(vec![
+            0x18, 0x44, 0x67, 0x44, 0x07, 0x37, 0x09, 0x55, 0x16, 0x16
+        ])
     );
+    assert_eq!(parse_seed(""), None);
+    assert_eq!(parse_seed("0x"), None);
+    assert_eq!(parse_seed("abc"), None);
+    assert_eq!(parse_seed("+1"), None);
+    assert_eq!(parse_seed("xyz0"), None);
+    assert_eq!(parse_seed("é0"), None);
+}
+
+#[cfg(test)]
+#[test]
+fn parse_size_suffixes() {
+    assert_eq!(parse_size("0"), Some(0));
+    assert_eq!(parse_size("4096"), Some(4096));
+    assert_eq!(parse_size("10G"), Some(10_000_000_000));
+    assert_eq!(parse_size("10GB"), Some(10_000_000_000));
+    assert_eq!(parse_size("3k"), Some(3_000));
+    assert_eq!(parse_size("4KiB"), Some(4096));
+    assert_eq!(parse_size("2Mi"), Some(2 * 1024 * 1024));
+    assert_eq!(parse_size("1EiB"), Some(1 << 60));
+    assert_eq!(parse_size("16EiB"), None);
+    assert_eq!(parse_size(""), None);
+    assert_eq!(parse_size("G"), None);
+    assert_eq!(parse_size("1iB"), None);
+    assert_eq!(parse_size("1X"), None);
+    assert_eq!(parse_size("-1"), None);
 }
@@ -0,0 +1,571 @@
+//! Numbers read from the byte stream and written as decimal text.
+
+use std::{fmt, io, str::FromStr};
+
+use zerocopy::AsBytes as _;
+
+use crate::{RandomStream, BUF_SIZE};
+
+/// Number of text bytes buffered before they are written to the inner writer.
+const OUT_BUF_SIZE: usize = 64 * 1024;
+
+/// Number of words read from the stream at a time by [`write_numbers`].
+const BATCH_WORDS: usize = BUF_SIZE / 8;
+
+/// Two-digit decimal strings of 00 to 99.
+const DIGIT_PAIRS: [u8; 200] = {
+    let mut table = [0u8; 200];
+    let mut i = 0;
+    while i < 100 {
+        table[i * 2] = b'0' + (i / 10) as u8;
+        table[i * 2 + 1] = b'0' + (i % 10) as u8;
+        i += 1;
+    }
+    table
+};
+
+/// Numeric type of the values read from the stream.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum NumberFormat {
+    /// `u8`
+    U8,
+    /// `u16`
+    U16,
+    /// `u32`
+    U32,
+    /// `u64`
+    U64,
+    /// `i32`
+    I32,
+    /// `i64`
+    I64,
+    /// `f32` uniformly distributed in [0, 1), from the upper 24 bits of a `u32`.
+    F32,
+    /// `f64` uniformly distributed in [0, 1), from the upper 53 bits of a `u64`.
+    F64,
+}
+
+impl NumberFormat {
+    /// All formats in the order of listing.
+    pub const ALL: [Self; 8] = [
+        Self::U8,
+        Self::U16,
+        Self::U32,
+        Self::U64,
+        Self::I32,
+        Self::I64,
+        Self::F32,
+        Self::F64,
+    ];
+
+    /// Returns the name used to select the format on the command line.
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::U8 => "u8",
+            Self::U16 => "u16",
+            Self::U32 => "u32",
+            Self::U64 => "u64",
+            Self::I32 => "i32",
+            Self::I64 => "i64",
+            Self::F32 => "f32",
+            Self::F64 => "f64",
+        }
+    }
+
+    /// Returns the number of stream bytes consumed per value.
+    pub const fn size(self) -> usize {
+        match self {
+            Self::U8 => 1,
+            Self::U16 => 2,
+            Self::U32 | Self::I32 | Self::F32 => 4,
+            Self::U64 | Self::I64 | Self::F64 => 8,
+        }
+    }
+
+    /// Writes the value of the little-endian `bytes` (of [`NumberFormat::size`]) to `out`.
+    fn write_value<W: io::Write>(self, bytes: &[u8], out: &mut TextWriter<W>) -> io::Result<()> {
+        let mut word = [0u8; 8];
+        word[..bytes.len()].copy_from_slice(bytes);
+        let v = u64::from_le_bytes(word);
+        match self {
+            Self::U8 | Self::U16 | Self::U32 | Self::U64 => out.write_u64(v),
+            Self::I32 => out.write_i64(i64::from(v as u32 as i32)),
+            Self::I64 => out.write_i64(v as i64),
+            Self::F32 => out.write_f32((v as u32 >> 8) as f32 * (1.0 / (1u32 << 24) as f32)),
+            Self::F64 => out.write_f64((v >> 11) as f64 * (1.0 / (1u64 << 53) as f64)),
+        }
+    }
+}
+
+impl fmt::Display for NumberFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Error returned when parsing an unknown number format name.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseNumberFormatError(String);
+
+impl fmt::Display for ParseNumberFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown number format '{}'", self.0)
+    }
+}
+
+impl std::error::Error for ParseNumberFormatError {}
+
+impl FromStr for NumberFormat {
+    type Err = ParseNumberFormatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|f| f.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| ParseNumberFormatError(s.to_owned()))
+    }
+}
+
+/// Byte order of binary output.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum Endian {
+    /// Little-endian.
+    #[default]
+    Little,
+    /// Big-endian.
+    Big,
+}
+
+impl Endian {
+    /// Writes the lowest `width` bytes of `v` to `out`.
+    pub fn write_int(self, out: &mut impl io::Write, v: u128, width: usize) -> io::Result<()> {
+        match self {
+            Self::Little => out.write_all(&v.to_le_bytes()[..width]),
+            Self::Big => out.write_all(&v.to_be_bytes()[(16 - width)..]),
+        }
+    }
+}
+
+impl FromStr for Endian {
+    type Err = ParseEndianError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "little" | "le" => Ok(Self::Little),
+            "big" | "be" => Ok(Self::Big),
+            _ => Err(ParseEndianError(s.to_owned())),
+        }
+    }
+}
+
+/// Error returned when parsing an unknown byte order.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseEndianError(String);
+
+impl fmt::Display for ParseEndianError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown byte order '{}'", self.0)
+    }
+}
+
+impl std::error::Error for ParseEndianError {}
+
+/// Buffered writer of numbers in decimal text, separated by a separator and terminated by a line
+/// break.
+#[derive(Debug)]
+pub struct TextWriter<W: io::Write> {
+    inner: W,
+    separator: Vec<u8>,
+    first: bool,
+    out: Vec<u8>,
+}
+
+impl<W: io::Write> TextWriter<W> {
+    /// Creates a writer that separates numbers by `separator`.
+    pub fn new(inner: W, separator: &[u8]) -> Self {
+        Self {
+            inner,
+            separator: separator.to_vec(),
+            first: true,
+            out: Vec::with_capacity(OUT_BUF_SIZE + 64),
+        }
+    }
+
+    /// Writes an unsigned integer.
+    pub fn write_u64(&mut self, v: u64) -> io::Result<()> {
+        self.begin();
+        push_u64(&mut self.out, v);
+        self.end()
+    }
+
+    /// Writes a signed integer.
+    pub fn write_i64(&mut self, v: i64) -> io::Result<()> {
+        self.begin();
+        if v < 0 {
+            self.out.push(b'-');
+        }
+        push_u64(&mut self.out, v.unsigned_abs());
+        self.end()
+    }
+
+    /// Writes an unsigned 128-bit integer.
+    pub fn write_u128(&mut self, v: u128) -> io::Result<()> {
+        self.begin();
+        push_u128(&mut self.out, v);
+        self.end()
+    }
+
+    /// Writes a signed 128-bit integer.
+    pub fn write_i128(&mut self, v: i128) -> io::Result<()> {
+        self.begin();
+        if v < 0 {
+            self.out.push(b'-');
+        }
+        push_u128(&mut self.out, v.unsigned_abs());
+        self.end()
+    }
+
+    /// Writes a float in the shortest representation that reads back to the same value.
+    pub fn write_f64(&mut self, v: f64) -> io::Result<()> {
+        self.begin();
+        push_float(&mut self.out, ryu::Buffer::new().format(v));
+        self.end()
+    }
+
+    /// Writes a float in the shortest representation that reads back to the same value.
+    pub fn write_f32(&mut self, v: f32) -> io::Result<()> {
+        self.begin();
+        push_float(&mut self.out, ryu::Buffer::new().format(v));
+        self.end()
+    }
+
+    /// Writes the buffered numbers to the inner writer.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.inner.write_all(&self.out)?;
+        self.out.clear();
+        self.inner.flush()
+    }
+
+    /// Terminates the last line if any number has been written and returns the inner writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        if !self.first {
+            self.out.push(b'\n');
+        }
+        self.flush()?;
+        Ok(self.inner)
+    }
+
+    fn begin(&mut self) {
+        if !self.first {
+            self.out.extend_from_slice(&self.separator);
+        }
+        self.first = false;
+    }
+
+    fn end(&mut self) -> io::Result<()> {
+        if self.out.len() >= OUT_BUF_SIZE {
+            self.inner.write_all(&self.out)?;
+            self.out.clear();
+        }
+        Ok(())
+    }
+}
+
+/// Appends the decimal digits of `v` to `out`.
+fn push_u64(out: &mut Vec<u8>, mut v: u64) {
+    let mut buf = [0u8; 20];
+    let mut i = buf.len();
+    while v >= 100 {
+        let r = (v % 100) as usize * 2;
+        v /= 100;
+        i -= 2;
+        buf[i..(i + 2)].copy_from_slice(&DIGIT_PAIRS[r..(r + 2)]);
+    }
+    if v >= 10 {
+        let r = v as usize * 2;
+        i -= 2;
+        buf[i..(i + 2)].copy_from_slice(&DIGIT_PAIRS[r..(r + 2)]);
+    } else {
+        i -= 1;
+        buf[i] = b'0' + v as u8;
+    }
+    out.extend_from_slice(&buf[i..]);
+}
+
+/// Appends the decimal digits of `v` to `out`.
+fn push_u128(out: &mut Vec<u8>, v: u128) {
+    const TEN_19: u128 = 10_000_000_000_000_000_000;
+    if let Ok(v) = u64::try_from(v) {
+        return push_u64(out, v);
+    }
+    // split into 19-digit chunks that fit in u64
+    push_u128(out, v / TEN_19);
+    let start = out.len();
+    push_u64(out, (v % TEN_19) as u64);
+    let n_digits = out.len() - start;
+    out.splice(start..start, std::iter::repeat_n(b'0', 19 - n_digits));
+}
+
+/// Writes the values of [`write_numbers`] from batches of words read from `stream`.
+fn write_batches<W: io::Write>(
+    stream: &mut RandomStream,
+    format: NumberFormat,
+    out: &mut TextWriter<W>,
+    mut count: Option<u64>,
+) -> io::Result<()> {
+    let size = format.size();
+    let batch = BATCH_WORDS * 8 / size;
+    let mut words = vec![0u64; BATCH_WORDS];
+    loop {
+        let n = count.map_or(batch, |left| left.min(batch as u64) as usize);
+        if n == 0 {
+            return Ok(());
+        }
+        if n == batch {
+            stream.fill_words(&mut words)?;
+        } else {
+            // only the bytes of the last values, so that the rest of the stream is left
+            stream.fill_bytes(&mut words.as_bytes_mut()[..(n * size)])?;
+        }
+        for bytes in words.as_bytes()[..(n * size)].chunks_exact(size) {
+            format.write_value(bytes, out)?;
+        }
+        if let Some(left) = count.as_mut() {
+            *left -= n as u64;
+        }
+    }
+}
+
+/// Appends a float formatted by [`ryu`] to `out` without an exponent, like [`fmt::Display`]
+/// (e.g. `1e-7` as `0.0000001` and `1.0` as `1`).
+///
+/// The digits differ from those of [`fmt::Display`] only when two shortest representations are
+/// equally close to the value, a tie that the two break differently (e.g. in 77 of 20 million
+/// `f32` values), and both read back to the same value.
+fn push_float(out: &mut Vec<u8>, src: &str) {
+    let src = src.as_bytes();
+    let (negative, unsigned) = match src.split_first() {
+        Some((b'-', rest)) => (true, rest),
+        _ => (false, src),
+    };
+    if !unsigned.first().is_some_and(u8::is_ascii_digit) {
+        // inf and NaN
+        return out.extend_from_slice(src);
+    }
+    let (mantissa, exp) = match unsigned.iter().position(|&c| c == b'e') {
+        Some(i) => {
+            let exp = std::str::from_utf8(&unsigned[(i + 1)..]).unwrap();
+            (&unsigned[..i], exp.parse::<isize>().unwrap())
+        }
+        None => (unsigned, 0),
+    };
+    let int_len = mantissa
+        .iter()
+        .position(|&c| c == b'.')
+        .unwrap_or(mantissa.len());
+
+    // at most 17 significant digits plus the zero before the point of `0.5`
+    let mut buf = [0u8; 24];
+    let mut len = 0;
+    for &c in mantissa.iter().filter(|&&c| c != b'.') {
+        buf[len] = c;
+        len += 1;
+    }
+    let leading = buf[..len].iter().take_while(|&&c| c == b'0').count();
+    let trailing = buf[leading..len]
+        .iter()
+        .rev()
+        .take_while(|&&c| c == b'0')
+        .count();
+    let digits = &buf[leading..(len - trailing)];
+    let point = int_len as isize + exp - leading as isize;
+
+    if negative {
+        out.push(b'-');
+    }
+    if digits.is_empty() {
+        out.push(b'0');
+    } else if point <= 0 {
+        out.extend_from_slice(b"0.");
+        out.extend(std::iter::repeat_n(b'0', -point as usize));
+        out.extend_from_slice(digits);
+    } else if point as usize >= digits.len() {
+        out.extend_from_slice(digits);
+        out.extend(std::iter::repeat_n(b'0', point as usize - digits.len()));
+    } else {
+        out.extend_from_slice(&digits[..point as usize]);
+        out.push(b'.');
+        out.extend_from_slice(&digits[point as usize..]);
+    }
+}
+
+/// Calls `f` `count` times (or infinitely if `None`) until it fails, and returns `Ok(())` if it
+/// fails with [`io::ErrorKind::BrokenPipe`].
+pub(crate) fn repeat(count: Option<u64>, mut f: impl FnMut() -> io::Result<()>) -> io::Result<()> {
+    let ret = match count {
+        Some(n) => (0..n).try_for_each(|_| f()),
+        None => loop {
+            if let Err(e) = f() {
+                break Err(e);
+            }
+        },
+    };
+    ignore_broken_pipe(ret)
+}
+
+/// Maps the result of a finished writer to `Ok(())`, also when it reports
+/// [`io::ErrorKind::BrokenPipe`], which indicates that the reader has gone away.
+pub fn ignore_broken_pipe<T>(ret: io::Result<T>) -> io::Result<()> {
+    match ret {
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        ret => ret.map(drop),
+    }
+}
+
+/// Writes `count` values of `format` read from `stream` (or infinitely if `None`) to `out`.
+///
+/// The values are read from the stream in batches of words, and exactly `count` values are
+/// consumed from it. Returns `Ok(())` also when `out` reports [`io::ErrorKind::BrokenPipe`].
+pub fn write_numbers<W: io::Write>(
+    stream: &mut RandomStream,
+    format: NumberFormat,
+    out: &mut TextWriter<W>,
+    count: Option<u64>,
+) -> io::Result<()> {
+    ignore_broken_pipe(write_batches(stream, format, out, count))?;
+    ignore_broken_pipe(out.flush())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{push_float, push_u128, push_u64, write_numbers, NumberFormat, TextWriter};
+    use crate::Builder;
+
+    #[test]
+    fn integer_text() {
+        for v in [0, 7, 10, 99, 100, 12345, 1 << 53, u64::MAX] {
+            let mut out = Vec::new();
+            push_u64(&mut out, v);
+            assert_eq!(out, v.to_string().as_bytes());
+        }
+        for v in [
+            u128::from(u64::MAX) + 1,
+            10u128.pow(19) * 7 + 5,
+            10u128.pow(38),
+            u128::MAX,
+        ] {
+            let mut out = Vec::new();
+            push_u128(&mut out, v);
+            assert_eq!(out, v.to_string().as_bytes());
+        }
+
+        let mut w = TextWriter::new(Vec::new(), b", ");
+        w.write_i64(i64::MIN).unwrap();
+        w.write_i64(-1).unwrap();
+        w.write_u64(42).unwrap();
+        w.write_f64(0.5).unwrap();
+        w.write_f32(0.25).unwrap();
+        w.write_i128(i128::MIN).unwrap();
+        let out = w.finish().unwrap();
+        assert_eq!(
+            out,
+            b"-9223372036854775808, -1, 42, 0.5, 0.25, -170141183460469231731687303715884105728\n"
+        );
+        assert_eq!(TextWriter::new(Vec::new(), b"\n").finish().unwrap(), b"");
+    }
+
+    /// Asserts that `text` is `v` printed like [`fmt::Display`], up to the last digit of ties.
+    fn assert_float_text<F>(text: Vec<u8>, v: F)
+    where
+        F: Copy + std::fmt::Display + std::str::FromStr<Err: std::fmt::Debug> + PartialEq,
+    {
+        let text = String::from_utf8(text).unwrap();
+        let expected = v.to_string();
+        if text != expected {
+            assert!(
+                text.len() == expected.len() && text.parse::<F>().unwrap() == v,
+                "{} printed as {}",
+                expected,
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn float_text() {
+        let mut stream = Builder::new().seed(b"floats").build();
+        let specials = [
+            0.0,
+            -0.0,
+            1.0,
+            0.5,
+            1e-7,
+            -1.5e300,
+            123456.789,
+            1e21,
+            f64::MIN_POSITIVE,
+            5e-324,
+            f64::MAX,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            f64::NAN,
+        ];
+        for i in 0..100_000 {
+            let bits = stream.next_u64().unwrap();
+            let v = specials.get(i).copied().unwrap_or(f64::from_bits(bits));
+            let mut out = Vec::new();
+            push_float(&mut out, ryu::Buffer::new().format(v));
+            assert_float_text(out, v);
+
+            let v = specials
+                .get(i)
+                .map_or(f32::from_bits(bits as u32), |&v| v as f32);
+            let mut out = Vec::new();
+            push_float(&mut out, ryu::Buffer::new().format(v));
+            assert_float_text(out, v);
+        }
+    }
+
+    #[test]
+    fn values_from_stream() {
+        for format in NumberFormat::ALL {
+            let mut stream = Builder::new().seed(b"numbers").build();
+            let mut bytes = vec![0u8; format.size() * 1000];
+            stream.fill_bytes(&mut bytes).unwrap();
+
+            let mut stream = Builder::new().seed(b"numbers").build();
+            let mut w = TextWriter::new(Vec::new(), b"\n");
+            write_numbers(&mut stream, format, &mut w, Some(1000)).unwrap();
+            let mut next = Builder::new().seed(b"numbers").build();
+            next.fill_bytes(&mut bytes).unwrap();
+            assert_eq!(
+                stream.next_u64().unwrap(),
+                next.next_u64().unwrap(),
+                "{}: the values must consume exactly their bytes",
+                format
+            );
+            let text = String::from_utf8(w.finish().unwrap()).unwrap();
+            assert_eq!(text.lines().count(), 1000);
+
+            for (line, chunk) in text.lines().zip(bytes.chunks_exact(format.size())) {
+                let mut word = [0u8; 8];
+                word[..chunk.len()].copy_from_slice(chunk);
+                let v = u64::from_le_bytes(word);
+                let ok = match format {
+                    NumberFormat::I32 => line.parse::<i32>() == Ok(v as u32 as i32),
+                    NumberFormat::I64 => line.parse::<i64>() == Ok(v as i64),
+                    NumberFormat::F32 => {
+                        let x: f32 = line.parse().unwrap();
+                        (0.0..1.0).contains(&x) && x == (v as u32 >> 8) as f32 / 16777216.0
+                    }
+                    NumberFormat::F64 => {
+                        let x: f64 = line.parse().unwrap();
+                        (0.0..1.0).contains(&x) && x == (v >> 11) as f64 / 9007199254740992.0
+                    }
+                    _ => line.parse::<u64>() == Ok(v),
+                };
+                assert!(ok, "{}: {} from {:#x}", format, line, v);
+            }
+        }
+    }
+}
@@ -0,0 +1,372 @@
+//! Output to files and block devices, optionally bypassing the page cache.
+
+use std::{
+    fs::{self, File},
+    io::{self, Write as _},
+    mem,
+    path::Path,
+};
+
+use zerocopy::AsBytes as _;
+
+/// Size in bytes of the buffer of [`FileOutput`], rounded up to whole blocks.
+const BUF_LEN: usize = 1024 * 1024;
+
+/// Options for opening a [`FileOutput`].
+#[derive(Clone, Debug, Default)]
+pub struct FileOptions {
+    direct: bool,
+    in_place: bool,
+    sync_interval: Option<u64>,
+    sync_at_end: bool,
+}
+
+impl FileOptions {
+    /// Creates options for buffered writes without syncing.
+    pub const fn new() -> Self {
+        Self {
+            direct: false,
+            in_place: false,
+            sync_interval: None,
+            sync_at_end: false,
+        }
+    }
+
+    /// Bypasses the page cache with `O_DIRECT` (Linux only), writing whole blocks from buffers
+    /// aligned to the logical block size.
+    pub fn direct(mut self, direct: bool) -> Self {
+        self.direct = direct;
+        self
+    }
+
+    /// Overwrites an existing regular file in place instead of creating or truncating it.
+    pub fn in_place(mut self, in_place: bool) -> Self {
+        self.in_place = in_place;
+        self
+    }
+
+    /// Calls `fsync(2)` after every `bytes` bytes written, rounded up to the internal buffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bytes` is zero.
+    pub fn fsync_every(mut self, bytes: u64) -> Self {
+        assert!(bytes > 0, "sync interval must be positive");
+        self.sync_interval = Some(bytes);
+        self
+    }
+
+    /// Calls `fdatasync(2)` in [`FileOutput::finish`].
+    pub fn fdatasync_at_end(mut self, sync: bool) -> Self {
+        self.sync_at_end = sync;
+        self
+    }
+
+    /// Opens `path` for writing, creating or truncating a regular file unless overwriting it in
+    /// place, or overwriting a block device from the start.
+    pub fn open(&self, path: impl AsRef<Path>) -> io::Result<FileOutput> {
+        let mut open = fs::OpenOptions::new();
+        open.write(true).create(!self.in_place);
+        if self.direct {
+            set_direct(&mut open)?;
+        }
+        let file = open.open(path)?;
+        let file_type = file.metadata()?.file_type();
+        if file_type.is_file() && !self.in_place {
+            file.set_len(0)?;
+        }
+
+        let (block_size, device_size) = block_geometry(&file)?;
+        let buf_words = BUF_LEN.div_ceil(block_size) * block_size / mem::size_of::<u64>();
+        Ok(FileOutput {
+            file,
+            options: self.clone(),
+            block_size,
+            device_size,
+            buf: AlignedBuf::new(buf_words, block_size),
+            len: 0,
+            unsynced: 0,
+        })
+    }
+}
+
+/// Writer to a file or block device, opened by [`FileOptions::open`].
+///
+/// Writes are collected in an aligned buffer and passed on in whole blocks; [`FileOutput::finish`]
+/// writes the rest and must be called at the end.
+pub struct FileOutput {
+    file: File,
+    options: FileOptions,
+    block_size: usize,
+    device_size: Option<u64>,
+    buf: AlignedBuf,
+    /// Number of buffered bytes.
+    len: usize,
+    /// Number of bytes written since the last sync.
+    unsynced: u64,
+}
+
+impl FileOutput {
+    /// Returns the size in bytes of a block device, or `None` for other files.
+    pub const fn device_size(&self) -> Option<u64> {
+        self.device_size
+    }
+
+    /// Returns the size in bytes of the blocks that direct writes are made of: the logical block
+    /// size of a block device or the preferred I/O size of the file system.
+    pub const fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    /// Writes the buffered bytes, the last partial block without `O_DIRECT`, and syncs the data
+    /// if requested.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.write_blocks()?;
+        if self.len > 0 {
+            #[cfg(target_os = "linux")]
+            if self.options.direct {
+                use std::os::fd::AsRawFd as _;
+
+                let fd = self.file.as_raw_fd();
+                // SAFETY: fcntl with these commands does not access memory
+                let ret = unsafe {
+                    let flags = libc::fcntl(fd, libc::F_GETFL);
+                    libc::fcntl(fd, libc::F_SETFL, flags & !libc::O_DIRECT)
+                };
+                if ret < 0 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+            self.file.write_all(&self.buf.as_bytes()[..self.len])?;
+            self.len = 0;
+        }
+        if self.options.sync_at_end {
+            self.file.sync_data()?;
+        }
+        Ok(())
+    }
+
+    /// Writes the whole blocks of the buffer and moves the rest to the front.
+    fn write_blocks(&mut self) -> io::Result<()> {
+        let n = self.len - self.len % self.block_size;
+        if n == 0 {
+            return Ok(());
+        }
+        let bytes = self.buf.as_bytes_mut();
+        self.file.write_all(&bytes[..n])?;
+        bytes.copy_within(n..self.len, 0);
+        self.len -= n;
+
+        self.unsynced += n as u64;
+        if self
+            .options
+            .sync_interval
+            .is_some_and(|i| self.unsynced >= i)
+        {
+            self.file.sync_all()?;
+            self.unsynced = 0;
+        }
+        Ok(())
+    }
+}
+
+impl io::Write for FileOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let bytes = self.buf.as_bytes_mut();
+        let n = buf.len().min(bytes.len() - self.len);
+        bytes[self.len..self.len + n].copy_from_slice(&buf[..n]);
+        self.len += n;
+        if self.len == bytes.len() {
+            self.write_blocks()?;
+        }
+        Ok(n)
+    }
+
+    /// Writes the whole blocks of the buffer; the rest is written by [`FileOutput::finish`].
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_blocks()?;
+        self.file.flush()
+    }
+}
+
+/// Makes `open` open files with `O_DIRECT`, or fails if the platform does not support it.
+pub(crate) fn set_direct(open: &mut fs::OpenOptions) -> io::Result<()> {
+    #[cfg(target_os = "linux")]
+    {
+        std::os::unix::fs::OpenOptionsExt::custom_flags(open, libc::O_DIRECT);
+        Ok(())
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = open;
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "direct I/O is supported only on Linux",
+        ))
+    }
+}
+
+/// Returns the block size for direct writes and the size of a block device.
+pub(crate) fn block_geometry(file: &File) -> io::Result<(usize, Option<u64>)> {
+    let metadata = file.metadata()?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{FileTypeExt as _, MetadataExt as _};
+
+        if metadata.file_type().is_block_device() {
+            #[cfg(target_os = "linux")]
+            return linux::block_device_geometry(file).map(|(b, s)| (b, Some(s)));
+        }
+        Ok((metadata.blksize().max(512) as usize, None))
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        Ok((4096, None))
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::{fs::File, io, os::fd::AsRawFd as _};
+
+    /// `_IOR(0x12, 114, size_t)`, in the encoding of ioctl numbers of the architecture.
+    const BLKGETSIZE64: libc::Ioctl = {
+        let read = if cfg!(any(
+            target_arch = "mips",
+            target_arch = "mips64",
+            target_arch = "powerpc",
+            target_arch = "powerpc64",
+            target_arch = "sparc",
+            target_arch = "sparc64"
+        )) {
+            2 << 29
+        } else {
+            2 << 30
+        };
+        (read | std::mem::size_of::<usize>() << 16 | 0x12 << 8 | 114) as libc::Ioctl
+    };
+
+    /// Returns the logical block size and the size in bytes of a block device.
+    pub(super) fn block_device_geometry(file: &File) -> io::Result<(usize, u64)> {
+        let fd = file.as_raw_fd();
+        let mut block_size: libc::c_int = 0;
+        // SAFETY: BLKSSZGET stores an int in `block_size`
+        if unsafe { libc::ioctl(fd, libc::BLKSSZGET, &mut block_size) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mut size: u64 = 0;
+        // SAFETY: BLKGETSIZE64 stores a u64 in `size`
+        if unsafe { libc::ioctl(fd, BLKGETSIZE64, &mut size) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok((block_size.max(512) as usize, size))
+    }
+}
+
+/// Buffer of words starting at a multiple of an alignment in bytes.
+pub(crate) struct AlignedBuf {
+    words: Vec<u64>,
+    offset: usize,
+    len: usize,
+}
+
+impl AlignedBuf {
+    /// Creates a zeroed buffer of `len` words aligned to `align` bytes, a multiple of eight.
+    pub(crate) fn new(len: usize, align: usize) -> Self {
+        let align_words = align.div_ceil(mem::size_of::<u64>());
+        let words = vec![0u64; len + align_words];
+        let misalignment = words.as_ptr() as usize % align;
+        let offset = (align - misalignment) % align / mem::size_of::<u64>();
+        Self { words, offset, len }
+    }
+
+    pub(crate) fn as_mut_slice(&mut self) -> &mut [u64] {
+        &mut self.words[self.offset..self.offset + self.len]
+    }
+
+    pub(crate) fn as_bytes(&self) -> &[u8] {
+        self.words[self.offset..self.offset + self.len].as_bytes()
+    }
+
+    pub(crate) fn as_bytes_mut(&mut self) -> &mut [u8] {
+        self.as_mut_slice().as_bytes_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, io::Write as _};
+
+    use super::{AlignedBuf, FileOptions};
+    use crate::Builder;
+
+    #[test]
+    fn aligned_buffers() {
+        for align in [8, 512, 4096, 65536] {
+            let mut buf = AlignedBuf::new(100, align);
+            let words = buf.as_mut_slice();
+            assert_eq!(words.len(), 100);
+            assert_eq!(words.as_ptr() as usize % align, 0);
+        }
+    }
+
+    #[test]
+    fn writes_file() {
+        let dir = std::env::temp_dir().join(format!("gen-random-output-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("out");
+        fs::write(&path, vec![1; 5 << 20]).unwrap();
+
+        for (options, n) in [
+            (FileOptions::new(), 0),
+            (FileOptions::new().fsync_every(1 << 20), (3 << 20) + 5),
+            (FileOptions::new().fdatasync_at_end(true), 4095),
+        ] {
+            let mut expected = Vec::new();
+            let builder = Builder::new().seed(b"output");
+            builder.build().write_to(&mut expected, Some(n)).unwrap();
+
+            let mut out = options.open(&path).unwrap();
+            assert_eq!(out.device_size(), None);
+            builder.build().write_to(&mut out, Some(n)).unwrap();
+            out.write_all(b"").unwrap();
+            out.finish().unwrap();
+            assert!(
+                fs::read(&path).unwrap() == expected,
+                "{:?}, {} bytes",
+                options,
+                n
+            );
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn writes_file_directly() {
+        // tmpfs does not support O_DIRECT, so use the directory of the build
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("target")
+            .join(format!("gen-random-direct-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("out");
+
+        let n = (2 << 20) + 4096 + 100;
+        let mut expected = Vec::new();
+        let builder = Builder::new().seed(b"direct");
+        builder.build().write_to(&mut expected, Some(n)).unwrap();
+
+        match FileOptions::new().direct(true).open(&path) {
+            Ok(mut out) => {
+                builder.build().write_to(&mut out, Some(n)).unwrap();
+                out.finish().unwrap();
+                assert!(fs::read(&path).unwrap() == expected);
+            }
+            // the file system may not support direct I/O
+            Err(e) => assert_eq!(e.raw_os_error(), Some(libc::EINVAL), "{}", e),
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
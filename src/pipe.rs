@@ -0,0 +1,225 @@
+//! Zero-copy output to pipes with `vmsplice(2)` on Linux.
+
+use std::{
+    cmp, fs,
+    io::{self, Write as _},
+    mem,
+    os::{
+        fd::{AsRawFd as _, BorrowedFd, RawFd},
+        unix::fs::FileTypeExt as _,
+    },
+    thread,
+    time::Duration,
+};
+
+use zerocopy::AsBytes as _;
+
+use crate::{output::AlignedBuf, RandomStream};
+
+/// Size of the pipe buffer requested to reduce the number of system calls; at most the default
+/// `/proc/sys/fs/pipe-max-size` so that unprivileged processes can set it.
+const PIPE_SIZE: usize = 1024 * 1024;
+
+/// Writes the stream to `fd` with `vmsplice(2)` if it is a pipe, until `count` bytes are written
+/// (or infinitely if `None`).
+///
+/// The stream is generated directly into two page-aligned buffers of the size of the pipe buffer,
+/// which are handed to the pipe in turn without `SPLICE_F_GIFT`, so the pipe references the pages
+/// of the buffers rather than owning them. A buffer is reused only after the other has been
+/// entirely handed over, which means that the pipe has been emptied of the first, so the reader
+/// must copy the data out of the pipe (e.g. with `read(2)`): a reader that splices the pages
+/// elsewhere (e.g. with `splice(2)` or `tee(2)`) would see them overwritten.
+///
+/// Before returning, also on errors, this waits until the reader has emptied the pipe or gone
+/// away, so that freeing the buffers cannot change unread data; if that cannot be determined, the
+/// buffers are leaked instead.
+///
+/// Returns `Ok(false)` without writing anything if `fd` is not a pipe, so that the caller can fall
+/// back to [`RandomStream::write_to`], and `Ok(true)` when done, also when the reader has gone
+/// away. If `vmsplice(2)` turns out not to be supported for the pipe, the buffers are written with
+/// `write(2)` instead. The stream is consumed up to the next word boundary after the written
+/// bytes.
+pub fn vmsplice_to(
+    stream: &mut RandomStream,
+    fd: BorrowedFd<'_>,
+    count: Option<u64>,
+) -> io::Result<bool> {
+    let mut file = fs::File::from(fd.try_clone_to_owned()?);
+    if !file.metadata()?.file_type().is_fifo() {
+        return Ok(false);
+    }
+    let fd = fd.as_raw_fd();
+
+    // SAFETY: fcntl with these commands does not access memory
+    let size = unsafe {
+        libc::fcntl(fd, libc::F_SETPIPE_SZ, PIPE_SIZE as libc::c_int);
+        libc::fcntl(fd, libc::F_GETPIPE_SZ)
+    };
+    let Ok(size) = usize::try_from(size) else {
+        return Ok(false);
+    };
+    let words = size.div_ceil(mem::size_of::<u64>());
+    let mut bufs = [
+        AlignedBuf::new(words, page_size()),
+        AlignedBuf::new(words, page_size()),
+    ];
+
+    let ret = hand_over(stream, &mut file, fd, &mut bufs, size, count);
+    // the pipe may still reference the pages of the buffers
+    if wait_until_drained(fd).is_err() {
+        mem::forget(bufs);
+    }
+    ret
+}
+
+/// Hands the stream over to the pipe `fd` of `size` bytes in the buffers in turn.
+fn hand_over(
+    stream: &mut RandomStream,
+    file: &mut fs::File,
+    fd: RawFd,
+    bufs: &mut [AlignedBuf; 2],
+    size: usize,
+    mut count: Option<u64>,
+) -> io::Result<bool> {
+    let mut supported = true;
+    for i in [0, 1].into_iter().cycle() {
+        let len = match count {
+            Some(0) => return Ok(true),
+            Some(n) => cmp::min(n, size as u64) as usize,
+            None => size,
+        };
+        let buf = bufs[i].as_mut_slice();
+        stream.fill_words(&mut buf[..len.div_ceil(mem::size_of::<u64>())])?;
+
+        let mut bytes = &buf.as_bytes()[..len];
+        while supported && !bytes.is_empty() {
+            let iov = libc::iovec {
+                iov_base: bytes.as_ptr() as *mut libc::c_void,
+                iov_len: bytes.len(),
+            };
+            // SAFETY: `iov` points to `bytes`, which is not modified or freed while the pipe may
+            // hold it
+            let ret = unsafe { libc::vmsplice(fd, &iov, 1, 0) };
+            if ret >= 0 {
+                bytes = &bytes[ret as usize..];
+                continue;
+            }
+            let e = io::Error::last_os_error();
+            match e.raw_os_error() {
+                Some(libc::EINTR) => {}
+                Some(libc::EPIPE) => return Ok(true),
+                Some(libc::EINVAL | libc::ENOSYS) => supported = false,
+                _ => return Err(e),
+            }
+        }
+        match file.write_all(bytes) {
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => return Ok(true),
+            ret => ret?,
+        }
+
+        if let Some(n) = count.as_mut() {
+            *n -= len as u64;
+        }
+    }
+    unreachable!()
+}
+
+/// Waits until the pipe `fd` is empty, or its reader has gone away.
+fn wait_until_drained(fd: RawFd) -> io::Result<()> {
+    loop {
+        let mut pollfd = libc::pollfd {
+            fd,
+            events: 0,
+            revents: 0,
+        };
+        // SAFETY: `pollfd` is a valid array of one element
+        if unsafe { libc::poll(&mut pollfd, 1, 0) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if pollfd.revents & libc::POLLERR != 0 {
+            // no readers left
+            return Ok(());
+        }
+        let mut unread: libc::c_int = 0;
+        // SAFETY: FIONREAD writes an int to `unread`
+        if unsafe { libc::ioctl(fd, libc::FIONREAD, &mut unread) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if unread == 0 {
+            return Ok(());
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+/// Returns the page size of the system (e.g. 16 or 64 KiB on some AArch64 kernels), to which the
+/// buffers handed over are aligned.
+fn page_size() -> usize {
+    // SAFETY: sysconf does not access memory
+    let size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    usize::try_from(size).unwrap_or(4096)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{self, Read as _},
+        os::fd::AsFd as _,
+        thread,
+        time::Duration,
+    };
+
+    use super::vmsplice_to;
+    use crate::Builder;
+
+    #[test]
+    fn vmsplice_matches_write_to() {
+        let builder = Builder::new().reseed_interval(4096).seed(b"vmsplice");
+        for n in [0, 13, 1 << 20, (5 << 20) + 3] {
+            let mut expected = Vec::new();
+            let mut stream = builder.build();
+            stream.fill_bytes(&mut [0; 5]).unwrap();
+            stream.write_to(&mut expected, Some(n)).unwrap();
+
+            let (mut reader, writer) = io::pipe().unwrap();
+            let mut stream = builder.build();
+            stream.fill_bytes(&mut [0; 5]).unwrap();
+            let handle = thread::spawn(move || vmsplice_to(&mut stream, writer.as_fd(), Some(n)));
+            let mut actual = Vec::new();
+            reader.read_to_end(&mut actual).unwrap();
+            assert!(handle.join().unwrap().unwrap());
+            assert!(actual == expected, "{} bytes", n);
+        }
+    }
+
+    #[test]
+    fn waits_until_reader_has_read() {
+        let (mut reader, writer) = io::pipe().unwrap();
+        let mut stream = Builder::new().build();
+        let handle = thread::spawn(move || vmsplice_to(&mut stream, writer.as_fd(), Some(4096)));
+        thread::sleep(Duration::from_millis(50));
+        assert!(
+            !handle.is_finished(),
+            "must not free the buffers while the pipe holds their pages"
+        );
+        let mut actual = Vec::new();
+        reader.read_to_end(&mut actual).unwrap();
+        assert!(handle.join().unwrap().unwrap());
+        assert_eq!(actual.len(), 4096);
+    }
+
+    #[test]
+    fn falls_back_for_files() {
+        let file = std::fs::File::open("/dev/null").unwrap();
+        let mut stream = Builder::new().build();
+        assert!(!vmsplice_to(&mut stream, file.as_fd(), Some(8)).unwrap());
+    }
+
+    #[test]
+    fn stops_when_reader_goes_away() {
+        let (reader, writer) = io::pipe().unwrap();
+        drop(reader);
+        let mut stream = Builder::new().build();
+        assert!(vmsplice_to(&mut stream, writer.as_fd(), None).unwrap());
+    }
+}
@@ -0,0 +1,375 @@
+//! Integers uniformly distributed in a range.
+
+use std::{fmt, io, io::Write as _, str::FromStr};
+
+use crate::{
+    numbers::{self, Endian, TextWriter},
+    RandomStream,
+};
+
+/// Range of integers within `i128` or `u128` to sample uniformly.
+///
+/// Parsed from `LOW..HIGH` (exclusive) or `LOW..=HIGH` (inclusive), e.g. `1..=6` or `-10..10`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum IntRange {
+    /// Range with a non-negative lower bound.
+    Unsigned {
+        /// Lower bound.
+        low: u128,
+        /// Upper bound minus the lower bound.
+        span: u128,
+    },
+    /// Range with a negative lower bound.
+    Signed {
+        /// Lower bound.
+        low: i128,
+        /// Upper bound minus the lower bound.
+        span: u128,
+    },
+}
+
+/// Integer sampled from an [`IntRange`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Int {
+    /// Value of an unsigned range.
+    Unsigned(u128),
+    /// Value of a signed range.
+    Signed(i128),
+}
+
+impl IntRange {
+    /// Returns the difference between the upper and lower bounds.
+    pub const fn span(self) -> u128 {
+        match self {
+            Self::Unsigned { span, .. } | Self::Signed { span, .. } => span,
+        }
+    }
+
+    /// Returns the number of bytes (1, 2, 4, 8 or 16) of the smallest integer type that holds
+    /// all values of the range.
+    pub const fn width(self) -> usize {
+        let bits = match self {
+            Self::Unsigned { low, span } => 128 - (low + span).leading_zeros(),
+            Self::Signed { low, span } => {
+                let high = low.wrapping_add(span as i128);
+                let high_bits = if high < 0 {
+                    0
+                } else {
+                    129 - high.leading_zeros()
+                };
+                let low_bits = 129 - low.leading_ones();
+                if high_bits > low_bits {
+                    high_bits
+                } else {
+                    low_bits
+                }
+            }
+        };
+        let bytes = bits.div_ceil(8) as usize;
+        if bytes <= 1 {
+            1
+        } else {
+            bytes.next_power_of_two()
+        }
+    }
+
+    /// Draws a value uniformly from the range using Lemire's nearly divisionless method, which
+    /// consumes one word of `stream` (two if the span exceeds 64 bits) per attempt and rarely
+    /// needs more than one attempt.
+    pub fn sample(self, stream: &mut RandomStream) -> io::Result<Int> {
+        let span = self.span();
+        let offset = if span == u128::MAX {
+            next_u128(stream)?
+        } else if span == u128::from(u64::MAX) {
+            u128::from(stream.next_u64()?)
+        } else if span < u128::from(u64::MAX) {
+            u128::from(lemire_u64(span as u64 + 1, || stream.next_u64())?)
+        } else {
+            lemire_u128(span + 1, || next_u128(stream))?
+        };
+        Ok(match self {
+            Self::Unsigned { low, .. } => Int::Unsigned(low + offset),
+            Self::Signed { low, .. } => Int::Signed(low.wrapping_add(offset as i128)),
+        })
+    }
+}
+
+/// Error returned when parsing an invalid or empty range.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseRangeError(String);
+
+impl fmt::Display for ParseRangeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid or empty range '{}'", self.0)
+    }
+}
+
+impl std::error::Error for ParseRangeError {}
+
+/// Bound of a range, which may be negative or exceed `i128::MAX`.
+#[derive(Clone, Copy)]
+enum Bound {
+    Neg(i128),
+    NonNeg(u128),
+}
+
+impl Bound {
+    fn parse(s: &str) -> Option<Self> {
+        if s.starts_with('-') {
+            s.parse().ok().map(|n: i128| match u128::try_from(n) {
+                Ok(n) => Self::NonNeg(n),
+                Err(_) => Self::Neg(n),
+            })
+        } else if s.starts_with(|c: char| c.is_ascii_digit()) {
+            s.parse().ok().map(Self::NonNeg)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the bound minus one.
+    fn pred(self) -> Option<Self> {
+        match self {
+            Self::NonNeg(0) => Some(Self::Neg(-1)),
+            Self::NonNeg(n) => Some(Self::NonNeg(n - 1)),
+            Self::Neg(n) => n.checked_sub(1).map(Self::Neg),
+        }
+    }
+}
+
+impl FromStr for IntRange {
+    type Err = ParseRangeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseRangeError(s.to_owned());
+        let (low, high, inclusive) = match s.split_once("..") {
+            Some((low, high)) => match high.strip_prefix('=') {
+                Some(high) => (low, high, true),
+                None => (low, high, false),
+            },
+            None => return Err(err()),
+        };
+        let low = Bound::parse(low).ok_or_else(err)?;
+        let mut high = Bound::parse(high).ok_or_else(err)?;
+        if !inclusive {
+            high = high.pred().ok_or_else(err)?;
+        }
+
+        match (low, high) {
+            (Bound::NonNeg(low), Bound::NonNeg(high)) if low <= high => Ok(Self::Unsigned {
+                low,
+                span: high - low,
+            }),
+            (Bound::Neg(low), Bound::Neg(high)) if low <= high => Ok(Self::Signed {
+                low,
+                span: high.wrapping_sub(low) as u128,
+            }),
+            (Bound::Neg(low), Bound::NonNeg(high)) if high <= i128::MAX as u128 => {
+                Ok(Self::Signed {
+                    low,
+                    span: (high as i128).wrapping_sub(low) as u128,
+                })
+            }
+            _ => Err(err()),
+        }
+    }
+}
+
+fn next_u128(stream: &mut RandomStream) -> io::Result<u128> {
+    let low = stream.next_u64()?;
+    Ok(u128::from(low) | u128::from(stream.next_u64()?) << 64)
+}
+
+/// Returns a value uniformly distributed in `[0, s)` from the random words returned by `next`
+/// (Lemire 2019, "Fast Random Integer Generation in an Interval").
+pub(crate) fn lemire_u64(s: u64, mut next: impl FnMut() -> io::Result<u64>) -> io::Result<u64> {
+    let mut m = u128::from(next()?) * u128::from(s);
+    if (m as u64) < s {
+        let t = s.wrapping_neg() % s;
+        while (m as u64) < t {
+            m = u128::from(next()?) * u128::from(s);
+        }
+    }
+    Ok((m >> 64) as u64)
+}
+
+/// 128-bit version of [`lemire_u64`].
+fn lemire_u128(s: u128, mut next: impl FnMut() -> io::Result<u128>) -> io::Result<u128> {
+    let (mut hi, mut lo) = mul_wide(next()?, s);
+    if lo < s {
+        let t = s.wrapping_neg() % s;
+        while lo < t {
+            (hi, lo) = mul_wide(next()?, s);
+        }
+    }
+    Ok(hi)
+}
+
+/// Returns the upper and lower halves of the 256-bit product of `a` and `b`.
+fn mul_wide(a: u128, b: u128) -> (u128, u128) {
+    const MASK: u128 = u64::MAX as u128;
+    let (a1, a0) = (a >> 64, a & MASK);
+    let (b1, b0) = (b >> 64, b & MASK);
+    let (p00, p01, p10, p11) = (a0 * b0, a0 * b1, a1 * b0, a1 * b1);
+    let mid = (p00 >> 64) + (p01 & MASK) + (p10 & MASK);
+    let lo = (p00 & MASK) | mid << 64;
+    let hi = p11 + (p01 >> 64) + (p10 >> 64) + (mid >> 64);
+    (hi, lo)
+}
+
+/// Writes `count` values sampled from `range` (or infinitely if `None`) to `out` in decimal.
+///
+/// Returns `Ok(())` also when `out` reports [`io::ErrorKind::BrokenPipe`].
+pub fn write_text<W: io::Write>(
+    stream: &mut RandomStream,
+    range: IntRange,
+    out: &mut TextWriter<W>,
+    count: Option<u64>,
+) -> io::Result<()> {
+    numbers::repeat(count, || match range.sample(stream)? {
+        Int::Unsigned(v) => out.write_u128(v),
+        Int::Signed(v) => out.write_i128(v),
+    })?;
+    numbers::ignore_broken_pipe(out.flush())
+}
+
+/// Writes `count` values sampled from `range` (or infinitely if `None`) to `out` as binary
+/// integers of [`IntRange::width`] bytes.
+///
+/// Returns `Ok(())` also when `out` reports [`io::ErrorKind::BrokenPipe`].
+pub fn write_binary(
+    stream: &mut RandomStream,
+    range: IntRange,
+    out: impl io::Write,
+    endian: Endian,
+    count: Option<u64>,
+) -> io::Result<()> {
+    let width = range.width();
+    let mut out = io::BufWriter::with_capacity(crate::BUF_SIZE, out);
+    numbers::repeat(count, || {
+        let v = match range.sample(stream)? {
+            Int::Unsigned(v) => v,
+            Int::Signed(v) => v as u128,
+        };
+        endian.write_int(&mut out, v, width)
+    })?;
+    numbers::ignore_broken_pipe(out.flush())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{lemire_u64, mul_wide, Int, IntRange};
+    use crate::Builder;
+
+    #[test]
+    fn parse_ranges() {
+        let cases = [
+            ("1..=6", IntRange::Unsigned { low: 1, span: 5 }),
+            ("0..10", IntRange::Unsigned { low: 0, span: 9 }),
+            ("-10..10", IntRange::Signed { low: -10, span: 19 }),
+            ("-3..0", IntRange::Signed { low: -3, span: 2 }),
+            ("-5..=-5", IntRange::Signed { low: -5, span: 0 }),
+            (
+                "0..=340282366920938463463374607431768211455",
+                IntRange::Unsigned {
+                    low: 0,
+                    span: u128::MAX,
+                },
+            ),
+            (
+                "-170141183460469231731687303715884105728..=170141183460469231731687303715884105727",
+                IntRange::Signed {
+                    low: i128::MIN,
+                    span: u128::MAX,
+                },
+            ),
+        ];
+        for (src, expected) in cases {
+            assert_eq!(src.parse(), Ok(expected), "{}", src);
+        }
+        for src in [
+            "", "1", "6..1", "1..1", "0..0", "a..b", "1..=", "+1..5", "-1..=1e3",
+        ] {
+            assert!(src.parse::<IntRange>().is_err(), "{}", src);
+        }
+        // does not fit in either i128 or u128
+        assert!("-1..=340282366920938463463374607431768211455"
+            .parse::<IntRange>()
+            .is_err());
+    }
+
+    #[test]
+    fn widths() {
+        let cases = [
+            ("0..256", 1),
+            ("0..257", 2),
+            ("-128..128", 1),
+            ("-129..0", 2),
+            ("-1..=32768", 4),
+            ("0..=4294967296", 8),
+            ("-1..=18446744073709551615", 16),
+        ];
+        for (src, width) in cases {
+            assert_eq!(src.parse::<IntRange>().unwrap().width(), width, "{}", src);
+        }
+    }
+
+    #[test]
+    fn wide_multiplication() {
+        assert_eq!(mul_wide(u128::MAX, u128::MAX), (u128::MAX - 1, 1));
+        assert_eq!(mul_wide(1 << 64, 1 << 64), (1, 0));
+        assert_eq!(mul_wide(3, 5), (0, 15));
+    }
+
+    #[test]
+    fn lemire_rejects_biased_words() {
+        // with s = 3, t = 2^64 mod 3 = 1, so a product whose lower half is zero is rejected
+        let mut words = [0, u64::MAX / 3 + 1, u64::MAX].into_iter();
+        assert_eq!(lemire_u64(3, || Ok(words.next().unwrap())).unwrap(), 1);
+        assert_eq!(words.next(), Some(u64::MAX));
+    }
+
+    #[test]
+    fn samples_cover_range() {
+        let mut stream = Builder::new().seed(b"range").build();
+        for (src, n_values) in [
+            ("1..=6", 6),
+            ("-2..3", 5),
+            ("18446744073709551614..=18446744073709551617", 4),
+        ] {
+            let range: IntRange = src.parse().unwrap();
+            let mut counts = vec![0u32; n_values];
+            for _ in 0..6000 {
+                let i = match (range, range.sample(&mut stream).unwrap()) {
+                    (IntRange::Unsigned { low, .. }, Int::Unsigned(v)) => v - low,
+                    (IntRange::Signed { low, .. }, Int::Signed(v)) => (v - low) as u128,
+                    _ => unreachable!(),
+                };
+                counts[i as usize] += 1;
+            }
+            // each count is within about 5 standard deviations of the mean
+            assert!(
+                counts
+                    .iter()
+                    .all(|&c| c.abs_diff(6000 / n_values as u32) < 200),
+                "{}: {:?}",
+                src,
+                counts
+            );
+        }
+
+        for src in [
+            "0..=340282366920938463463374607431768211455",
+            "-5..=170141183460469231731687303715884105727",
+        ] {
+            let range: IntRange = src.parse().unwrap();
+            for _ in 0..100 {
+                match (range, range.sample(&mut stream).unwrap()) {
+                    (IntRange::Signed { low, .. }, Int::Signed(v)) => assert!(v >= low),
+                    (IntRange::Unsigned { .. }, Int::Unsigned(_)) => {}
+                    _ => unreachable!(),
+                }
+            }
+        }
+    }
+}
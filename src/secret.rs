@@ -0,0 +1,323 @@
+//! Passwords and passphrases for humans, chosen uniformly without bias.
+
+use std::{fmt, io};
+
+use crate::{range::lemire_u64, RandomStream};
+
+/// Upper-case letters.
+pub const UPPER: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+/// Lower-case letters.
+pub const LOWER: &str = "abcdefghijklmnopqrstuvwxyz";
+
+/// Decimal digits.
+pub const DIGITS: &str = "0123456789";
+
+/// Printable ASCII symbols.
+pub const SYMBOLS: &str = "!\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~";
+
+/// Characters that are easily confused with one another in common fonts.
+pub const AMBIGUOUS: &str = "0OoIl1|`'\"";
+
+/// Error returned when a password or passphrase cannot be generated as configured.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SecretError {
+    /// No character class was given.
+    NoClasses,
+    /// A character class is empty, possibly after removing ambiguous characters.
+    EmptyClass(String),
+    /// The password is too short to contain a character of every class.
+    TooShort {
+        /// Requested length.
+        length: usize,
+        /// Number of classes that must be covered.
+        classes: usize,
+    },
+    /// The wordlist has no words.
+    EmptyWordlist,
+    /// The wordlist has a word more than once, which would overstate the entropy.
+    DuplicateWord(String),
+}
+
+impl fmt::Display for SecretError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoClasses => f.write_str("no character classes given"),
+            Self::EmptyClass(class) => write!(f, "character class '{}' is empty", class),
+            Self::TooShort { length, classes } => write!(
+                f,
+                "length {} is too short to cover {} character classes",
+                length, classes
+            ),
+            Self::EmptyWordlist => f.write_str("wordlist is empty"),
+            Self::DuplicateWord(word) => write!(f, "wordlist has duplicate word '{}'", word),
+        }
+    }
+}
+
+impl std::error::Error for SecretError {}
+
+/// Generator of passwords with at least one character of every class.
+///
+/// Passwords are drawn uniformly from all strings of the alphabet that cover every class, by
+/// rejecting those that miss a class.
+#[derive(Clone, Debug)]
+pub struct PasswordGenerator {
+    /// Union of the classes without duplicates.
+    alphabet: Vec<char>,
+    /// Membership of the characters of `alphabet`, a bit per class.
+    masks: Vec<u32>,
+    classes: usize,
+    length: usize,
+}
+
+impl PasswordGenerator {
+    /// Maximum number of character classes.
+    pub const MAX_CLASSES: usize = 16;
+
+    /// Creates a generator of passwords of `length` characters drawn from the union of
+    /// `classes`, leaving out [`AMBIGUOUS`] characters if `exclude_ambiguous` is `true`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are more than [`PasswordGenerator::MAX_CLASSES`] classes.
+    pub fn new(
+        classes: &[&str],
+        length: usize,
+        exclude_ambiguous: bool,
+    ) -> Result<Self, SecretError> {
+        assert!(classes.len() <= Self::MAX_CLASSES, "too many classes");
+        if classes.is_empty() {
+            return Err(SecretError::NoClasses);
+        }
+        let mut alphabet = Vec::new();
+        let mut masks: Vec<u32> = Vec::new();
+        for (i, class) in classes.iter().enumerate() {
+            let mut chars = class
+                .chars()
+                .filter(|c| !exclude_ambiguous || !AMBIGUOUS.contains(*c))
+                .peekable();
+            if chars.peek().is_none() {
+                return Err(SecretError::EmptyClass((*class).to_owned()));
+            }
+            for c in chars {
+                match alphabet.iter().position(|&a| a == c) {
+                    Some(j) => masks[j] |= 1 << i,
+                    None => {
+                        alphabet.push(c);
+                        masks.push(1 << i);
+                    }
+                }
+            }
+        }
+        if length < classes.len() {
+            return Err(SecretError::TooShort {
+                length,
+                classes: classes.len(),
+            });
+        }
+        Ok(Self {
+            alphabet,
+            masks,
+            classes: classes.len(),
+            length,
+        })
+    }
+
+    /// Returns the entropy of a password in bits, the base-2 logarithm of the number of
+    /// passwords that cover every class.
+    pub fn entropy_bits(&self) -> f64 {
+        // inclusion–exclusion over the sets of classes that a password misses, relative to the
+        // total number of strings to stay within the range of f64
+        let total = self.alphabet.len() as f64;
+        let len = self.length as i32;
+        let covering: f64 = (0..1u32 << self.classes)
+            .map(|missed| {
+                let allowed = self.masks.iter().filter(|&&m| m & missed == 0).count();
+                let p = (allowed as f64 / total).powi(len);
+                if missed.count_ones() % 2 == 0 {
+                    p
+                } else {
+                    -p
+                }
+            })
+            .sum();
+        f64::from(len) * total.log2() + covering.log2()
+    }
+
+    /// Generates a password.
+    pub fn generate(&self, stream: &mut RandomStream) -> io::Result<String> {
+        let all = (1u32 << self.classes) - 1;
+        let mut indices = Vec::with_capacity(self.length);
+        loop {
+            indices.clear();
+            let mut covered = 0;
+            for _ in 0..self.length {
+                let i = lemire_u64(self.alphabet.len() as u64, || stream.next_u64())? as usize;
+                covered |= self.masks[i];
+                indices.push(i);
+            }
+            if covered == all {
+                return Ok(indices.iter().map(|&i| self.alphabet[i]).collect());
+            }
+        }
+    }
+}
+
+/// List of words for passphrases.
+#[derive(Clone, Debug)]
+pub struct Wordlist {
+    words: Vec<String>,
+}
+
+impl Wordlist {
+    /// Parses a wordlist of one word per line, taking the last field of lines with several
+    /// whitespace-separated fields so that diceware lists such as the EFF's (`11111\tabacus`)
+    /// can be used as they are. Blank lines are skipped.
+    pub fn parse(text: &str) -> Result<Self, SecretError> {
+        let mut words: Vec<String> = text
+            .lines()
+            .filter_map(|line| line.split_whitespace().last())
+            .map(str::to_owned)
+            .collect();
+        if words.is_empty() {
+            return Err(SecretError::EmptyWordlist);
+        }
+        let mut sorted: Vec<&str> = words.iter().map(String::as_str).collect();
+        sorted.sort_unstable();
+        if let Some(pair) = sorted.windows(2).find(|pair| pair[0] == pair[1]) {
+            return Err(SecretError::DuplicateWord(pair[0].to_owned()));
+        }
+        words.shrink_to_fit();
+        Ok(Self { words })
+    }
+
+    /// Returns the number of words.
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    /// Returns `true` if there are no words, which [`Wordlist::parse`] does not allow.
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    /// Returns the entropy in bits of a passphrase of `count` words.
+    pub fn entropy_bits(&self, count: usize) -> f64 {
+        count as f64 * (self.words.len() as f64).log2()
+    }
+
+    /// Generates a passphrase of `count` words joined by `separator`.
+    pub fn passphrase(
+        &self,
+        stream: &mut RandomStream,
+        count: usize,
+        separator: &str,
+    ) -> io::Result<String> {
+        let mut phrase = String::new();
+        for n in 0..count {
+            if n > 0 {
+                phrase.push_str(separator);
+            }
+            let i = lemire_u64(self.words.len() as u64, || stream.next_u64())?;
+            phrase.push_str(&self.words[i as usize]);
+        }
+        Ok(phrase)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PasswordGenerator, SecretError, Wordlist, AMBIGUOUS, DIGITS, LOWER, SYMBOLS};
+    use crate::{generators::Algorithm, Builder, RandomStream};
+
+    fn stream() -> RandomStream {
+        Builder::new()
+            .algorithm(Algorithm::ChaCha20)
+            .seed(b"secret")
+            .build()
+    }
+
+    #[test]
+    fn passwords_cover_classes() {
+        let classes = [super::UPPER, LOWER, DIGITS, SYMBOLS];
+        let gen = PasswordGenerator::new(&classes, 4, true).unwrap();
+        let mut stream = stream();
+        for _ in 0..200 {
+            let password = gen.generate(&mut stream).unwrap();
+            assert_eq!(password.chars().count(), 4);
+            for class in classes {
+                assert!(password.chars().any(|c| class.contains(c)), "{}", password);
+            }
+            assert!(
+                !password.chars().any(|c| AMBIGUOUS.contains(c)),
+                "{}",
+                password
+            );
+        }
+    }
+
+    #[test]
+    fn custom_alphabet() {
+        let gen = PasswordGenerator::new(&["αβγ"], 12, false).unwrap();
+        let password = gen.generate(&mut stream()).unwrap();
+        assert_eq!(password.chars().count(), 12);
+        assert!(password.chars().all(|c| "αβγ".contains(c)));
+    }
+
+    #[test]
+    fn password_entropy() {
+        let gen = PasswordGenerator::new(&[LOWER], 10, false).unwrap();
+        assert!((gen.entropy_bits() - 10.0 * 26f64.log2()).abs() < 1e-9);
+
+        // 36^2 strings minus the 10^2 without letters and the 26^2 without digits
+        let gen = PasswordGenerator::new(&[LOWER, DIGITS], 2, false).unwrap();
+        assert!((gen.entropy_bits() - 520f64.log2()).abs() < 1e-9);
+
+        // overlapping classes: "ab" and "bc", strings over "abc" with an a or b and a b or c
+        let gen = PasswordGenerator::new(&["ab", "bc"], 2, false).unwrap();
+        assert!((gen.entropy_bits() - 7f64.log2()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn password_errors() {
+        assert_eq!(
+            PasswordGenerator::new(&[], 8, false).unwrap_err(),
+            SecretError::NoClasses
+        );
+        assert_eq!(
+            PasswordGenerator::new(&[LOWER, "0O"], 8, true).unwrap_err(),
+            SecretError::EmptyClass("0O".into())
+        );
+        assert_eq!(
+            PasswordGenerator::new(&[LOWER, DIGITS, SYMBOLS], 2, false).unwrap_err(),
+            SecretError::TooShort {
+                length: 2,
+                classes: 3
+            }
+        );
+    }
+
+    #[test]
+    fn wordlists() {
+        let list = Wordlist::parse("11111\tabacus\n11112\tabdomen\n\n11113 abide\n").unwrap();
+        assert_eq!(list.len(), 3);
+        assert!((list.entropy_bits(4) - 4.0 * 3f64.log2()).abs() < 1e-9);
+
+        let phrase = list.passphrase(&mut stream(), 5, "-").unwrap();
+        let words: Vec<_> = phrase.split('-').collect();
+        assert_eq!(words.len(), 5);
+        assert!(words
+            .iter()
+            .all(|w| ["abacus", "abdomen", "abide"].contains(w)));
+
+        assert_eq!(
+            Wordlist::parse(" \n").unwrap_err(),
+            SecretError::EmptyWordlist
+        );
+        assert_eq!(
+            Wordlist::parse("a\nb\na\n").unwrap_err(),
+            SecretError::DuplicateWord("a".into())
+        );
+    }
+}
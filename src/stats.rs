@@ -0,0 +1,349 @@
+//! Statistical tests of randomness on bit streams.
+//!
+//! [`Suite`] is an [`io::Write`] sink that runs tests of NIST SP 800-22 with the functions of
+//! [`sp800_22`] and a chi-square test of the byte distribution on everything written to it,
+//! reading every byte from the most significant bit.
+
+use std::{f64::consts::SQRT_2, fmt, io};
+
+use crate::dist::ln_gamma;
+
+pub mod sp800_22;
+pub mod sp800_90b;
+pub mod stress;
+
+/// Length in bits of the blocks of the block frequency test.
+const BLOCK_FREQUENCY_LEN: usize = 128;
+
+/// Pattern length of the serial test, reduced for short inputs.
+const SERIAL_LEN: u32 = 16;
+
+/// Pattern length of the approximate entropy test, reduced for short inputs.
+const APPROXIMATE_ENTROPY_LEN: u32 = 10;
+
+/// Minimum number of bits of all tests, as recommended by SP 800-22.
+const MIN_BITS: usize = 100;
+
+/// P-value of a test, or `None` if the input was too short for it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TestResult {
+    pub name: String,
+    pub p_value: Option<f64>,
+}
+
+impl TestResult {
+    fn new(name: &str, p_value: Option<f64>) -> Self {
+        Self {
+            name: name.to_owned(),
+            p_value,
+        }
+    }
+
+    /// Returns `Some(true)` if the p-value is at least `alpha`, or `None` if the test was skipped.
+    pub fn passed(&self, alpha: f64) -> Option<bool> {
+        self.p_value.map(|p| p >= alpha)
+    }
+}
+
+impl fmt::Display for TestResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.p_value {
+            Some(p) => write!(f, "{}: p = {:.6}", self.name, p),
+            None => write!(f, "{}: skipped", self.name),
+        }
+    }
+}
+
+/// Sink that runs the monobit, runs, longest-run-of-ones, block frequency, serial, approximate
+/// entropy and cumulative sums tests of SP 800-22 and a chi-square test of the byte distribution
+/// on the bytes written to it.
+///
+/// The bits are kept one per byte, as the functions of [`sp800_22`] take them, so the suite holds
+/// eight bytes of memory per byte written. Parameters that depend on the length are chosen in
+/// [`Suite::finish`] from the number of bits: the block length of the longest-run test as in SP
+/// 800-22, and the pattern lengths of the serial (up to 16) and approximate entropy (up to 10)
+/// tests as the largest satisfying their recommendations.
+#[derive(Clone, Debug)]
+pub struct Suite {
+    eps: Vec<u8>,
+    bytes: [u64; 256],
+}
+
+impl Default for Suite {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Suite {
+    /// Creates a suite that has seen no bits.
+    pub fn new() -> Self {
+        Self {
+            eps: Vec::new(),
+            bytes: [0; 256],
+        }
+    }
+
+    /// Returns the number of bits written.
+    pub fn bits(&self) -> u64 {
+        self.eps.len() as u64
+    }
+
+    /// Returns the results of the tests, in the order of SP 800-22 followed by the byte
+    /// distribution.
+    pub fn finish(self) -> Vec<TestResult> {
+        let eps = &self.eps[..];
+        let n = eps.len();
+        let enough = n >= MIN_BITS;
+        let mut results = vec![
+            TestResult::new("monobit", enough.then(|| sp800_22::frequency(eps))),
+            TestResult::new(
+                "block frequency",
+                (enough && n >= BLOCK_FREQUENCY_LEN)
+                    .then(|| sp800_22::block_frequency(eps, BLOCK_FREQUENCY_LEN)),
+            ),
+            TestResult::new("runs", enough.then(|| sp800_22::runs(eps))),
+            TestResult::new("longest run of ones", sp800_22::longest_run(eps)),
+        ];
+
+        let log2_n = n.checked_ilog2().unwrap_or(0);
+        let serial_len = SERIAL_LEN.min(log2_n.saturating_sub(3));
+        let (p1, p2) = match enough && serial_len >= 2 {
+            true => {
+                let (p1, p2) = sp800_22::serial(eps, serial_len);
+                (Some(p1), Some(p2))
+            }
+            false => (None, None),
+        };
+        results.push(TestResult::new("serial 1", p1));
+        results.push(TestResult::new("serial 2", p2));
+        let apen_len = APPROXIMATE_ENTROPY_LEN.min(log2_n.saturating_sub(6));
+        results.push(TestResult::new(
+            "approximate entropy",
+            (enough && apen_len >= 2).then(|| sp800_22::approximate_entropy(eps, apen_len)),
+        ));
+
+        let (forward, backward) = sp800_22::cumulative_sums(eps);
+        results.push(TestResult::new(
+            "cumulative sums (forward)",
+            enough.then_some(forward),
+        ));
+        results.push(TestResult::new(
+            "cumulative sums (backward)",
+            enough.then_some(backward),
+        ));
+
+        // at least five bytes of every value are expected
+        let n_bytes: u64 = self.bytes.iter().sum();
+        results.push(TestResult::new(
+            "byte distribution",
+            (n_bytes >= 5 * 256).then(|| self.byte_distribution(n_bytes)),
+        ));
+        results
+    }
+
+    fn byte_distribution(&self, n_bytes: u64) -> f64 {
+        let expected = n_bytes as f64 / 256.0;
+        let chi2: f64 = self
+            .bytes
+            .iter()
+            .map(|&v| (v as f64 - expected).powi(2) / expected)
+            .sum();
+        igamc(255.0 / 2.0, chi2 / 2.0)
+    }
+}
+
+impl io::Write for Suite {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.eps.reserve(buf.len() * 8);
+        for &e in buf {
+            self.bytes[e as usize] += 1;
+            self.eps.extend((0..8).rev().map(|i| e >> i & 1));
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Returns the p-value of the cumulative sums test for the maximum excursion `z` of a random walk
+/// of `n` steps, summing over the same terms as the NIST reference implementation.
+fn cumulative_sums_p(n: i64, z: i64) -> f64 {
+    let z = z.max(1);
+    let scale = z as f64 / (n as f64).sqrt();
+    let sum = |from: i64, to: i64, a: i64, b: i64| -> f64 {
+        (from..=to)
+            .map(|k| {
+                normal_cdf((4 * k + a) as f64 * scale) - normal_cdf((4 * k + b) as f64 * scale)
+            })
+            .sum()
+    };
+    let sum1 = sum((-n / z + 1) / 4, (n / z - 1) / 4, 1, -1);
+    let sum2 = sum((-n / z - 3) / 4, (n / z - 1) / 4, 3, 1);
+    (1.0 - sum1 + sum2).clamp(0.0, 1.0)
+}
+
+/// Returns the regularized upper incomplete gamma function `Q(a, x)` for `a > 0`, the survival
+/// function of the chi-square distribution with `2 * a` degrees of freedom at `2 * x`.
+pub fn igamc(a: f64, x: f64) -> f64 {
+    const EPS: f64 = 1e-16;
+    const TINY: f64 = 1e-300;
+    const MAX_ITERATIONS: u32 = 10_000_000;
+
+    if x <= 0.0 {
+        return 1.0;
+    }
+    let prefactor = || (a * x.ln() - x - ln_gamma(a)).exp();
+    if x < a + 1.0 {
+        // series of the lower function
+        let (mut term, mut sum) = (1.0 / a, 1.0 / a);
+        for i in 1..MAX_ITERATIONS {
+            term *= x / (a + i as f64);
+            sum += term;
+            if term < sum * EPS {
+                break;
+            }
+        }
+        (1.0 - sum * prefactor()).max(0.0)
+    } else {
+        // continued fraction evaluated by the modified Lentz method
+        let mut b = x + 1.0 - a;
+        let mut c = 1.0 / TINY;
+        let mut d = 1.0 / b;
+        let mut h = d;
+        for i in 1..MAX_ITERATIONS {
+            let an = -(i as f64) * (i as f64 - a);
+            b += 2.0;
+            d = an * d + b;
+            if d.abs() < TINY {
+                d = TINY;
+            }
+            c = b + an / c;
+            if c.abs() < TINY {
+                c = TINY;
+            }
+            d = 1.0 / d;
+            let delta = d * c;
+            h *= delta;
+            if (delta - 1.0).abs() < EPS {
+                break;
+            }
+        }
+        (h * prefactor()).min(1.0)
+    }
+}
+
+/// Returns the complementary error function.
+pub fn erfc(x: f64) -> f64 {
+    let q = igamc(0.5, x * x);
+    if x < 0.0 {
+        2.0 - q
+    } else {
+        q
+    }
+}
+
+/// Returns the cumulative distribution function of the standard normal distribution.
+pub fn normal_cdf(x: f64) -> f64 {
+    0.5 * erfc(-x / SQRT_2)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write as _;
+
+    use super::{erfc, igamc, normal_cdf, Suite};
+    use crate::Builder;
+
+    fn suite_of(bits: &str) -> Suite {
+        Suite {
+            eps: bits.bytes().map(|c| c - b'0').collect(),
+            ..Suite::new()
+        }
+    }
+
+    fn assert_p(actual: f64, expected: f64, what: &str) {
+        assert!(
+            (actual - expected).abs() < 1e-6,
+            "{}: p = {}, expected {}",
+            what,
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn special_functions() {
+        assert_p(erfc(0.0), 1.0, "erfc(0)");
+        assert_p(erfc(1.0), 0.157_299_207, "erfc(1)");
+        assert_p(erfc(-1.0), 1.842_700_793, "erfc(-1)");
+        assert!((erfc(5.0) / 1.537_459_794_428_035e-12 - 1.0).abs() < 1e-9);
+        assert_p(normal_cdf(1.959_963_985), 0.975, "normal_cdf(1.96)");
+        // chi-square survival functions
+        assert_p(igamc(1.0, 2.0), (-2f64).exp(), "igamc(1, 2)");
+        assert_p(igamc(2.5, 11.070_498 / 2.0), 0.05, "chi2(5) at 11.07");
+        assert_p(igamc(127.5, 293.2478 / 2.0), 0.05, "chi2(255) at 293.25");
+        assert_p(igamc(1e6, 1e6), 0.499_867, "igamc(1e6, 1e6)");
+    }
+
+    /// Examples of SP 800-22 rev. 1a, section 2, through the suite.
+    #[test]
+    fn nist_examples() {
+        let results = suite_of(
+            "1100100100001111110110101010001000100001011010001100001000110100\
+             110001001100011001100010100010111000",
+        )
+        .finish();
+        for (name, expected) in [
+            ("monobit", 0.109_599),
+            ("runs", 0.500_798),
+            ("cumulative sums (forward)", 0.219_194),
+            ("cumulative sums (backward)", 0.114_866),
+        ] {
+            let result = results.iter().find(|r| r.name == name).unwrap();
+            assert_p(result.p_value.unwrap(), expected, name);
+        }
+
+        let results = suite_of(
+            "11001100000101010110110001001100111000000000001001001101010100010001001111010110\
+             100000001101011111001100111001101101100010110010",
+        )
+        .finish();
+        assert_eq!(results[3].name, "longest run of ones");
+        assert_p(
+            results[3].p_value.unwrap(),
+            0.180_609,
+            "longest run of ones",
+        );
+    }
+
+    #[test]
+    fn writes_bits_msb_first() {
+        let mut suite = Suite::new();
+        suite.write_all(&[0xa0, 0x01]).unwrap();
+        assert_eq!(suite.eps, suite_of("1010000000000001").eps);
+        assert_eq!((suite.bytes[0xa0], suite.bytes[0x01]), (1, 1));
+    }
+
+    #[test]
+    fn skips_short_inputs() {
+        let mut suite = Suite::new();
+        suite.write_all(&[0x5a; 10]).unwrap();
+        let results = suite.finish();
+        assert!(results.iter().all(|r| r.p_value.is_none()), "{:?}", results);
+    }
+
+    #[test]
+    fn seeded_stream_passes() {
+        let mut suite = Suite::new();
+        let mut stream = Builder::new().seed(b"stats").build();
+        stream.write_to(&mut suite, Some(1 << 20)).unwrap();
+        assert_eq!(suite.bits(), 8 << 20);
+        for result in suite.finish() {
+            // the seed is fixed, so this cannot fail by chance
+            assert!(result.passed(0.001).unwrap(), "{}", result);
+        }
+    }
+}
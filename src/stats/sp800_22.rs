@@ -0,0 +1,1198 @@
+//! The statistical test suite of NIST SP 800-22 rev. 1a.
+//!
+//! The tests take a sequence of bits with one bit per byte, like the reference implementation.
+//! [`Battery`] is an [`io::Write`] sink that splits the bits written to it into sequences, runs
+//! all 15 tests on every sequence, and checks the p-values of each test across sequences for the
+//! proportion of passing sequences and their uniformity, like the `assess` tool.
+
+use std::{
+    f64::consts::{LN_2, PI, SQRT_2},
+    io, ops,
+};
+
+use super::{cumulative_sums_p, erfc, igamc};
+use crate::dist::ln_gamma;
+
+/// Significance level of the uniformity of p-values.
+pub const UNIFORMITY_ALPHA: f64 = 0.0001;
+
+/// Minimum number of p-values for a meaningful uniformity check.
+pub const MIN_UNIFORMITY_SAMPLES: usize = 55;
+
+/// Parameters of the longest-run-of-ones test: the minimum number of bits, the block length, the
+/// longest run counted in the first class, and the probabilities of the classes as in the NIST
+/// reference implementation.
+const LONGEST_RUN: [(u64, u64, u32, &[f64]); 3] = [
+    (128, 8, 1, &[0.21484375, 0.3671875, 0.23046875, 0.1875]),
+    (
+        6272,
+        128,
+        4,
+        &[
+            0.117_403_578_8,
+            0.242_955_959,
+            0.249_363_483,
+            0.175_177_06,
+            0.102_701_071,
+            0.112_398_847,
+        ],
+    ),
+    (
+        750_000,
+        10_000,
+        10,
+        &[0.0882, 0.2092, 0.2483, 0.1933, 0.1208, 0.0675, 0.0727],
+    ),
+];
+
+/// States of the random excursions test.
+pub const EXCURSION_STATES: [i64; 8] = [-4, -3, -2, -1, 1, 2, 3, 4];
+
+/// States of the random excursions variant test.
+pub const VARIANT_STATES: [i64; 18] = [
+    -9, -8, -7, -6, -5, -4, -3, -2, -1, 1, 2, 3, 4, 5, 6, 7, 8, 9,
+];
+
+/// Frequency (monobit) test, section 2.1.
+pub fn frequency(eps: &[u8]) -> f64 {
+    let n = eps.len() as f64;
+    let sum: i64 = eps.iter().map(|&b| 2 * b as i64 - 1).sum();
+    erfc(sum.abs() as f64 / n.sqrt() / SQRT_2)
+}
+
+/// Frequency test within blocks of `m` bits, section 2.2.
+pub fn block_frequency(eps: &[u8], m: usize) -> f64 {
+    let blocks = eps.chunks_exact(m);
+    let n_blocks = blocks.len();
+    let chi2: f64 = blocks
+        .map(|block| {
+            let ones = block.iter().filter(|&&b| b == 1).count();
+            (ones as f64 / m as f64 - 0.5).powi(2)
+        })
+        .sum::<f64>()
+        * 4.0
+        * m as f64;
+    igamc(n_blocks as f64 / 2.0, chi2 / 2.0)
+}
+
+/// Runs test, section 2.3.
+pub fn runs(eps: &[u8]) -> f64 {
+    let n = eps.len() as f64;
+    let pi = eps.iter().filter(|&&b| b == 1).count() as f64 / n;
+    // the test is not applicable if the frequency test fails
+    if (pi - 0.5).abs() >= 2.0 / n.sqrt() {
+        return 0.0;
+    }
+    let v = 1 + eps.windows(2).filter(|w| w[0] != w[1]).count();
+    let pq = pi * (1.0 - pi);
+    erfc((v as f64 - 2.0 * n * pq).abs() / (2.0 * (2.0 * n).sqrt() * pq))
+}
+
+/// Test for the longest run of ones in a block, section 2.4, or `None` for fewer than 128 bits.
+pub fn longest_run(eps: &[u8]) -> Option<f64> {
+    let &(_, m, first, probs) = LONGEST_RUN
+        .iter()
+        .rev()
+        .find(|&&(min, ..)| eps.len() as u64 >= min)?;
+    let mut classes = vec![0u64; probs.len()];
+    for block in eps.chunks_exact(m as usize) {
+        let (mut run, mut longest) = (0, 0);
+        for &b in block {
+            run = if b == 1 { run + 1 } else { 0 };
+            longest = longest.max(run);
+        }
+        let class = (longest as u32).saturating_sub(first) as usize;
+        classes[class.min(probs.len() - 1)] += 1;
+    }
+    Some(chi_square_p(&classes, probs))
+}
+
+/// Binary matrix rank test on 32x32 matrices, section 2.5, or `None` for fewer than 1024 bits.
+pub fn rank(eps: &[u8]) -> Option<f64> {
+    const M: usize = 32;
+
+    // probabilities of full rank and of one less
+    let probability = |r: i32| {
+        let product: f64 = (0..r)
+            .map(|i| (1.0 - 2f64.powi(i - 32)).powi(2) / (1.0 - 2f64.powi(i - r)))
+            .product();
+        2f64.powi(r * (64 - r) - 1024) * product
+    };
+    let probs = [probability(32), probability(31)];
+    let probs = [probs[0], probs[1], 1.0 - probs[0] - probs[1]];
+
+    let matrices = eps.chunks_exact(M * M);
+    if matrices.len() == 0 {
+        return None;
+    }
+    let mut classes = [0; 3];
+    for matrix in matrices {
+        let mut rows: Vec<u32> = matrix
+            .chunks_exact(M)
+            .map(|row| row.iter().fold(0, |acc, &b| acc << 1 | b as u32))
+            .collect();
+        let r = gf2_rank(&mut rows);
+        classes[(M - r).min(2)] += 1;
+    }
+    let n = classes.iter().sum::<u64>() as f64;
+    let chi2: f64 = classes
+        .iter()
+        .zip(probs)
+        .map(|(&f, p)| (f as f64 - n * p).powi(2) / (n * p))
+        .sum();
+    Some((-chi2 / 2.0).exp())
+}
+
+/// Returns the rank over GF(2) of the matrix of `rows`, destroying it.
+fn gf2_rank(rows: &mut [u32]) -> usize {
+    let mut rank = 0;
+    for bit in (0..32).rev() {
+        let Some(pivot) = (rank..rows.len()).find(|&i| rows[i] >> bit & 1 == 1) else {
+            continue;
+        };
+        rows.swap(rank, pivot);
+        for i in 0..rows.len() {
+            if i != rank && rows[i] >> bit & 1 == 1 {
+                rows[i] ^= rows[rank];
+            }
+        }
+        rank += 1;
+    }
+    rank
+}
+
+/// Discrete Fourier transform (spectral) test, section 2.6.
+pub fn dft(eps: &[u8]) -> f64 {
+    let n = eps.len();
+    let x: Vec<f64> = eps.iter().map(|&b| 2.0 * b as f64 - 1.0).collect();
+    let threshold = ((1.0f64 / 0.05).ln() * n as f64).sqrt();
+    let n0 = 0.95 * n as f64 / 2.0;
+    let n1 = dft_moduli(&x)
+        .iter()
+        .take(n / 2)
+        .filter(|&&m| m < threshold)
+        .count();
+    let d = (n1 as f64 - n0) / (n as f64 * 0.95 * 0.05 / 4.0).sqrt();
+    erfc(d.abs() / SQRT_2)
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Complex {
+    re: f64,
+    im: f64,
+}
+
+impl Complex {
+    fn from_angle(theta: f64) -> Self {
+        let (im, re) = theta.sin_cos();
+        Self { re, im }
+    }
+
+    fn conj(self) -> Self {
+        Self {
+            re: self.re,
+            im: -self.im,
+        }
+    }
+
+    fn norm(self) -> f64 {
+        self.re.hypot(self.im)
+    }
+}
+
+impl ops::Add for Complex {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            re: self.re + other.re,
+            im: self.im + other.im,
+        }
+    }
+}
+
+impl ops::Sub for Complex {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self {
+            re: self.re - other.re,
+            im: self.im - other.im,
+        }
+    }
+}
+
+impl ops::Mul for Complex {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        Self {
+            re: self.re * other.re - self.im * other.im,
+            im: self.re * other.im + self.im * other.re,
+        }
+    }
+}
+
+/// Returns the moduli of the discrete Fourier transform of `x` of any length, computed with
+/// Bluestein's algorithm.
+fn dft_moduli(x: &[f64]) -> Vec<f64> {
+    let n = x.len();
+    if n == 0 {
+        return Vec::new();
+    }
+    let len = (2 * n - 1).next_power_of_two();
+    // exp(-i pi k^2 / n), reducing k^2 modulo 2n for accuracy
+    let chirp: Vec<Complex> = (0..n as u64)
+        .map(|k| Complex::from_angle(-PI * ((k * k) % (2 * n as u64)) as f64 / n as f64))
+        .collect();
+
+    let mut a = vec![Complex::default(); len];
+    for ((a, &x), &w) in a.iter_mut().zip(x).zip(&chirp) {
+        *a = Complex { re: x, im: 0.0 } * w;
+    }
+    let mut b = vec![Complex::default(); len];
+    b[0] = chirp[0].conj();
+    for k in 1..n {
+        b[k] = chirp[k].conj();
+        b[len - k] = chirp[k].conj();
+    }
+
+    // the convolution does not depend on the order of the transforms
+    let twiddles: Vec<Complex> = (0..len / 2)
+        .map(|k| Complex::from_angle(-2.0 * PI * k as f64 / len as f64))
+        .collect();
+    fft_bit_reversed(&mut a, &twiddles, 1);
+    fft_bit_reversed(&mut b, &twiddles, 1);
+    a.iter_mut().zip(&b).for_each(|(a, &b)| *a = *a * b);
+    let twiddles: Vec<Complex> = twiddles.iter().map(|w| w.conj()).collect();
+    inverse_fft_bit_reversed(&mut a, &twiddles, 1);
+    a.iter()
+        .zip(&chirp)
+        .map(|(&a, &w)| (a * w).norm() / len as f64)
+        .collect()
+}
+
+/// Transforms `a`, whose length is a power of two, in place with the radix-2 decimation in
+/// frequency FFT, leaving the result in bit-reversed order.
+///
+/// `twiddles` are those of a transform `stride` times as long. The halves are transformed
+/// recursively so that the later stages run in the cache.
+fn fft_bit_reversed(a: &mut [Complex], twiddles: &[Complex], stride: usize) {
+    if a.len() < 2 {
+        return;
+    }
+    let (lo, hi) = a.split_at_mut(a.len() / 2);
+    for (k, (u, v)) in lo.iter_mut().zip(hi.iter_mut()).enumerate() {
+        (*u, *v) = (*u + *v, (*u - *v) * twiddles[k * stride]);
+    }
+    fft_bit_reversed(lo, twiddles, stride * 2);
+    fft_bit_reversed(hi, twiddles, stride * 2);
+}
+
+/// Inverts [`fft_bit_reversed`] up to the factor `a.len()` with the radix-2 decimation in time
+/// FFT, taking the input in bit-reversed order and the conjugate twiddles.
+fn inverse_fft_bit_reversed(a: &mut [Complex], twiddles: &[Complex], stride: usize) {
+    if a.len() < 2 {
+        return;
+    }
+    let (lo, hi) = a.split_at_mut(a.len() / 2);
+    inverse_fft_bit_reversed(lo, twiddles, stride * 2);
+    inverse_fft_bit_reversed(hi, twiddles, stride * 2);
+    for (k, (u, v)) in lo.iter_mut().zip(hi.iter_mut()).enumerate() {
+        let t = *v * twiddles[k * stride];
+        (*u, *v) = (*u + t, *u - t);
+    }
+}
+
+/// Returns the aperiodic templates of `m` bits in ascending order, which cannot overlap
+/// themselves, as used by the non-overlapping template matching test.
+pub fn aperiodic_templates(m: u32) -> Vec<u32> {
+    (0..1u32 << m)
+        .filter(|&t| (1..m).all(|k| t >> k != t & ((1 << (m - k)) - 1)))
+        .collect()
+}
+
+/// Non-overlapping template matching test, section 2.7, for every template of `m` bits in
+/// `templates`, with the sequence split into `n_blocks` blocks.
+pub fn non_overlapping_template(
+    eps: &[u8],
+    m: u32,
+    templates: &[u32],
+    n_blocks: usize,
+) -> Vec<f64> {
+    let block_len = eps.len() / n_blocks;
+    let mut index = vec![usize::MAX; 1 << m];
+    for (i, &t) in templates.iter().enumerate() {
+        index[t as usize] = i;
+    }
+
+    // number of matches of every template in every block
+    let mut matches = vec![vec![0u64; n_blocks]; templates.len()];
+    let mut next = vec![0; templates.len()];
+    for (j, block) in eps.chunks_exact(block_len).take(n_blocks).enumerate() {
+        next.fill(0);
+        let mut window = 0;
+        for (i, &b) in block.iter().enumerate() {
+            window = (window << 1 | b as usize) & ((1 << m) - 1);
+            let start = (i + 1).wrapping_sub(m as usize);
+            if i + 1 < m as usize || index[window] == usize::MAX || start < next[index[window]] {
+                continue;
+            }
+            matches[index[window]][j] += 1;
+            next[index[window]] = start + m as usize;
+        }
+    }
+
+    let m = m as i32;
+    let mean = (block_len as f64 - m as f64 + 1.0) / 2f64.powi(m);
+    let variance =
+        block_len as f64 * (1.0 / 2f64.powi(m) - (2.0 * m as f64 - 1.0) / 2f64.powi(2 * m));
+    matches
+        .iter()
+        .map(|w| {
+            let chi2: f64 = w
+                .iter()
+                .map(|&w| (w as f64 - mean).powi(2) / variance)
+                .sum();
+            igamc(n_blocks as f64 / 2.0, chi2 / 2.0)
+        })
+        .collect()
+}
+
+/// Overlapping template matching test for the template of `m` ones, section 2.8, or `None` for
+/// fewer than 1032 bits.
+///
+/// The class probabilities are computed like the reference implementation, which gives the
+/// results of the examples in the specification.
+pub fn overlapping_template(eps: &[u8], m: usize) -> Option<f64> {
+    const BLOCK_LEN: usize = 1032;
+
+    let eta = (BLOCK_LEN - m + 1) as f64 / 2f64.powi(m as i32) / 2.0;
+    let mut probs: Vec<f64> = (0..5).map(|u| overlapping_probability(u, eta)).collect();
+    probs.push(1.0 - probs.iter().sum::<f64>());
+
+    let blocks = eps.chunks_exact(BLOCK_LEN);
+    if blocks.len() == 0 {
+        return None;
+    }
+    let mut classes = [0; 6];
+    for block in blocks {
+        let matches = block
+            .windows(m)
+            .filter(|w| w.iter().all(|&b| b == 1))
+            .count();
+        classes[matches.min(5)] += 1;
+    }
+    Some(chi_square_p(&classes, &probs))
+}
+
+/// Returns the probability of `u` overlapping matches in a block, as in the reference
+/// implementation.
+fn overlapping_probability(u: u32, eta: f64) -> f64 {
+    if u == 0 {
+        return (-eta).exp();
+    }
+    let u = u as f64;
+    (1..=u as u32)
+        .map(|l| {
+            let l = l as f64;
+            (-eta - u * LN_2 + l * eta.ln() - ln_gamma(l + 1.0) + ln_gamma(u)
+                - ln_gamma(l)
+                - ln_gamma(u - l + 1.0))
+            .exp()
+        })
+        .sum()
+}
+
+/// Maurer's universal statistical test, section 2.9, or `None` for fewer than 387,840 bits.
+pub fn universal(eps: &[u8]) -> Option<f64> {
+    const MIN_BITS: [usize; 11] = [
+        387_840,
+        904_960,
+        2_068_480,
+        4_654_080,
+        10_342_400,
+        22_753_280,
+        49_643_520,
+        107_560_960,
+        231_669_760,
+        496_435_200,
+        1_059_061_760,
+    ];
+    const EXPECTED: [f64; 11] = [
+        5.217_705_2,
+        6.196_250_7,
+        7.183_665_6,
+        8.176_424_8,
+        9.172_324_3,
+        10.170_032,
+        11.168_765,
+        12.168_070,
+        13.167_693,
+        14.167_488,
+        15.167_379,
+    ];
+    const VARIANCE: [f64; 11] = [
+        2.954, 3.125, 3.238, 3.311, 3.356, 3.384, 3.401, 3.410, 3.416, 3.419, 3.421,
+    ];
+
+    let i = MIN_BITS.iter().rposition(|&min| eps.len() >= min)?;
+    let l = i + 6;
+    let q = 10 << l;
+    let k = eps.len() / l - q;
+
+    let mut last = vec![0; 1 << l];
+    let mut sum = 0.0;
+    for (i, block) in eps.chunks_exact(l).take(q + k).enumerate() {
+        let value = block.iter().fold(0, |acc, &b| acc << 1 | b as usize);
+        if i >= q {
+            sum += ((i + 1 - last[value]) as f64).log2();
+        }
+        last[value] = i + 1;
+    }
+
+    let phi = sum / k as f64;
+    let l = l as f64;
+    let c = 0.7 - 0.8 / l + (4.0 + 32.0 / l) * (k as f64).powf(-3.0 / l) / 15.0;
+    let sigma = c * (VARIANCE[i] / k as f64).sqrt();
+    Some(erfc((phi - EXPECTED[i]).abs() / (SQRT_2 * sigma)))
+}
+
+/// Linear complexity test with blocks of `m` bits, section 2.10, or `None` if there is no block.
+///
+/// The class probabilities are those of the reference implementation.
+pub fn linear_complexity(eps: &[u8], m: usize) -> Option<f64> {
+    const PROBS: [f64; 7] = [0.01047, 0.03125, 0.125, 0.5, 0.25, 0.0625, 0.020833];
+
+    let blocks = eps.chunks_exact(m);
+    if blocks.len() == 0 {
+        return None;
+    }
+    let sign = if m.is_multiple_of(2) { 1.0 } else { -1.0 };
+    let mf = m as f64;
+    let mean = mf / 2.0 + (9.0 - sign) / 36.0 - (mf / 3.0 + 2.0 / 9.0) / 2f64.powi(m as i32);
+    let mut classes = [0; 7];
+    for block in blocks {
+        let t = sign * (linear_complexity_of(block) as f64 - mean) + 2.0 / 9.0;
+        let class = [-2.5, -1.5, -0.5, 0.5, 1.5, 2.5]
+            .iter()
+            .position(|&bound| t <= bound)
+            .unwrap_or(6);
+        classes[class] += 1;
+    }
+    Some(chi_square_p(&classes, &PROBS))
+}
+
+/// Returns the length of the shortest LFSR generating `bits`, with the Berlekamp-Massey algorithm
+/// on polynomials packed into words.
+fn linear_complexity_of(bits: &[u8]) -> usize {
+    let words = bits.len() / 64 + 1;
+    // connection polynomials, where bit i is the coefficient of x^i
+    let mut c = vec![0u64; words];
+    let mut b = vec![0u64; words];
+    c[0] = 1;
+    b[0] = 1;
+    // the bits read so far in reverse, where bit i is the bit before the last i
+    let mut recent = vec![0u64; words];
+    let (mut l, mut last_change) = (0, 0);
+    let mut t = vec![0u64; words];
+
+    for (n, &bit) in bits.iter().enumerate() {
+        for i in (1..words).rev() {
+            recent[i] = recent[i] << 1 | recent[i - 1] >> 63;
+        }
+        recent[0] = recent[0] << 1 | bit as u64;
+
+        let discrepancy = c
+            .iter()
+            .zip(&recent)
+            .fold(0, |acc, (c, s)| acc ^ (c & s).count_ones())
+            & 1;
+        if discrepancy == 0 {
+            continue;
+        }
+        t.copy_from_slice(&c);
+        // c ^= b * x^shift
+        let shift = n + 1 - last_change;
+        let (word_shift, bit_shift) = (shift / 64, shift % 64);
+        for i in (word_shift..words).rev() {
+            let mut v = b[i - word_shift] << bit_shift;
+            if bit_shift > 0 && i > word_shift {
+                v |= b[i - word_shift - 1] >> (64 - bit_shift);
+            }
+            c[i] ^= v;
+        }
+        if 2 * l <= n {
+            l = n + 1 - l;
+            last_change = n + 1;
+            b.copy_from_slice(&t);
+        }
+    }
+    l
+}
+
+/// Returns the numbers of overlapping patterns of `m` bits in `eps` wrapping around the end,
+/// indexed by the patterns read from the most significant bit.
+fn circular_patterns(eps: &[u8], m: u32) -> Vec<u64> {
+    let mut counts = vec![0; 1 << m];
+    let mask = (1 << m) - 1;
+    let mut window = 0;
+    let wrapped = eps.iter().chain(eps.iter().cycle().take(m as usize - 1));
+    for (i, &b) in wrapped.enumerate() {
+        window = (window << 1 | b as usize) & mask;
+        if i + 1 >= m as usize {
+            counts[window] += 1;
+        }
+    }
+    counts
+}
+
+/// Returns the numbers of the patterns one bit shorter than those counted in `counts`.
+fn shorter_patterns(counts: &[u64]) -> Vec<u64> {
+    counts.chunks_exact(2).map(|c| c[0] + c[1]).collect()
+}
+
+/// Serial test for patterns of `m` bits, section 2.11.
+pub fn serial(eps: &[u8], m: u32) -> (f64, f64) {
+    let n = eps.len() as f64;
+    let psi2 = |counts: &[u64]| {
+        let sum: f64 = counts.iter().map(|&v| (v as f64).powi(2)).sum();
+        sum * counts.len() as f64 / n - n
+    };
+    let counts = circular_patterns(eps, m);
+    let counts1 = shorter_patterns(&counts);
+    let counts2 = shorter_patterns(&counts1);
+    let psi2 = [psi2(&counts), psi2(&counts1), psi2(&counts2)];
+    // psi2 of empty patterns is zero
+    let psi2 = match m {
+        1 => [psi2[0], 0.0, 0.0],
+        2 => [psi2[0], psi2[1], 0.0],
+        _ => psi2,
+    };
+    let del1 = psi2[0] - psi2[1];
+    let del2 = psi2[0] - 2.0 * psi2[1] + psi2[2];
+    (
+        igamc(2f64.powi(m as i32 - 2), del1 / 2.0),
+        igamc(2f64.powi(m as i32 - 3), del2 / 2.0),
+    )
+}
+
+/// Approximate entropy test for patterns of `m` bits, section 2.12.
+pub fn approximate_entropy(eps: &[u8], m: u32) -> f64 {
+    let n = eps.len() as f64;
+    let phi = |counts: &[u64]| -> f64 {
+        counts
+            .iter()
+            .filter(|&&v| v > 0)
+            .map(|&v| v as f64 / n * (v as f64 / n).ln())
+            .sum()
+    };
+    let counts = circular_patterns(eps, m + 1);
+    let apen = phi(&shorter_patterns(&counts)) - phi(&counts);
+    let chi2 = 2.0 * n * (LN_2 - apen);
+    igamc(2f64.powi(m as i32 - 1), chi2 / 2.0)
+}
+
+/// Cumulative sums test forward and backward, section 2.13.
+pub fn cumulative_sums(eps: &[u8]) -> (f64, f64) {
+    let (mut walk, mut max, mut min) = (0i64, 0i64, 0i64);
+    for &b in eps {
+        walk += 2 * b as i64 - 1;
+        max = max.max(walk);
+        min = min.min(walk);
+    }
+    let n = eps.len() as i64;
+    (
+        cumulative_sums_p(n, max.max(-min)),
+        cumulative_sums_p(n, (walk - min).max(max - walk)),
+    )
+}
+
+/// Returns the random walk of `eps` and its number of cycles, or `None` if there are too few
+/// cycles for the random excursions tests.
+fn excursion_walk(eps: &[u8]) -> Option<(Vec<i64>, usize)> {
+    let mut s = 0;
+    let walk: Vec<i64> = eps
+        .iter()
+        .map(|&b| {
+            s += 2 * b as i64 - 1;
+            s
+        })
+        .collect();
+    let cycles = walk.iter().filter(|&&s| s == 0).count() + (s != 0) as usize;
+    let min_cycles = (0.005 * (eps.len() as f64).sqrt()).max(500.0);
+    (cycles as f64 >= min_cycles).then_some((walk, cycles))
+}
+
+/// Random excursions test for the states [`EXCURSION_STATES`], section 2.14, or `None` if there
+/// are fewer than 500 cycles.
+pub fn random_excursions(eps: &[u8]) -> Option<Vec<f64>> {
+    let (walk, cycles) = excursion_walk(eps)?;
+    // numbers of cycles that visit every state 0, 1, ... 4 or at least 5 times
+    let mut classes = [[0u64; 6]; 8];
+    let mut visits = [0usize; 8];
+    let end = walk.last().is_some_and(|&s| s != 0).then_some(&0);
+    for &s in walk.iter().chain(end) {
+        if s == 0 {
+            for (classes, v) in classes.iter_mut().zip(&mut visits) {
+                classes[(*v).min(5)] += 1;
+                *v = 0;
+            }
+        } else if let Some(i) = EXCURSION_STATES.iter().position(|&x| x == s) {
+            visits[i] += 1;
+        }
+    }
+
+    let p_values = EXCURSION_STATES
+        .iter()
+        .zip(&classes)
+        .map(|(&x, classes)| {
+            let q = 1.0 / (2.0 * x.abs() as f64);
+            let mut probs = [0.0; 6];
+            probs[0] = 1.0 - q;
+            for (k, p) in probs.iter_mut().enumerate().take(5).skip(1) {
+                *p = q * q * (1.0 - q).powi(k as i32 - 1);
+            }
+            probs[5] = q * (1.0 - q).powi(4);
+            debug_assert_eq!(classes.iter().sum::<u64>(), cycles as u64);
+            chi_square_p(classes, &probs)
+        })
+        .collect();
+    Some(p_values)
+}
+
+/// Random excursions variant test for the states [`VARIANT_STATES`], section 2.15, or `None` if
+/// there are fewer than 500 cycles.
+pub fn random_excursions_variant(eps: &[u8]) -> Option<Vec<f64>> {
+    let (walk, cycles) = excursion_walk(eps)?;
+    let mut visits = [0u64; 19];
+    for &s in &walk {
+        if s.abs() <= 9 {
+            visits[(s + 9) as usize] += 1;
+        }
+    }
+    let j = cycles as f64;
+    let p_values = VARIANT_STATES
+        .iter()
+        .map(|&x| {
+            let xi = visits[(x + 9) as usize] as f64;
+            erfc((xi - j).abs() / (2.0 * j * (4.0 * x.abs() as f64 - 2.0)).sqrt())
+        })
+        .collect();
+    Some(p_values)
+}
+
+/// Returns the p-value of a chi-square test of the numbers of observations in classes with the
+/// probabilities `probs`.
+fn chi_square_p(classes: &[u64], probs: &[f64]) -> f64 {
+    let n = classes.iter().sum::<u64>() as f64;
+    let chi2: f64 = classes
+        .iter()
+        .zip(probs)
+        .map(|(&v, &p)| (v as f64 - n * p).powi(2) / (n * p))
+        .sum();
+    igamc((probs.len() - 1) as f64 / 2.0, chi2 / 2.0)
+}
+
+/// P-values of a test, or of one of the templates or states of a test, across sequences.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Row {
+    /// Name of the test in the report of the reference implementation, followed by the template
+    /// or state if any.
+    pub name: String,
+    /// P-values of the sequences to which the test applied.
+    pub p_values: Vec<f64>,
+}
+
+impl Row {
+    /// Returns the number of p-values of at least `alpha`.
+    pub fn passed(&self, alpha: f64) -> usize {
+        self.p_values.iter().filter(|&&p| p >= alpha).count()
+    }
+
+    /// Returns the minimum proportion of passing sequences: the lower end of the range of three
+    /// standard deviations around `1 - alpha`.
+    pub fn min_proportion(&self, alpha: f64) -> f64 {
+        let p = 1.0 - alpha;
+        p - 3.0 * (p * alpha / self.p_values.len() as f64).sqrt()
+    }
+
+    /// Returns whether the proportion of passing sequences is acceptable.
+    pub fn proportion_passed(&self, alpha: f64) -> bool {
+        self.passed(alpha) as f64 >= self.min_proportion(alpha) * self.p_values.len() as f64
+    }
+
+    /// Returns the numbers of p-values in the ten intervals [0, 0.1), [0.1, 0.2), ... [0.9, 1].
+    pub fn histogram(&self) -> [usize; 10] {
+        let mut bins = [0; 10];
+        for &p in &self.p_values {
+            bins[((p * 10.0) as usize).min(9)] += 1;
+        }
+        bins
+    }
+
+    /// Returns the p-value of a chi-square test of the uniformity of the p-values, or `None` if
+    /// there are none.
+    pub fn uniformity(&self) -> Option<f64> {
+        if self.p_values.is_empty() {
+            return None;
+        }
+        let probs = [0.1; 10];
+        let bins = self.histogram().map(|b| b as u64);
+        Some(chi_square_p(&bins, &probs))
+    }
+
+    /// Returns whether the p-values are uniform, or `None` if there are fewer than
+    /// [`MIN_UNIFORMITY_SAMPLES`].
+    pub fn uniformity_passed(&self) -> Option<bool> {
+        match self.uniformity() {
+            Some(p) if self.p_values.len() >= MIN_UNIFORMITY_SAMPLES => Some(p >= UNIFORMITY_ALPHA),
+            _ => None,
+        }
+    }
+}
+
+/// Results of the tests on all sequences.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Report {
+    /// Number of sequences tested.
+    pub sequences: usize,
+    /// Rows in the order of the reference implementation.
+    pub rows: Vec<Row>,
+}
+
+/// Sink that runs all tests of SP 800-22 on the sequences of bits written to it, reading every
+/// byte from the most significant bit.
+///
+/// The parameters default to those of the reference implementation.
+#[derive(Clone, Debug)]
+pub struct Battery {
+    sequence_len: usize,
+    block_frequency_len: usize,
+    template_len: u32,
+    overlapping_template_len: usize,
+    linear_complexity_len: usize,
+    serial_len: u32,
+    approximate_entropy_len: u32,
+    templates: Vec<u32>,
+    bits: Vec<u8>,
+    sequences: usize,
+    rows: Vec<Row>,
+}
+
+impl Battery {
+    /// Creates a battery that tests sequences of `sequence_len` bits; any rest is not tested.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `sequence_len` is zero.
+    pub fn new(sequence_len: usize) -> Self {
+        assert!(sequence_len > 0, "sequence length must be positive");
+        Self {
+            sequence_len,
+            block_frequency_len: 128,
+            template_len: 9,
+            overlapping_template_len: 9,
+            linear_complexity_len: 500,
+            serial_len: 16,
+            approximate_entropy_len: 10,
+            templates: aperiodic_templates(9),
+            bits: Vec::with_capacity(sequence_len),
+            sequences: 0,
+            rows: Vec::new(),
+        }
+    }
+
+    /// Sets the block length of the block frequency test [default: 128].
+    pub fn block_frequency_len(mut self, m: usize) -> Self {
+        assert!(m > 0, "block length must be positive");
+        self.block_frequency_len = m;
+        self
+    }
+
+    /// Sets the template length of the non-overlapping template matching test, 2 to 21
+    /// [default: 9].
+    pub fn template_len(mut self, m: u32) -> Self {
+        assert!((2..=21).contains(&m), "template length must be 2 to 21");
+        self.template_len = m;
+        self.templates = aperiodic_templates(m);
+        self
+    }
+
+    /// Sets the template length of the overlapping template matching test [default: 9].
+    pub fn overlapping_template_len(mut self, m: usize) -> Self {
+        assert!((1..=1032).contains(&m), "template length must be 1 to 1032");
+        self.overlapping_template_len = m;
+        self
+    }
+
+    /// Sets the block length of the linear complexity test [default: 500].
+    pub fn linear_complexity_len(mut self, m: usize) -> Self {
+        assert!(m > 0, "block length must be positive");
+        self.linear_complexity_len = m;
+        self
+    }
+
+    /// Sets the pattern length of the serial test, 1 to 24 [default: 16].
+    pub fn serial_len(mut self, m: u32) -> Self {
+        assert!((1..=24).contains(&m), "pattern length must be 1 to 24");
+        self.serial_len = m;
+        self
+    }
+
+    /// Sets the pattern length of the approximate entropy test, 1 to 23 [default: 10].
+    pub fn approximate_entropy_len(mut self, m: u32) -> Self {
+        assert!((1..=23).contains(&m), "pattern length must be 1 to 23");
+        self.approximate_entropy_len = m;
+        self
+    }
+
+    /// Returns the number of sequences tested so far.
+    pub const fn sequences(&self) -> usize {
+        self.sequences
+    }
+
+    /// Runs all tests on `eps`, returning the p-values of the rows in order, or `None` for tests
+    /// that do not apply.
+    pub fn test_sequence(&self, eps: &[u8]) -> Vec<(String, Option<f64>)> {
+        let mut rows = Vec::new();
+        let mut push = |name: &str, p: Option<f64>| rows.push((name.to_owned(), p));
+
+        push("Frequency", Some(frequency(eps)));
+        push(
+            "BlockFrequency",
+            (eps.len() >= self.block_frequency_len)
+                .then(|| block_frequency(eps, self.block_frequency_len)),
+        );
+        let (forward, backward) = cumulative_sums(eps);
+        push("CumulativeSums forward", Some(forward));
+        push("CumulativeSums backward", Some(backward));
+        push("Runs", Some(runs(eps)));
+        push("LongestRun", longest_run(eps));
+        push("Rank", rank(eps));
+        push("FFT", Some(dft(eps)));
+
+        let m = self.template_len;
+        let applies = eps.len() / 8 >= m as usize;
+        let p_values = applies.then(|| non_overlapping_template(eps, m, &self.templates, 8));
+        for (i, &t) in self.templates.iter().enumerate() {
+            let name = format!("NonOverlappingTemplate {:0w$b}", t, w = m as usize);
+            push(&name, p_values.as_ref().map(|p| p[i]));
+        }
+
+        push(
+            "OverlappingTemplate",
+            overlapping_template(eps, self.overlapping_template_len),
+        );
+        push("Universal", universal(eps));
+        push(
+            "ApproximateEntropy",
+            Some(approximate_entropy(eps, self.approximate_entropy_len)),
+        );
+
+        let p_values = random_excursions(eps);
+        for (i, x) in EXCURSION_STATES.iter().enumerate() {
+            let name = format!("RandomExcursions x = {:+}", x);
+            push(&name, p_values.as_ref().map(|p| p[i]));
+        }
+        let p_values = random_excursions_variant(eps);
+        for (i, x) in VARIANT_STATES.iter().enumerate() {
+            let name = format!("RandomExcursionsVariant x = {:+}", x);
+            push(&name, p_values.as_ref().map(|p| p[i]));
+        }
+
+        let (p1, p2) = serial(eps, self.serial_len);
+        push("Serial 1", Some(p1));
+        push("Serial 2", Some(p2));
+        push(
+            "LinearComplexity",
+            linear_complexity(eps, self.linear_complexity_len),
+        );
+        rows
+    }
+
+    fn push_bit(&mut self, bit: u8) {
+        self.bits.push(bit);
+        if self.bits.len() == self.sequence_len {
+            let results = self.test_sequence(&self.bits);
+            if self.rows.is_empty() {
+                self.rows = results
+                    .iter()
+                    .map(|(name, _)| Row {
+                        name: name.clone(),
+                        p_values: Vec::new(),
+                    })
+                    .collect();
+            }
+            for (row, (_, p)) in self.rows.iter_mut().zip(results) {
+                row.p_values.extend(p);
+            }
+            self.bits.clear();
+            self.sequences += 1;
+        }
+    }
+
+    /// Returns the p-values of all sequences.
+    pub fn finish(self) -> Report {
+        Report {
+            sequences: self.sequences,
+            rows: self.rows,
+        }
+    }
+}
+
+impl io::Write for Battery {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for &e in buf {
+            for i in (0..8).rev() {
+                self.push_bit(e >> i & 1);
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write as _;
+
+    use super::*;
+    use crate::Builder;
+
+    /// The first 1,000,000 bits of the binary expansion of e, as in `data/data.e` of the
+    /// reference implementation.
+    fn e_bits() -> Vec<u8> {
+        let bytes = include_bytes!("testdata/e.bin");
+        bytes
+            .iter()
+            .flat_map(|&b| (0..8).rev().map(move |i| b >> i & 1))
+            .take(1_000_000)
+            .collect()
+    }
+
+    fn bits(s: &str) -> Vec<u8> {
+        s.bytes()
+            .filter(|c| !c.is_ascii_whitespace())
+            .map(|c| c - b'0')
+            .collect()
+    }
+
+    fn assert_p(actual: f64, expected: f64, what: &str) {
+        assert!(
+            (actual - expected).abs() < 1e-6,
+            "{}: p = {}, expected {}",
+            what,
+            actual,
+            expected
+        );
+    }
+
+    const EPSILON_100: &str = "1100100100001111110110101010001000100001011010001100001000110100\
+                               110001001100011001100010100010111000";
+
+    /// Examples of section 2 on short sequences.
+    #[test]
+    fn short_examples() {
+        let eps = bits(EPSILON_100);
+        assert_p(frequency(&eps), 0.109_599, "frequency");
+        assert_p(block_frequency(&eps, 10), 0.706_438, "block frequency");
+        assert_p(runs(&eps), 0.500_798, "runs");
+        assert_p(
+            approximate_entropy(&eps, 2),
+            0.235_301,
+            "approximate entropy",
+        );
+        assert_p(
+            cumulative_sums(&eps).0,
+            0.219_194,
+            "cumulative sums forward",
+        );
+
+        let eps = bits(
+            "11001100000101010110110001001100111000000000001001001101010100010001001111010110\
+             100000001101011111001100111001101101100010110010",
+        );
+        assert_p(longest_run(&eps).unwrap(), 0.180_609, "longest run");
+
+        assert_p(frequency(&bits("1011010101")), 0.527_089, "frequency");
+        assert_p(
+            block_frequency(&bits("0110011010"), 3),
+            0.801_252,
+            "block frequency",
+        );
+        assert_p(runs(&bits("1001101011")), 0.147_232, "runs");
+        let p = non_overlapping_template(&bits("10100100101110010110"), 3, &[0b001], 2);
+        assert_p(p[0], 0.344_154, "non-overlapping template");
+        let (p1, p2) = serial(&bits("0011011101"), 3);
+        assert_p(p1, 0.808_792, "serial 1");
+        assert_p(p2, 0.670_320, "serial 2");
+        assert_p(
+            approximate_entropy(&bits("0100110101"), 3),
+            0.261_961,
+            "approximate entropy",
+        );
+        assert_p(
+            cumulative_sums(&bits("1011010111")).0,
+            0.411_658,
+            "cumulative sums",
+        );
+    }
+
+    /// Examples of section 2 on the expansion of e.
+    #[test]
+    fn e_examples() {
+        let eps = e_bits();
+        assert_p(dft(&eps), 0.847_187, "dft");
+        assert_p(rank(&eps[..100_000]).unwrap(), 0.532_069, "rank");
+        assert_p(
+            overlapping_template(&eps, 9).unwrap(),
+            0.110_434,
+            "overlapping template",
+        );
+        assert_p(universal(&eps).unwrap(), 0.282_568, "universal");
+        assert_p(
+            linear_complexity(&eps, 1000).unwrap(),
+            0.845_406,
+            "linear complexity",
+        );
+        let (p1, p2) = serial(&eps, 2);
+        assert_p(p1, 0.843_764, "serial 1");
+        assert_p(p2, 0.561_915, "serial 2");
+
+        let expected = [
+            0.573_306, 0.197_996, 0.164_011, 0.007_779, 0.786_868, 0.440_912, 0.797_854, 0.778_186,
+        ];
+        for ((p, e), x) in random_excursions(&eps)
+            .unwrap()
+            .iter()
+            .zip(expected)
+            .zip(EXCURSION_STATES)
+        {
+            assert_p(*p, e, &format!("random excursions x = {}", x));
+        }
+        let expected = [
+            0.858_946, 0.794_755, 0.576_249, 0.493_417, 0.633_873, 0.917_283, 0.934_708, 0.816_012,
+            0.826_009, 0.137_861, 0.200_642, 0.441_254, 0.939_291, 0.505_683, 0.445_935, 0.512_207,
+            0.538_635, 0.593_930,
+        ];
+        for ((p, e), x) in random_excursions_variant(&eps)
+            .unwrap()
+            .iter()
+            .zip(expected)
+            .zip(VARIANT_STATES)
+        {
+            assert_p(*p, e, &format!("random excursions variant x = {}", x));
+        }
+    }
+
+    /// Results for `data.e` with the default parameters listed in appendix B.
+    #[test]
+    fn e_default_parameters() {
+        let results = Battery::new(1_000_000).test_sequence(&e_bits());
+        let expected = [
+            ("Frequency", 0.953_749),
+            ("BlockFrequency", 0.211_072),
+            ("CumulativeSums forward", 0.669_887),
+            ("CumulativeSums backward", 0.724_266),
+            ("Runs", 0.561_917),
+            ("LongestRun", 0.718_945),
+            ("Rank", 0.306_156),
+            ("FFT", 0.847_187),
+            ("NonOverlappingTemplate 000000001", 0.078_790),
+            ("OverlappingTemplate", 0.110_434),
+            ("Universal", 0.282_568),
+            ("ApproximateEntropy", 0.700_073),
+            ("RandomExcursions x = +1", 0.786_868),
+            ("RandomExcursionsVariant x = -1", 0.826_009),
+            ("Serial 1", 0.766_182),
+            ("Serial 2", 0.462_921),
+            ("LinearComplexity", 0.826_335),
+        ];
+        for (name, p) in expected {
+            let (_, actual) = results.iter().find(|(n, _)| n == name).unwrap();
+            assert_p(actual.unwrap(), p, name);
+        }
+        assert_eq!(results.len(), 188);
+        assert!(results.iter().all(|(_, p)| p.is_some()));
+    }
+
+    #[test]
+    fn templates() {
+        assert_eq!(aperiodic_templates(2), [0b01, 0b10]);
+        assert_eq!(aperiodic_templates(3), [0b001, 0b011, 0b100, 0b110]);
+        assert_eq!(aperiodic_templates(9).len(), 148);
+        assert_eq!(aperiodic_templates(10).len(), 284);
+    }
+
+    #[test]
+    fn linear_complexities() {
+        assert_eq!(linear_complexity_of(&bits("1101011110001")), 4);
+        assert_eq!(linear_complexity_of(&bits("0000000000")), 0);
+        assert_eq!(linear_complexity_of(&bits("0000000001")), 10);
+        // an m-sequence of x^7 + x + 1 has linear complexity 7
+        let mut s = vec![1, 0, 0, 0, 0, 0, 0];
+        for i in 7..300 {
+            s.push(s[i - 7] ^ s[i - 6]);
+        }
+        assert_eq!(linear_complexity_of(&s), 7);
+    }
+
+    #[test]
+    fn battery_summarizes_sequences() {
+        let mut battery = Battery::new(1 << 17);
+        Builder::new()
+            .seed(b"sp800-22")
+            .build()
+            .write_to(&mut battery, Some(3 << 14))
+            .unwrap();
+        battery.write_all(&[0xff]).unwrap();
+        assert_eq!(battery.sequences(), 3);
+        let report = battery.finish();
+        assert_eq!(report.sequences, 3);
+        assert_eq!(report.rows.len(), 188);
+        assert_eq!(report.rows[8].name, "NonOverlappingTemplate 000000001");
+        for row in &report.rows {
+            match row.name.as_str() {
+                // too few cycles in short sequences
+                name if name.starts_with("RandomExcursions") => assert!(row.p_values.len() < 3),
+                "Universal" => assert!(row.p_values.is_empty()),
+                name => {
+                    assert_eq!(row.p_values.len(), 3, "{}", name);
+                    assert!(row.p_values.iter().all(|p| (0.0..=1.0).contains(p)));
+                    assert_eq!(row.uniformity_passed(), None);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn second_level_checks() {
+        let uniform = Row {
+            name: "uniform".to_owned(),
+            p_values: (0..100).map(|i| (i as f64 + 0.5) / 100.0).collect(),
+        };
+        assert_eq!(uniform.histogram(), [10; 10]);
+        assert_p(uniform.uniformity().unwrap(), 1.0, "uniformity");
+        assert_eq!(uniform.uniformity_passed(), Some(true));
+        assert_eq!(uniform.passed(0.01), 99);
+        // 0.99 - 3 * sqrt(0.99 * 0.01 / 100)
+        assert_p(
+            uniform.min_proportion(0.01),
+            0.960_150,
+            "minimum proportion",
+        );
+        assert!(uniform.proportion_passed(0.01));
+
+        let skewed = Row {
+            name: "skewed".to_owned(),
+            p_values: (0..100).map(|i| i as f64 / 1000.0).collect(),
+        };
+        assert!(skewed.uniformity().unwrap() < UNIFORMITY_ALPHA);
+        assert_eq!(skewed.uniformity_passed(), Some(false));
+        assert!(!skewed.proportion_passed(0.01));
+    }
+}
@@ -0,0 +1,1234 @@
+//! Entropy estimation of NIST SP 800-90B for noise sources.
+//!
+//! The estimators take samples of up to eight bits, one per byte, like the `ea_non_iid` tool of
+//! the reference implementation, and return the min-entropy per sample; the estimators defined
+//! only for binary samples take one bit per byte. [`NonIid`] runs all estimators of section 6.3
+//! on samples and on their bitstring, and [`permutation_tests`] runs the permutation tests of
+//! section 5.1 to check whether the samples are IID.
+
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    hash::{BuildHasherDefault, Hasher},
+    io,
+};
+
+use crate::{range::lemire_u64, RandomStream};
+
+/// Number of permutations of the IID permutation tests.
+pub const PERMUTATIONS: u32 = 10_000;
+
+/// Number of bits of the bitstring of non-binary samples estimated with
+/// [`NonIid::truncate_bitstring`].
+pub const TRUNCATED_BITSTRING_LEN: usize = 1_000_000;
+
+/// Quantile of the standard normal distribution for the upper bounds of 99% confidence.
+const Z_99: f64 = 2.576;
+
+/// Minimum number of occurrences of the tuples counted by the t-tuple estimate.
+const MIN_TUPLE_COUNT: u64 = 35;
+
+/// Window lengths of the subpredictors of the MultiMCW prediction estimate.
+const MCW_WINDOWS: [usize; 4] = [63, 255, 1023, 4095];
+
+/// Number of subpredictors of the lag prediction estimate.
+const LAG_DEPTH: usize = 128;
+
+/// Number of Markov models of the MultiMMC prediction estimate, and their maximum number of
+/// entries.
+const MMC_DEPTH: usize = 16;
+const MMC_MAX_ENTRIES: usize = 100_000;
+
+/// Longest context of the LZ78Y prediction estimate, and the maximum size of its dictionary.
+const LZ78Y_DEPTH: usize = 16;
+const LZ78Y_MAX_DICTIONARY: usize = 65_536;
+
+/// Min-entropy estimate in bits per sample, or `None` if there were too few samples for it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Estimate {
+    pub name: String,
+    pub min_entropy: Option<f64>,
+}
+
+impl Estimate {
+    fn new(name: &str, min_entropy: Option<f64>) -> Self {
+        Self {
+            name: name.to_owned(),
+            min_entropy,
+        }
+    }
+}
+
+/// Estimates of [`NonIid::assess`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Assessment {
+    /// Number of bits per sample.
+    pub bits: u32,
+    /// Estimates of the samples, in bits per sample.
+    pub estimates: Vec<Estimate>,
+    /// Number of bits of the bitstring of non-binary samples.
+    pub bitstring_len: usize,
+    /// Estimates of the bitstring of non-binary samples, in bits per bit.
+    pub bitstring: Vec<Estimate>,
+}
+
+impl Assessment {
+    /// Returns the lowest estimate of the samples, `H_original`.
+    pub fn original(&self) -> Option<f64> {
+        lowest(&self.estimates)
+    }
+
+    /// Returns the lowest estimate of the bitstring, `H_bitstring`, or `None` for binary samples.
+    pub fn bitstring(&self) -> Option<f64> {
+        lowest(&self.bitstring)
+    }
+
+    /// Returns the min-entropy per sample: the lower of `H_original` and `H_bitstring` times the
+    /// number of bits per sample.
+    pub fn min_entropy(&self) -> Option<f64> {
+        match (self.original(), self.bitstring()) {
+            (Some(h), Some(bitstring)) => Some(h.min(self.bits as f64 * bitstring)),
+            (h, bitstring) => h.or(bitstring.map(|h| self.bits as f64 * h)),
+        }
+    }
+}
+
+fn lowest(estimates: &[Estimate]) -> Option<f64> {
+    estimates
+        .iter()
+        .filter_map(|e| e.min_entropy)
+        .reduce(f64::min)
+}
+
+/// Non-IID track of section 6.
+#[derive(Clone, Debug)]
+pub struct NonIid {
+    bits: u32,
+    truncate: bool,
+}
+
+impl NonIid {
+    /// Creates an assessment of samples of `bits` bits.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bits` is not between 1 and 8.
+    pub fn new(bits: u32) -> Self {
+        assert!((1..=8).contains(&bits), "samples must have 1 to 8 bits");
+        Self {
+            bits,
+            truncate: false,
+        }
+    }
+
+    /// Estimates the bitstring of non-binary samples on its first [`TRUNCATED_BITSTRING_LEN`]
+    /// bits only, like the `-t` option of `ea_non_iid`.
+    pub fn truncate_bitstring(mut self, truncate: bool) -> Self {
+        self.truncate = truncate;
+        self
+    }
+
+    /// Runs all estimators of section 6.3 on `samples`, and on their bitstring if the samples
+    /// are not binary, as in section 3.1.3.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a sample has more than the given number of bits.
+    pub fn assess(&self, samples: &[u8]) -> Assessment {
+        assert!(
+            samples.iter().all(|&s| (s as u32) < 1 << self.bits),
+            "samples must have at most {} bits",
+            self.bits
+        );
+        let estimates = estimates(samples, self.bits);
+        let (bitstring_len, bitstring) = match self.bits {
+            1 => (0, Vec::new()),
+            _ => {
+                let mut bitstring = to_bitstring(samples, self.bits);
+                if self.truncate {
+                    bitstring.truncate(TRUNCATED_BITSTRING_LEN);
+                }
+                (bitstring.len(), self::estimates(&bitstring, 1))
+            }
+        };
+        Assessment {
+            bits: self.bits,
+            estimates,
+            bitstring_len,
+            bitstring,
+        }
+    }
+}
+
+/// Runs the estimators applicable to samples of `bits` bits, in the order of section 6.3.
+fn estimates(samples: &[u8], bits: u32) -> Vec<Estimate> {
+    let mut estimates = vec![Estimate::new(
+        "Most Common Value",
+        most_common_value(samples),
+    )];
+    if bits == 1 {
+        estimates.push(Estimate::new("Collision", collision(samples)));
+        estimates.push(Estimate::new("Markov", markov(samples)));
+        estimates.push(Estimate::new("Compression", compression(samples)));
+    }
+    let repeats = Repeats::new(samples);
+    estimates.push(Estimate::new("t-Tuple", repeats.t_tuple()));
+    estimates.push(Estimate::new("LRS", repeats.lrs()));
+    estimates.push(Estimate::new(
+        "MultiMCW Prediction",
+        multi_mcw(samples, bits),
+    ));
+    estimates.push(Estimate::new("Lag Prediction", lag(samples, bits)));
+    estimates.push(Estimate::new(
+        "MultiMMC Prediction",
+        multi_mmc(samples, bits),
+    ));
+    estimates.push(Estimate::new("LZ78Y Prediction", lz78y(samples, bits)));
+    estimates
+}
+
+/// Returns the bits of the samples of `bits` bits, from the most significant, one per byte.
+fn to_bitstring(samples: &[u8], bits: u32) -> Vec<u8> {
+    samples
+        .iter()
+        .flat_map(|&s| (0..bits).rev().map(move |i| s >> i & 1))
+        .collect()
+}
+
+/// Returns the upper bound of the 99% confidence interval of the probability `p` estimated from
+/// `n` observations.
+fn upper_bound(p: f64, n: usize) -> f64 {
+    (p + Z_99 * (p * (1.0 - p) / (n as f64 - 1.0)).sqrt()).min(1.0)
+}
+
+/// Returns the `x` in `[lo, hi]` at which the decreasing function `f` crosses `target`, found by
+/// bisection: `lo` if `f` stays below it and `hi` if `f` stays above it.
+fn bisect(f: impl Fn(f64) -> f64, target: f64, mut lo: f64, mut hi: f64) -> f64 {
+    if f(lo) <= target {
+        return lo;
+    }
+    for _ in 0..64 {
+        let mid = (lo + hi) / 2.0;
+        if f(mid) > target {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    (lo + hi) / 2.0
+}
+
+/// Most common value estimate, section 6.3.1, or `None` for fewer than two samples.
+pub fn most_common_value(samples: &[u8]) -> Option<f64> {
+    if samples.len() < 2 {
+        return None;
+    }
+    let mut counts = [0u64; 256];
+    for &s in samples {
+        counts[s as usize] += 1;
+    }
+    let p = *counts.iter().max()? as f64 / samples.len() as f64;
+    Some(-upper_bound(p, samples.len()).log2())
+}
+
+/// Collision estimate of binary samples, section 6.3.2, or `None` for fewer than two collisions.
+///
+/// The equation for the most likely probability is solved directly: the expected time until a
+/// collision of binary samples with probabilities `p` and `q` is `2 + 2 * p * q`.
+pub fn collision(bits: &[u8]) -> Option<f64> {
+    // times are 2 or 3, as one of three bits always collides with another
+    let (mut v, mut sum, mut sum_sq) = (0u64, 0u64, 0u64);
+    let mut i = 0;
+    while i + 1 < bits.len() {
+        let t = match bits[i] == bits[i + 1] {
+            true => 2,
+            false if i + 2 < bits.len() => 3,
+            false => break,
+        };
+        v += 1;
+        sum += t;
+        sum_sq += t * t;
+        i += t as usize;
+    }
+    if v < 2 {
+        return None;
+    }
+    let mean = sum as f64 / v as f64;
+    let sigma = ((sum_sq as f64 - v as f64 * mean * mean) / (v - 1) as f64)
+        .max(0.0)
+        .sqrt();
+    let mean = mean - Z_99 * sigma / (v as f64).sqrt();
+    // p >= q solving 2 + 2 * p * (1 - p) = mean, or the nearest bound
+    let p = (1.0 + (5.0 - 2.0 * mean).clamp(0.0, 1.0).sqrt()) / 2.0;
+    Some(-p.log2())
+}
+
+/// Markov estimate of binary samples, section 6.3.3, in bits per bit, or `None` for fewer than two
+/// samples.
+pub fn markov(bits: &[u8]) -> Option<f64> {
+    const LEN: i32 = 128;
+
+    if bits.len() < 2 {
+        return None;
+    }
+    let ones = bits.iter().filter(|&&b| b == 1).count();
+    let p1 = ones as f64 / bits.len() as f64;
+    let p = [1.0 - p1, p1];
+    let mut transitions = [[0u64; 2]; 2];
+    for pair in bits.windows(2) {
+        transitions[pair[0] as usize][pair[1] as usize] += 1;
+    }
+    let t = transitions.map(|[to0, to1]| {
+        let n = (to0 + to1).max(1) as f64;
+        [to0 as f64 / n, to1 as f64 / n]
+    });
+
+    // logarithms of the probabilities of the most likely sequences of 128 bits
+    let log = f64::log2;
+    let candidates = [
+        log(p[0]) + (LEN - 1) as f64 * log(t[0][0]),
+        log(p[0]) + (LEN / 2) as f64 * log(t[0][1]) + (LEN / 2 - 1) as f64 * log(t[1][0]),
+        log(p[0]) + log(t[0][1]) + (LEN - 2) as f64 * log(t[1][1]),
+        log(p[1]) + log(t[1][0]) + (LEN - 2) as f64 * log(t[0][0]),
+        log(p[1]) + (LEN / 2) as f64 * log(t[1][0]) + (LEN / 2 - 1) as f64 * log(t[0][1]),
+        log(p[1]) + (LEN - 1) as f64 * log(t[1][1]),
+    ];
+    let max = candidates.into_iter().fold(f64::NEG_INFINITY, f64::max);
+    Some((-max / LEN as f64).min(1.0))
+}
+
+/// Compression estimate of binary samples, section 6.3.4, in bits per bit, or `None` if there
+/// are not more than 1000 blocks of six bits.
+pub fn compression(bits: &[u8]) -> Option<f64> {
+    const B: usize = 6;
+    const D: usize = 1000;
+    const C: f64 = 0.5907;
+
+    let symbols: Vec<usize> = bits
+        .chunks_exact(B)
+        .map(|block| block.iter().fold(0, |acc, &b| acc << 1 | b as usize))
+        .collect();
+    let blocks = symbols.len();
+    if blocks < D + 2 {
+        return None;
+    }
+    let nu = (blocks - D) as f64;
+
+    // 1-based index of the last occurrence of every symbol
+    let mut last = [0usize; 1 << B];
+    for (i, &s) in symbols[..D].iter().enumerate() {
+        last[s] = i + 1;
+    }
+    let logs: Vec<f64> = (0..=blocks).map(|u| (u as f64).log2()).collect();
+    let (mut sum, mut sum_sq) = (0.0, 0.0);
+    for (i, &s) in symbols.iter().enumerate().skip(D) {
+        let i = i + 1;
+        let distance = if last[s] > 0 { i - last[s] } else { i };
+        last[s] = i;
+        sum += logs[distance];
+        sum_sq += logs[distance].powi(2);
+    }
+    let mean = sum / nu;
+    let sigma = C * (sum_sq / (nu - 1.0) - mean * mean).max(0.0).sqrt();
+    let mean = mean - Z_99 * sigma / nu.sqrt();
+
+    // expected mean of the logarithms of the distances of a symbol of probability z, with the
+    // sums over the symbols and over the distances exchanged
+    let g = |z: f64| -> f64 {
+        let mut sum = 0.0;
+        // (1 - z)^(u - 1)
+        let mut power = 1.0;
+        for (u, &log) in logs.iter().enumerate().skip(1) {
+            if u < blocks {
+                sum += log * z * z * power * (blocks - u.max(D)) as f64;
+            }
+            if u > D {
+                sum += log * z * power;
+            }
+            power *= 1.0 - z;
+            // the rest is negligible, and subnormal numbers are slow
+            if power < f64::MIN_POSITIVE {
+                break;
+            }
+        }
+        sum / nu
+    };
+    let n = (1 << B) as f64;
+    let expected = |p: f64| g(p) + (n - 1.0) * g((1.0 - p) / (n - 1.0));
+    let p = bisect(expected, mean, 1.0 / n, 1.0);
+    Some(-p.log2() / B as f64)
+}
+
+/// Numbers of repeated tuples of every length, found with a suffix array, for the t-tuple and
+/// LRS estimates.
+#[derive(Clone, Debug)]
+struct Repeats {
+    len: usize,
+    /// Number of occurrences of the most common tuple of every length, indexed by the length up
+    /// to that of the longest repeated tuple.
+    max_counts: Vec<u64>,
+    /// Number of pairs of equal tuples of every length, indexed likewise.
+    pairs: Vec<u64>,
+}
+
+impl Repeats {
+    fn new(samples: &[u8]) -> Self {
+        let n = samples.len();
+        let sa = suffix_array(samples);
+        let lcp = lcp_array(samples, &sa);
+        let longest = lcp.iter().copied().max().unwrap_or(0);
+
+        // every interval of the suffix array whose suffixes share a prefix of `h` elements but
+        // not all `h + 1`, nested in one sharing `parent`, is a tuple of every length in
+        // `parent + 1..=h` occurring `size` times
+        let mut best = vec![0u64; longest + 1];
+        let mut pairs_diff = vec![0i64; longest + 2];
+        let mut stack = vec![(0, 0)];
+        for i in 1..=n {
+            let h = lcp.get(i).copied().unwrap_or(0);
+            let mut left = i - 1;
+            while h < stack.last().unwrap().0 {
+                let (ell, l) = stack.pop().unwrap();
+                let parent = h.max(stack.last().unwrap().0);
+                let size = (i - l) as u64;
+                best[ell] = best[ell].max(size);
+                let pairs = (size * (size - 1) / 2) as i64;
+                pairs_diff[parent + 1] += pairs;
+                pairs_diff[ell + 1] -= pairs;
+                left = l;
+            }
+            if h > stack.last().unwrap().0 {
+                stack.push((h, left));
+            }
+        }
+
+        let mut max_counts = best;
+        for w in (1..longest).rev() {
+            max_counts[w] = max_counts[w].max(max_counts[w + 1]);
+        }
+        let pairs = pairs_diff[..=longest]
+            .iter()
+            .scan(0, |sum, &d| {
+                *sum += d;
+                Some(*sum as u64)
+            })
+            .collect();
+        Self {
+            len: n,
+            max_counts,
+            pairs,
+        }
+    }
+
+    /// Returns the longest length of tuples whose most common one occurs at least 35 times.
+    fn t(&self) -> usize {
+        (1..self.max_counts.len())
+            .take_while(|&w| self.max_counts[w] >= MIN_TUPLE_COUNT)
+            .last()
+            .unwrap_or(0)
+    }
+
+    /// t-Tuple estimate, section 6.3.5, or `None` if no value occurs 35 times.
+    fn t_tuple(&self) -> Option<f64> {
+        let t = self.t();
+        if t == 0 || self.len < 2 {
+            return None;
+        }
+        let p = (1..=t)
+            .map(|w| {
+                let p = self.max_counts[w] as f64 / (self.len - w + 1) as f64;
+                p.powf(1.0 / w as f64)
+            })
+            .fold(0.0, f64::max);
+        Some(-upper_bound(p, self.len).log2())
+    }
+
+    /// Longest repeated substring estimate, section 6.3.6, or `None` if no tuple longer than
+    /// those of the t-tuple estimate is repeated.
+    fn lrs(&self) -> Option<f64> {
+        let u = self.t() + 1;
+        let v = self.pairs.len() - 1;
+        if u > v || self.len < 2 {
+            return None;
+        }
+        let p = (u..=v)
+            .map(|w| {
+                let tuples = (self.len - w + 1) as f64;
+                let p = self.pairs[w] as f64 / (tuples * (tuples - 1.0) / 2.0);
+                p.powf(1.0 / w as f64)
+            })
+            .fold(0.0, f64::max);
+        Some(-upper_bound(p, self.len).log2())
+    }
+}
+
+/// t-Tuple estimate, section 6.3.5, or `None` if no value occurs 35 times.
+pub fn t_tuple(samples: &[u8]) -> Option<f64> {
+    Repeats::new(samples).t_tuple()
+}
+
+/// Longest repeated substring (LRS) estimate, section 6.3.6, or `None` if no tuple longer than
+/// those of the t-tuple estimate is repeated.
+pub fn lrs(samples: &[u8]) -> Option<f64> {
+    Repeats::new(samples).lrs()
+}
+
+/// Returns the suffix array of `s`, sorted by prefix doubling.
+fn suffix_array(s: &[u8]) -> Vec<usize> {
+    let n = s.len();
+    let mut rank: Vec<u64> = s.iter().map(|&c| c as u64).collect();
+    // ranks of the prefixes of length 2k, with the end before everything, and their suffixes
+    let mut keyed: Vec<(u64, usize)> = Vec::with_capacity(n);
+    let mut k = 1;
+    loop {
+        keyed.clear();
+        keyed.extend((0..n).map(|i| {
+            let next = if i + k < n { rank[i + k] + 1 } else { 0 };
+            (rank[i] << 32 | next, i)
+        }));
+        keyed.sort_unstable();
+        let mut r = 0;
+        for j in 0..n {
+            if j > 0 && keyed[j - 1].0 < keyed[j].0 {
+                r += 1;
+            }
+            rank[keyed[j].1] = r;
+        }
+        if n == 0 || r == n as u64 - 1 {
+            break;
+        }
+        k *= 2;
+    }
+    keyed.into_iter().map(|(_, i)| i).collect()
+}
+
+/// Returns the lengths of the longest common prefixes of adjacent suffixes of the suffix array,
+/// where the first is zero (Kasai et al. 2001).
+fn lcp_array(s: &[u8], sa: &[usize]) -> Vec<usize> {
+    let n = s.len();
+    let mut rank = vec![0; n];
+    for (i, &p) in sa.iter().enumerate() {
+        rank[p] = i;
+    }
+    let mut lcp = vec![0; n];
+    let mut h = 0;
+    for i in 0..n {
+        if rank[i] == 0 {
+            h = 0;
+            continue;
+        }
+        let j = sa[rank[i] - 1];
+        while i + h < n && j + h < n && s[i + h] == s[j + h] {
+            h += 1;
+        }
+        lcp[rank[i]] = h;
+        h = h.saturating_sub(1);
+    }
+    lcp
+}
+
+/// Outcomes of the predictions of a prediction estimate.
+#[derive(Clone, Debug, Default)]
+struct Predictions {
+    n: usize,
+    correct: usize,
+    run: usize,
+    longest_run: usize,
+}
+
+impl Predictions {
+    fn record(&mut self, correct: bool) {
+        self.n += 1;
+        if correct {
+            self.correct += 1;
+            self.run += 1;
+            self.longest_run = self.longest_run.max(self.run);
+        } else {
+            self.run = 0;
+        }
+    }
+
+    /// Returns the min-entropy of samples of `bits` bits from the global and local predictability,
+    /// or `None` for fewer than two predictions.
+    fn min_entropy(&self, bits: u32) -> Option<f64> {
+        if self.n < 2 {
+            return None;
+        }
+        let n = self.n as f64;
+        let global = match self.correct {
+            0 => 1.0 - 0.01f64.powf(1.0 / n),
+            c => upper_bound(c as f64 / n, self.n),
+        };
+
+        // probability of no run of r correct predictions in n, for a success probability p
+        let r = (self.longest_run + 1) as f64;
+        let no_run = |p: f64| -> f64 {
+            let q = 1.0 - p;
+            let mut x: f64 = 1.0;
+            for _ in 0..10 {
+                x = 1.0 + q * p.powf(r) * x.powf(r + 1.0);
+            }
+            (1.0 - p * x) / ((r + 1.0 - r * x) * q) / x.powf(n + 1.0)
+        };
+        let local = bisect(no_run, 0.99, 0.0, 1.0);
+
+        let p = global.max(local).max(1.0 / (1u32 << bits) as f64);
+        Some(-p.log2())
+    }
+}
+
+/// Scoreboard choosing among subpredictors, section 6.3.7 onwards.
+#[derive(Clone, Debug)]
+struct Scoreboard {
+    scores: Vec<u64>,
+    winner: usize,
+    predictions: Predictions,
+}
+
+impl Scoreboard {
+    fn new(len: usize) -> Self {
+        Self {
+            scores: vec![0; len],
+            winner: 0,
+            predictions: Predictions::default(),
+        }
+    }
+
+    /// Records the prediction of the winner for `actual`, and scores the subpredictions.
+    fn record(&mut self, subpredictions: &[Option<u8>], actual: u8) {
+        self.predictions
+            .record(subpredictions[self.winner] == Some(actual));
+        for (d, &prediction) in subpredictions.iter().enumerate() {
+            if prediction == Some(actual) {
+                self.scores[d] += 1;
+                if self.scores[d] >= self.scores[self.winner] {
+                    self.winner = d;
+                }
+            }
+        }
+    }
+}
+
+/// Sliding window of a MultiMCW subpredictor, tracking its most common value.
+#[derive(Clone, Debug)]
+struct Window {
+    len: usize,
+    counts: [u32; 256],
+    /// Index of the last occurrence of every value.
+    last: [usize; 256],
+    frequent: u8,
+}
+
+impl Window {
+    fn new(len: usize) -> Self {
+        Self {
+            len,
+            counts: [0; 256],
+            last: [0; 256],
+            frequent: 0,
+        }
+    }
+
+    /// Slides the window to end with sample `i`, among values below `k`.
+    fn push(&mut self, samples: &[u8], i: usize, k: usize) {
+        if i >= self.len {
+            let old = samples[i - self.len] as usize;
+            self.counts[old] -= 1;
+            if old == self.frequent as usize {
+                // the most common value, the most recent of ties
+                let best = (0..k)
+                    .filter(|&v| self.counts[v] > 0)
+                    .max_by_key(|&v| (self.counts[v], self.last[v]));
+                self.frequent = best.unwrap_or(0) as u8;
+            }
+        }
+        let new = samples[i];
+        self.counts[new as usize] += 1;
+        self.last[new as usize] = i;
+        if self.counts[new as usize] >= self.counts[self.frequent as usize] {
+            self.frequent = new;
+        }
+    }
+}
+
+/// Multi most common in window (MultiMCW) prediction estimate, section 6.3.7, or `None` for too
+/// few samples.
+pub fn multi_mcw(samples: &[u8], bits: u32) -> Option<f64> {
+    let k = 1 << bits;
+    let mut windows = MCW_WINDOWS.map(Window::new);
+    let mut scoreboard = Scoreboard::new(windows.len());
+    for (i, &s) in samples.iter().enumerate() {
+        if i >= MCW_WINDOWS[0] {
+            let predictions: [_; MCW_WINDOWS.len()] =
+                std::array::from_fn(|d| (i >= windows[d].len).then_some(windows[d].frequent));
+            scoreboard.record(&predictions, s);
+        }
+        for window in &mut windows {
+            window.push(samples, i, k);
+        }
+    }
+    scoreboard.predictions.min_entropy(bits)
+}
+
+/// Lag prediction estimate, section 6.3.8, or `None` for fewer than three samples.
+pub fn lag(samples: &[u8], bits: u32) -> Option<f64> {
+    let mut scoreboard = Scoreboard::new(LAG_DEPTH);
+    let mut predictions = [None; LAG_DEPTH];
+    for (i, &s) in samples.iter().enumerate().skip(1) {
+        // subpredictor d predicts the sample d + 1 back
+        predictions.copy_within(..LAG_DEPTH - 1, 1);
+        predictions[0] = Some(samples[i - 1]);
+        scoreboard.record(&predictions, s);
+    }
+    scoreboard.predictions.min_entropy(bits)
+}
+
+/// Counts of the values following a context, tracking the most common one, the largest of ties.
+#[derive(Clone, Debug, Default)]
+struct Successors {
+    counts: Vec<(u8, u32)>,
+    best: u8,
+    best_count: u32,
+}
+
+impl Successors {
+    fn contains(&self, value: u8) -> bool {
+        self.counts.iter().any(|&(v, _)| v == value)
+    }
+
+    fn increment(&mut self, value: u8) {
+        let count = match self.counts.iter_mut().find(|(v, _)| *v == value) {
+            Some((_, count)) => {
+                *count += 1;
+                *count
+            }
+            None => {
+                self.counts.push((value, 1));
+                1
+            }
+        };
+        if (count, value) > (self.best_count, self.best) {
+            (self.best, self.best_count) = (value, count);
+        }
+    }
+}
+
+/// Hasher of the contexts of the MultiMMC and LZ78Y predictors, much faster than the default one
+/// and good enough for keys that an adversary does not choose (FxHash of rustc).
+#[derive(Clone, Debug, Default)]
+struct ContextHasher(u64);
+
+impl Hasher for ContextHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.write_u64(b as u64);
+        }
+    }
+
+    fn write_u64(&mut self, word: u64) {
+        self.0 = (self.0.rotate_left(5) ^ word).wrapping_mul(0x51_7c_c1_b7_27_22_0a_95);
+    }
+
+    fn write_u128(&mut self, word: u128) {
+        self.write_u64(word as u64);
+        self.write_u64((word >> 64) as u64);
+    }
+
+    fn write_usize(&mut self, word: usize) {
+        self.write_u64(word as u64);
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+type ContextMap<K> = HashMap<K, Successors, BuildHasherDefault<ContextHasher>>;
+
+/// Returns the context of the last `len` samples of `history`, which holds the last 16.
+fn context(history: u128, len: usize) -> u128 {
+    match len {
+        16 => history,
+        _ => history & ((1 << (8 * len)) - 1),
+    }
+}
+
+/// Multi Markov model with counting (MultiMMC) prediction estimate, section 6.3.9, or `None` for
+/// fewer than four samples.
+///
+/// Ties of the most common value following a context are broken in favor of the largest value.
+pub fn multi_mmc(samples: &[u8], bits: u32) -> Option<f64> {
+    let mut models: Vec<ContextMap<u128>> = vec![ContextMap::default(); MMC_DEPTH];
+    let mut entries = [0; MMC_DEPTH];
+    let mut scoreboard = Scoreboard::new(MMC_DEPTH);
+    // the last 16 samples before the previous one, and before the current one
+    let (mut previous, mut history) = (0u128, 0u128);
+    for (i, &s) in samples.iter().enumerate() {
+        if i >= 2 {
+            let last = samples[i - 1];
+            for (d, model) in models.iter_mut().enumerate().take(i - 1) {
+                let successors = model.entry(context(previous, d + 1));
+                match successors {
+                    Entry::Occupied(mut e) if e.get().contains(last) => e.get_mut().increment(last),
+                    _ if entries[d] >= MMC_MAX_ENTRIES => {}
+                    e => {
+                        e.or_default().increment(last);
+                        entries[d] += 1;
+                    }
+                }
+            }
+
+            let mut predictions = [None; MMC_DEPTH];
+            for (d, model) in models.iter().enumerate().take(i) {
+                predictions[d] = model.get(&context(history, d + 1)).map(|e| e.best);
+            }
+            scoreboard.record(&predictions, s);
+        }
+        previous = history;
+        history = history << 8 | s as u128;
+    }
+    scoreboard.predictions.min_entropy(bits)
+}
+
+/// LZ78Y prediction estimate, section 6.3.10, or `None` for fewer than 19 samples.
+///
+/// Ties of the most common value following a context are broken in favor of the largest value.
+pub fn lz78y(samples: &[u8], bits: u32) -> Option<f64> {
+    let mut dictionary = ContextMap::<(usize, u128)>::default();
+    let mut predictions = Predictions::default();
+    let (mut previous, mut history) = (0u128, 0u128);
+    for (i, &s) in samples.iter().enumerate() {
+        if i > LZ78Y_DEPTH {
+            let last = samples[i - 1];
+            for j in (1..=LZ78Y_DEPTH).rev() {
+                let full = dictionary.len() >= LZ78Y_MAX_DICTIONARY;
+                match dictionary.entry((j, context(previous, j))) {
+                    Entry::Occupied(mut e) => e.get_mut().increment(last),
+                    Entry::Vacant(e) if !full => e.insert(Successors::default()).increment(last),
+                    Entry::Vacant(_) => {}
+                }
+            }
+
+            // the most common value after the longest context, unless a shorter one beats it
+            let (mut prediction, mut max_count) = (None, 0);
+            for j in (1..=LZ78Y_DEPTH).rev() {
+                if let Some(e) = dictionary.get(&(j, context(history, j))) {
+                    if e.best_count > max_count {
+                        (prediction, max_count) = (Some(e.best), e.best_count);
+                    }
+                }
+            }
+            predictions.record(prediction == Some(s));
+        }
+        previous = history;
+        history = history << 8 | s as u128;
+    }
+    predictions.min_entropy(bits)
+}
+
+/// Result of a permutation test: the numbers of permutations whose statistic was greater than and
+/// equal to that of the samples.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PermutationTest {
+    pub name: String,
+    pub greater: u32,
+    pub equal: u32,
+    pub permutations: u32,
+}
+
+impl PermutationTest {
+    /// Returns `false` if the statistic of the samples ranks among the five highest or lowest of
+    /// the permutations, which rejects the IID assumption.
+    pub fn passed(&self) -> bool {
+        self.greater + self.equal > 5 && self.greater < self.permutations.saturating_sub(5)
+    }
+}
+
+/// Names of the statistics of the permutation tests, in the order of section 5.1. The compression
+/// statistic is computed only with the `bzip2` feature.
+pub const STATISTICS: &[&str] = &[
+    "excursion",
+    "number of directional runs",
+    "length of directional runs",
+    "number of increases and decreases",
+    "number of runs based on the median",
+    "length of runs based on the median",
+    "average collision",
+    "maximum collision",
+    "periodicity (lag 1)",
+    "periodicity (lag 2)",
+    "periodicity (lag 8)",
+    "periodicity (lag 16)",
+    "periodicity (lag 32)",
+    "covariance (lag 1)",
+    "covariance (lag 2)",
+    "covariance (lag 8)",
+    "covariance (lag 16)",
+    "covariance (lag 32)",
+    #[cfg(feature = "bzip2")]
+    "compression",
+];
+
+/// Lags of the periodicity and covariance statistics.
+const LAGS: [usize; 5] = [1, 2, 8, 16, 32];
+
+/// Permutation tests of section 5.1, comparing the statistics of `samples` of `bits` bits with
+/// those of `permutations` random shuffles drawn from `stream`.
+///
+/// The compression statistic, which needs bzip2, is left out unless the `bzip2` feature is enabled;
+/// it compresses the samples of every permutation, which makes it the slowest by far. As in section
+/// 5.1, binary samples are converted to the number of ones of every eight bits for the directional runs,
+/// increases and decreases, periodicity and covariance statistics, and to bytes for the collision
+/// statistics.
+pub fn permutation_tests(
+    samples: &[u8],
+    bits: u32,
+    permutations: u32,
+    stream: &mut RandomStream,
+) -> io::Result<Vec<PermutationTest>> {
+    let binary = bits == 1;
+    let mean = samples.iter().map(|&s| s as f64).sum::<f64>() / samples.len().max(1) as f64;
+    let median = if binary {
+        0.5
+    } else {
+        let mut sorted = samples.to_vec();
+        sorted.sort_unstable();
+        match sorted.len() {
+            0 => 0.0,
+            n if n % 2 == 0 => (sorted[n / 2 - 1] as f64 + sorted[n / 2] as f64) / 2.0,
+            n => sorted[n / 2] as f64,
+        }
+    };
+
+    let original = statistics(samples, binary, mean, median);
+    let mut greater = [0; STATISTICS.len()];
+    let mut equal = [0; STATISTICS.len()];
+    let mut shuffled = samples.to_vec();
+    for _ in 0..permutations {
+        for i in (1..shuffled.len()).rev() {
+            let j = lemire_u64(i as u64 + 1, || stream.next_u64())? as usize;
+            shuffled.swap(i, j);
+        }
+        let t = statistics(&shuffled, binary, mean, median);
+        for i in 0..STATISTICS.len() {
+            if t[i] > original[i] {
+                greater[i] += 1;
+            } else if t[i] == original[i] {
+                equal[i] += 1;
+            }
+        }
+    }
+    Ok(STATISTICS
+        .iter()
+        .enumerate()
+        .map(|(i, name)| PermutationTest {
+            name: (*name).to_owned(),
+            greater: greater[i],
+            equal: equal[i],
+            permutations,
+        })
+        .collect())
+}
+
+/// Returns the statistics of the permutation tests of `s`, whose mean and median are given.
+fn statistics(s: &[u8], binary: bool, mean: f64, median: f64) -> [f64; STATISTICS.len()] {
+    let mut t = [0.0; STATISTICS.len()];
+
+    let (mut sum, mut excursion) = (0.0, 0.0f64);
+    for (i, &v) in s.iter().enumerate() {
+        sum += v as f64;
+        excursion = excursion.max((sum - (i + 1) as f64 * mean).abs());
+    }
+    t[0] = excursion;
+
+    // conversion I of binary samples: the number of ones of every eight bits
+    let weights: Vec<u8>;
+    let converted = match binary {
+        true => {
+            weights = s.chunks_exact(8).map(|c| c.iter().sum()).collect();
+            &weights
+        }
+        false => s,
+    };
+    let (n_runs, longest, increases) = runs(converted.windows(2).map(|w| w[0] <= w[1]));
+    let decreases = converted.len().saturating_sub(1) as u64 - increases;
+    t[1] = n_runs as f64;
+    t[2] = longest as f64;
+    t[3] = increases.max(decreases) as f64;
+
+    let (n_runs, longest, _) = runs(s.iter().map(|&v| v as f64 >= median));
+    t[4] = n_runs as f64;
+    t[5] = longest as f64;
+
+    // conversion II of binary samples: bytes of eight bits
+    let bytes: Vec<u8>;
+    let collided = match binary {
+        true => {
+            bytes = s
+                .chunks_exact(8)
+                .map(|c| c.iter().fold(0, |acc, &b| acc << 1 | b))
+                .collect();
+            &bytes
+        }
+        false => s,
+    };
+    let (average, maximum) = collisions(collided);
+    t[6] = average;
+    t[7] = maximum;
+
+    for (i, lag) in LAGS.into_iter().enumerate() {
+        let pairs = converted.iter().zip(converted.iter().skip(lag));
+        t[8 + i] = pairs.clone().filter(|(a, b)| a == b).count() as f64;
+        t[13 + i] = pairs.map(|(&a, &b)| a as u64 * b as u64).sum::<u64>() as f64;
+    }
+
+    #[cfg(feature = "bzip2")]
+    {
+        t[18] = compressed_len(s) as f64;
+    }
+    t
+}
+
+/// Returns the length of the samples compressed with bzip2, written in decimal and separated by
+/// spaces like the reference implementation does.
+#[cfg(feature = "bzip2")]
+fn compressed_len(s: &[u8]) -> usize {
+    use std::io::Write as _;
+
+    let mut text = Vec::with_capacity(s.len() * 4);
+    for (i, v) in s.iter().enumerate() {
+        if i > 0 {
+            text.push(b' ');
+        }
+        write!(text, "{}", v).expect("writing to a Vec cannot fail");
+    }
+    let mut encoder = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::new(5));
+    encoder
+        .write_all(&text)
+        .and_then(|_| encoder.finish())
+        .expect("writing to a Vec cannot fail")
+        .len()
+}
+
+/// Returns the number of runs, the longest run and the number of `true` values.
+fn runs(values: impl Iterator<Item = bool>) -> (u64, u64, u64) {
+    let (mut n_runs, mut run, mut longest, mut trues) = (0, 0, 0, 0);
+    let mut last = None;
+    for v in values {
+        if last == Some(v) {
+            run += 1;
+        } else {
+            n_runs += 1;
+            run = 1;
+        }
+        longest = longest.max(run);
+        trues += v as u64;
+        last = Some(v);
+    }
+    (n_runs, longest, trues)
+}
+
+/// Returns the average and maximum numbers of samples in the consecutive segments that end with
+/// the first repeated value.
+fn collisions(s: &[u8]) -> (f64, f64) {
+    // segment in which every value was last seen
+    let mut seen = [usize::MAX; 256];
+    let (mut segment, mut start) = (0, 0);
+    let (mut sum, mut max) = (0, 0);
+    for (i, &v) in s.iter().enumerate() {
+        if seen[v as usize] == segment {
+            let len = i - start + 1;
+            sum += len;
+            max = max.max(len);
+            segment += 1;
+            start = i + 1;
+        } else {
+            seen[v as usize] = segment;
+        }
+    }
+    match segment {
+        0 => (0.0, 0.0),
+        n => (sum as f64 / n as f64, max as f64),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{
+        collision, compression, lag, lz78y, markov, most_common_value, multi_mcw, multi_mmc,
+        permutation_tests, NonIid, Repeats, STATISTICS,
+    };
+    use crate::Builder;
+
+    fn random_bytes(seed: &[u8], n: usize) -> Vec<u8> {
+        let mut bytes = vec![0; n];
+        Builder::new()
+            .seed(seed)
+            .build()
+            .fill_bytes(&mut bytes)
+            .unwrap();
+        bytes
+    }
+
+    fn assert_close(actual: Option<f64>, expected: f64, what: &str) {
+        let actual = actual.unwrap_or_else(|| panic!("{}: no estimate", what));
+        assert!(
+            (actual - expected).abs() < 1e-6,
+            "{}: {}, expected {}",
+            what,
+            actual,
+            expected
+        );
+    }
+
+    /// Example of section 6.3.1 of SP 800-90B.
+    #[test]
+    fn nist_example() {
+        let samples = [0, 1, 1, 2, 0, 1, 2, 2, 0, 1, 0, 1, 1, 0, 2, 2, 1, 0, 2, 1];
+        // p = 0.4 + 2.576 * sqrt(0.4 * 0.6 / 19) = 0.6895
+        let h = most_common_value(&samples).unwrap();
+        assert!((h - 0.5363).abs() < 1e-4, "most common value: {}", h);
+    }
+
+    #[test]
+    fn simple_estimates() {
+        // 3 of 4 samples are 0: p = 0.75 + 2.576 * sqrt(0.75 * 0.25 / 3)
+        let p: f64 = 0.75 + 2.576 * (0.75f64 * 0.25 / 3.0).sqrt();
+        assert_close(
+            most_common_value(&[0, 1, 0, 0]),
+            -p.min(1.0).log2(),
+            "most common value",
+        );
+
+        // the most likely sequence alternates with certainty after the first bit
+        let alternating: Vec<u8> = (0..1000).map(|i| i as u8 & 1).collect();
+        assert_close(markov(&alternating), 1.0 / 128.0, "markov");
+        assert_close(markov(&[1; 1000]), 0.0, "markov");
+
+        // collisions after 2 bits only
+        assert_close(collision(&[1; 1000]), 0.0, "collision");
+        // collisions after 3 bits only: 2 + 2pq = 3 has no solution, so p = 1/2
+        let triples: Vec<u8> = [0, 1, 1].repeat(1000);
+        assert_close(collision(&triples), 1.0, "collision");
+        assert_eq!(collision(&[0, 1]), None);
+
+        assert_eq!(compression(&[0; 6000]), None);
+        assert_close(compression(&[0; 60_000]), 0.0, "compression");
+    }
+
+    /// The counts of the suffix array match those of a brute-force count.
+    #[test]
+    fn repeated_tuples() {
+        for (seed, n) in [(&b"a"[..], 2000), (b"b", 300), (b"c", 1)] {
+            let samples: Vec<u8> = random_bytes(seed, n).iter().map(|b| b & 3).collect();
+            let repeats = Repeats::new(&samples);
+            for w in 1..repeats.max_counts.len() + 2 {
+                let mut counts = HashMap::new();
+                for tuple in samples.windows(w) {
+                    *counts.entry(tuple).or_insert(0u64) += 1;
+                }
+                let max = counts.values().copied().max().unwrap_or(0);
+                let pairs: u64 = counts.values().map(|c| c * (c - 1) / 2).sum();
+                if w < repeats.max_counts.len() {
+                    assert_eq!(repeats.max_counts[w], max, "{} samples, length {}", n, w);
+                    assert_eq!(repeats.pairs[w], pairs, "{} samples, length {}", n, w);
+                } else {
+                    assert!(max <= 1, "{} samples, length {}", n, w);
+                }
+            }
+        }
+        let repeats = Repeats::new(&[7; 100]);
+        assert_eq!(repeats.max_counts.len(), 100);
+        assert_eq!(repeats.max_counts[65], 36);
+        assert_eq!(repeats.max_counts[66], 35);
+        assert_eq!(repeats.t(), 66);
+    }
+
+    #[test]
+    fn predictors_find_patterns() {
+        let cycle: Vec<u8> = (0..20_000).map(|i| (i % 10) as u8).collect();
+        let constant = vec![3; 20_000];
+        for (name, estimate) in [
+            ("MultiMCW", multi_mcw as fn(&[u8], u32) -> Option<f64>),
+            ("lag", lag),
+            ("MultiMMC", multi_mmc),
+            ("LZ78Y", lz78y),
+        ] {
+            let h = estimate(&constant, 4).unwrap();
+            assert!(h < 0.01, "{} of constant samples: {}", name, h);
+            if name != "MultiMCW" {
+                let h = estimate(&cycle, 4).unwrap();
+                assert!(h < 0.01, "{} of cyclic samples: {}", name, h);
+            }
+            assert_eq!(estimate(&[1, 2], 4), None, "{}", name);
+        }
+    }
+
+    #[test]
+    fn random_samples_have_full_entropy() {
+        let bytes = random_bytes(b"sp800-90b", 50_000);
+        let nibbles: Vec<u8> = bytes.iter().map(|b| b & 0xf).collect();
+        let assessment = NonIid::new(4).assess(&nibbles);
+        assert_eq!(assessment.estimates.len(), 7);
+        assert_eq!(assessment.bitstring.len(), 10);
+        assert_eq!(assessment.bitstring_len, 200_000);
+        for e in &assessment.estimates {
+            let h = e.min_entropy.unwrap();
+            // the fixed seed makes the estimates deterministic
+            assert!((3.6..=4.0).contains(&h), "{}: {}", e.name, h);
+        }
+        for e in &assessment.bitstring {
+            let h = e.min_entropy.unwrap();
+            assert!((0.8..=1.0).contains(&h), "bitstring {}: {}", e.name, h);
+        }
+        let h = assessment.min_entropy().unwrap();
+        assert!(h <= assessment.original().unwrap());
+        assert!(h <= 4.0 * assessment.bitstring().unwrap());
+
+        let truncated = NonIid::new(4)
+            .truncate_bitstring(true)
+            .assess(&nibbles[..1000]);
+        assert_eq!(truncated.bitstring_len, 4000);
+    }
+
+    #[test]
+    fn permutations() {
+        let mut stream = Builder::new().seed(b"permutations").build();
+        let samples: Vec<u8> = random_bytes(b"iid", 2000);
+        let tests = permutation_tests(&samples, 8, 200, &mut stream).unwrap();
+        for test in &tests {
+            // IID samples are rarely more extreme than all permutations
+            assert!(
+                test.greater + test.equal > 0 && test.greater < 200,
+                "{:?}",
+                test
+            );
+        }
+
+        let bits: Vec<u8> = samples.iter().map(|b| b & 1).collect();
+        let tests = permutation_tests(&bits, 1, 200, &mut stream).unwrap();
+        assert_eq!(tests.len(), STATISTICS.len());
+        for test in &tests {
+            assert!(
+                test.greater + test.equal > 0 && test.greater < 200,
+                "{:?}",
+                test
+            );
+        }
+
+        let mut sorted = samples.clone();
+        sorted.sort_unstable();
+        let tests = permutation_tests(&sorted, 8, 100, &mut stream).unwrap();
+        assert!(tests.iter().any(|t| !t.passed()));
+        assert_eq!(tests[0].greater, 0);
+        assert_eq!(tests[0].permutations, 100);
+        #[cfg(feature = "bzip2")]
+        {
+            // sorted samples compress better than any shuffle
+            let compression = tests.last().unwrap();
+            assert_eq!(compression.name, "compression");
+            assert_eq!(compression.greater, 100);
+        }
+    }
+}
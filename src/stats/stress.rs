@@ -0,0 +1,646 @@
+//! Streaming battery of tests in the style of PractRand for long runs of a generator.
+//!
+//! [`Battery`] is an [`io::Write`] sink that reads the bytes written to it as little-endian 64-bit
+//! words and accumulates the statistics of all tests in constant memory, so that
+//! [`Battery::results`] can be called at growing lengths to test everything written so far.
+
+use std::{fmt, io};
+
+use super::{igamc, TestResult};
+use crate::dist::ln_gamma;
+
+/// Birthdays per sample of the birthday spacings tests, drawn from `2^32` days, for an expected
+/// number of pairs of equal spacings of about `m^3 / (4 * 2^32) = 4` per sample.
+const BIRTHDAYS: usize = 4096;
+
+/// Largest gap counted separately by the gap tests.
+const MAX_GAP: usize = 8192;
+
+/// Logarithms of the block lengths in bits of the BCFN tests.
+const BCFN_LEVELS: [u32; 8] = [6, 8, 10, 12, 14, 16, 18, 20];
+
+/// Number of consecutive blocks of the tuples of the BCFN tests.
+const BCFN_TUPLE_LEN: usize = 4;
+
+/// Number of consecutive bytes of the tuples of the DC6 test.
+const DC6_TUPLE_LEN: usize = 4;
+
+/// Minimum expected count of the cells of the chi-square tests.
+const MIN_EXPECTED: f64 = 5.0;
+
+/// Evaluation of an anomalous p-value, by the distance of the p-value from 0 or 1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Evaluation {
+    /// Below 1e-3.
+    Unusual,
+    /// Below 1e-4.
+    MildlySuspicious,
+    /// Below 1e-6.
+    Suspicious,
+    /// Below 1e-8.
+    VerySuspicious,
+    /// Below 1e-10.
+    HighlySuspicious,
+    /// Below 1e-12.
+    Fail,
+}
+
+impl Evaluation {
+    /// Returns the evaluation of `p`, or `None` if it is not anomalous.
+    pub fn of(p: f64) -> Option<Self> {
+        let distance = p.min(1.0 - p);
+        [
+            (1e-12, Self::Fail),
+            (1e-10, Self::HighlySuspicious),
+            (1e-8, Self::VerySuspicious),
+            (1e-6, Self::Suspicious),
+            (1e-4, Self::MildlySuspicious),
+            (1e-3, Self::Unusual),
+        ]
+        .into_iter()
+        .find(|&(limit, _)| distance < limit)
+        .map(|(_, evaluation)| evaluation)
+    }
+}
+
+impl fmt::Display for Evaluation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Unusual => "unusual",
+            Self::MildlySuspicious => "mildly suspicious",
+            Self::Suspicious => "suspicious",
+            Self::VerySuspicious => "very suspicious",
+            Self::HighlySuspicious => "VERY SUSPICIOUS",
+            Self::Fail => "FAIL",
+        })
+    }
+}
+
+/// Returns the p-value of a chi-square test of `counts` against the probabilities of the cells,
+/// or `None` if the expected count of a cell is below [`MIN_EXPECTED`].
+fn chi_square(counts: &[u64], probs: impl Iterator<Item = f64>) -> Option<f64> {
+    let n = counts.iter().sum::<u64>() as f64;
+    let mut chi2 = 0.0;
+    for (&count, p) in counts.iter().zip(probs) {
+        let expected = n * p;
+        if expected < MIN_EXPECTED {
+            return None;
+        }
+        chi2 += (count as f64 - expected).powi(2) / expected;
+    }
+    Some(igamc((counts.len() - 1) as f64 / 2.0, chi2 / 2.0))
+}
+
+/// Floating-point frequency test: the distribution of the exponents of the words read as
+/// fractions in `[0, 1)`, and of the eight significand bits that follow the leading one.
+#[derive(Clone, Debug)]
+struct Fpf {
+    /// Counts of the numbers of leading zeros.
+    exponents: [u64; 65],
+    /// Counts of the significand bits for every number of leading zeros with eight bits after
+    /// the leading one.
+    significands: Vec<[u64; 256]>,
+}
+
+impl Fpf {
+    fn new() -> Self {
+        Self {
+            exponents: [0; 65],
+            significands: vec![[0; 256]; 56],
+        }
+    }
+
+    fn push(&mut self, word: u64) {
+        let e = word.leading_zeros() as usize;
+        self.exponents[e] += 1;
+        if let Some(counts) = self.significands.get_mut(e) {
+            counts[(word << (e + 1) >> 56) as usize] += 1;
+        }
+    }
+
+    /// Returns the p-values of the exponents, lumping those too rare to count separately, and of
+    /// the significands of all exponents with enough words.
+    fn results(&self) -> [TestResult; 2] {
+        let n = self.exponents.iter().sum::<u64>();
+        // exponent e has probability 2^-(e + 1), and the tail from e on 2^-e
+        let classes = (0..self.significands.len())
+            .take_while(|&e| n as f64 / 2f64.powi(e as i32 + 1) >= MIN_EXPECTED)
+            .count();
+        let exponents = (classes > 0).then(|| {
+            let mut counts = self.exponents[..classes].to_vec();
+            counts.push(self.exponents[classes..].iter().sum());
+            let probs = (0..classes).map(|e| 2f64.powi(-(e as i32) - 1));
+            chi_square(&counts, probs.chain([2f64.powi(-(classes as i32))]))
+        });
+
+        // chi-square statistics of independent tests add up
+        let (mut chi2, mut dof) = (0.0, 0);
+        for counts in &self.significands {
+            let expected = counts.iter().sum::<u64>() as f64 / 256.0;
+            if expected >= MIN_EXPECTED {
+                chi2 += counts
+                    .iter()
+                    .map(|&c| (c as f64 - expected).powi(2) / expected)
+                    .sum::<f64>();
+                dof += 255;
+            }
+        }
+        [
+            TestResult::new("FPF exponents", exponents.flatten()),
+            TestResult::new(
+                "FPF significands",
+                (dof > 0).then(|| igamc(dof as f64 / 2.0, chi2 / 2.0)),
+            ),
+        ]
+    }
+}
+
+/// Birthday spacings test of 32 bits of the words (Marsaglia): the number of pairs of equal
+/// spacings between the sorted birthdays of a sample, around a circle of `2^32` days, is about
+/// Poisson distributed.
+///
+/// The mean is `m * (m - 1)^2 / (4 * n)`, the number of pairs of spacings times the probability
+/// that two spacings are equal, rather than the asymptotic `m^3 / (4 * n)`, whose relative error
+/// of `2 / m` would show up as a failure after some terabytes.
+#[derive(Clone, Debug)]
+struct BirthdaySpacings {
+    name: &'static str,
+    shift: u32,
+    birthdays: Vec<u32>,
+    samples: u64,
+    collisions: u64,
+}
+
+impl BirthdaySpacings {
+    fn new(name: &'static str, shift: u32) -> Self {
+        Self {
+            name,
+            shift,
+            birthdays: Vec::with_capacity(BIRTHDAYS),
+            samples: 0,
+            collisions: 0,
+        }
+    }
+
+    fn push(&mut self, word: u64) {
+        self.birthdays.push((word >> self.shift) as u32);
+        if self.birthdays.len() < BIRTHDAYS {
+            return;
+        }
+        let days = &mut self.birthdays;
+        days.sort_unstable();
+        let last = days[BIRTHDAYS - 1];
+        for i in (1..BIRTHDAYS).rev() {
+            days[i] -= days[i - 1];
+        }
+        days[0] = days[0].wrapping_sub(last);
+        days.sort_unstable();
+        for run in days.chunk_by(|a, b| a == b) {
+            self.collisions += (run.len() * (run.len() - 1) / 2) as u64;
+        }
+        self.samples += 1;
+        self.birthdays.clear();
+    }
+
+    /// Returns the probability of at least as many pairs of equal spacings.
+    fn result(&self) -> TestResult {
+        let m = BIRTHDAYS as f64;
+        let lambda = self.samples as f64 * m * (m - 1.0).powi(2) / 2f64.powi(34);
+        let p = match self.collisions {
+            0 => 1.0,
+            c => 1.0 - igamc(c as f64, lambda),
+        };
+        TestResult::new(self.name, (self.samples > 0).then_some(p))
+    }
+}
+
+/// Gap test of eight bits of the words (Knuth): the numbers of words between those whose bits
+/// are zero are geometrically distributed.
+#[derive(Clone, Debug)]
+struct Gap {
+    name: &'static str,
+    shift: u32,
+    /// Length of the current gap, or `None` before the first zero.
+    gap: Option<usize>,
+    /// Counts of the gaps up to [`MAX_GAP`], and of the longer ones.
+    counts: Vec<u64>,
+}
+
+impl Gap {
+    fn new(name: &'static str, shift: u32) -> Self {
+        Self {
+            name,
+            shift,
+            gap: None,
+            counts: vec![0; MAX_GAP + 2],
+        }
+    }
+
+    fn push(&mut self, word: u64) {
+        if word >> self.shift & 0xff == 0 {
+            if let Some(gap) = self.gap {
+                self.counts[gap.min(MAX_GAP + 1)] += 1;
+            }
+            self.gap = Some(0);
+        } else if let Some(gap) = self.gap.as_mut() {
+            *gap += 1;
+        }
+    }
+
+    fn result(&self) -> TestResult {
+        const P: f64 = 1.0 / 256.0;
+
+        let n = self.counts.iter().sum::<u64>() as f64;
+        // gaps of length g have probability p * q^g, and the tail from g on q^g
+        let classes = (0..=MAX_GAP)
+            .take_while(|&g| n * P * (1.0 - P).powi(g as i32) >= MIN_EXPECTED)
+            .count();
+        let p_value = (classes > 0).then(|| {
+            let mut counts = self.counts[..classes].to_vec();
+            counts.push(self.counts[classes..].iter().sum());
+            let probs = (0..classes).map(|g| P * (1.0 - P).powi(g as i32));
+            chi_square(&counts, probs.chain([(1.0 - P).powi(classes as i32)]))
+        });
+        TestResult::new(self.name, p_value.flatten())
+    }
+}
+
+/// Bit count test of one block length: the Hamming weights of blocks are classified as low,
+/// middle or high, with probabilities of about 1/4, 1/2 and 1/4, and the classes of tuples of
+/// consecutive blocks are counted.
+#[derive(Clone, Debug)]
+struct Bcfn {
+    name: String,
+    /// Length of the blocks in words.
+    words: u64,
+    /// Highest weight of the low class and lowest weight of the high class.
+    low: u32,
+    high: u32,
+    probs: [f64; 3],
+    pos: u64,
+    weight: u32,
+    tuple: usize,
+    tuple_pos: usize,
+    counts: Vec<u64>,
+}
+
+impl Bcfn {
+    fn new(level: u32) -> Self {
+        let n = 1u64 << level;
+        let half = (n / 2) as u32;
+        let sigma = (n as f64).sqrt() / 2.0;
+        let d = (0.6745 * sigma + 0.5).round().max(1.0) as u32;
+        let low = half - d;
+
+        // binomial probability of at most `low` ones, summed over the terms that matter
+        let ln_n = ln_gamma(n as f64 + 1.0) - n as f64 * std::f64::consts::LN_2;
+        let from = (low as f64 - 40.0 * sigma).max(0.0) as u32;
+        let p_low: f64 = (from..=low)
+            .map(|w| {
+                let w = w as f64;
+                (ln_n - ln_gamma(w + 1.0) - ln_gamma(n as f64 - w + 1.0)).exp()
+            })
+            .sum();
+
+        Self {
+            name: format!("BCFN 2^{} bits", level),
+            words: n / 64,
+            low,
+            high: half + d,
+            probs: [p_low, 1.0 - 2.0 * p_low, p_low],
+            pos: 0,
+            weight: 0,
+            tuple: 0,
+            tuple_pos: 0,
+            counts: vec![0; 3usize.pow(BCFN_TUPLE_LEN as u32)],
+        }
+    }
+
+    fn push(&mut self, word: u64) {
+        self.weight += word.count_ones();
+        self.pos += 1;
+        if self.pos < self.words {
+            return;
+        }
+        let class = match self.weight {
+            w if w <= self.low => 0,
+            w if w >= self.high => 2,
+            _ => 1,
+        };
+        (self.pos, self.weight) = (0, 0);
+        self.tuple = self.tuple * 3 + class;
+        self.tuple_pos += 1;
+        if self.tuple_pos == BCFN_TUPLE_LEN {
+            self.counts[self.tuple] += 1;
+            (self.tuple, self.tuple_pos) = (0, 0);
+        }
+    }
+
+    fn result(&self) -> TestResult {
+        let probs = (0..self.counts.len()).map(|mut tuple| {
+            (0..BCFN_TUPLE_LEN)
+                .map(|_| {
+                    let p = self.probs[tuple % 3];
+                    tuple /= 3;
+                    p
+                })
+                .product()
+        });
+        TestResult::new(&self.name, chi_square(&self.counts, probs))
+    }
+}
+
+/// DC6 test: the Hamming weights of bytes are classified into five classes, and the classes of
+/// tuples of consecutive bytes are counted.
+#[derive(Clone, Debug)]
+struct Dc6 {
+    /// Class of every byte value: at most 2 ones, 3, 4, 5, or at least 6.
+    classes: [u8; 256],
+    counts: Vec<u64>,
+}
+
+impl Dc6 {
+    const PROBS: [f64; 5] = [
+        37.0 / 256.0,
+        56.0 / 256.0,
+        70.0 / 256.0,
+        56.0 / 256.0,
+        37.0 / 256.0,
+    ];
+
+    fn new() -> Self {
+        let mut classes = [0; 256];
+        for (byte, class) in classes.iter_mut().enumerate() {
+            *class = (byte.count_ones().clamp(2, 6) - 2) as u8;
+        }
+        Self {
+            classes,
+            counts: vec![0; 5usize.pow(DC6_TUPLE_LEN as u32)],
+        }
+    }
+
+    fn push(&mut self, word: u64) {
+        for tuple in word.to_le_bytes().chunks_exact(DC6_TUPLE_LEN) {
+            let cell = tuple
+                .iter()
+                .fold(0, |acc, &b| acc * 5 + self.classes[b as usize] as usize);
+            self.counts[cell] += 1;
+        }
+    }
+
+    fn result(&self) -> TestResult {
+        let probs = (0..self.counts.len()).map(|mut tuple| {
+            (0..DC6_TUPLE_LEN)
+                .map(|_| {
+                    let p = Self::PROBS[tuple % 5];
+                    tuple /= 5;
+                    p
+                })
+                .product()
+        });
+        TestResult::new("DC6 4x1 bytes", chi_square(&self.counts, probs))
+    }
+}
+
+/// Sink that runs the floating-point frequency (FPF), birthday spacings, gap, BCFN and DC6 tests
+/// on the words written to it.
+///
+/// Every call of [`Battery::results`] returns the p-values of all tests over everything written
+/// so far; tests are skipped until there are enough words for them, so the BCFN tests of longer
+/// blocks join in as the length grows.
+#[derive(Clone, Debug)]
+pub struct Battery {
+    words: u64,
+    /// Bytes of an incomplete word.
+    partial: [u8; 8],
+    partial_len: usize,
+    fpf: Fpf,
+    birthday_spacings: [BirthdaySpacings; 2],
+    gaps: [Gap; 2],
+    bcfn: Vec<Bcfn>,
+    dc6: Dc6,
+}
+
+impl Default for Battery {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Battery {
+    /// Creates a battery that has seen no words.
+    pub fn new() -> Self {
+        Self {
+            words: 0,
+            partial: [0; 8],
+            partial_len: 0,
+            fpf: Fpf::new(),
+            birthday_spacings: [
+                BirthdaySpacings::new("BirthdaySpacings high 32 bits", 32),
+                BirthdaySpacings::new("BirthdaySpacings low 32 bits", 0),
+            ],
+            gaps: [
+                Gap::new("Gap high 8 bits", 56),
+                Gap::new("Gap low 8 bits", 0),
+            ],
+            bcfn: BCFN_LEVELS.into_iter().map(Bcfn::new).collect(),
+            dc6: Dc6::new(),
+        }
+    }
+
+    /// Returns the number of bytes written, including those of an incomplete word.
+    pub const fn bytes(&self) -> u64 {
+        self.words * 8 + self.partial_len as u64
+    }
+
+    fn push_word(&mut self, word: u64) {
+        self.words += 1;
+        self.fpf.push(word);
+        for test in &mut self.birthday_spacings {
+            test.push(word);
+        }
+        for test in &mut self.gaps {
+            test.push(word);
+        }
+        for test in &mut self.bcfn {
+            test.push(word);
+        }
+        self.dc6.push(word);
+    }
+
+    /// Returns the results of all tests over the complete words written so far, with `None` for
+    /// the tests that need more words.
+    pub fn results(&self) -> Vec<TestResult> {
+        let mut results = self.fpf.results().to_vec();
+        results.extend(self.birthday_spacings.iter().map(BirthdaySpacings::result));
+        results.extend(self.gaps.iter().map(Gap::result));
+        results.extend(self.bcfn.iter().map(Bcfn::result));
+        results.push(self.dc6.result());
+        results
+    }
+}
+
+impl io::Write for Battery {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut bytes = buf;
+        if self.partial_len > 0 {
+            let n = bytes.len().min(8 - self.partial_len);
+            self.partial[self.partial_len..self.partial_len + n].copy_from_slice(&bytes[..n]);
+            self.partial_len += n;
+            bytes = &bytes[n..];
+            if self.partial_len < 8 {
+                return Ok(buf.len());
+            }
+            self.partial_len = 0;
+            self.push_word(u64::from_le_bytes(self.partial));
+        }
+        let words = bytes.chunks_exact(8);
+        let rest = words.remainder();
+        for word in words {
+            self.push_word(u64::from_le_bytes(word.try_into().unwrap()));
+        }
+        self.partial[..rest.len()].copy_from_slice(rest);
+        self.partial_len = rest.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write as _;
+
+    use super::{Battery, Evaluation};
+    use crate::{generators::Algorithm, Builder};
+
+    /// Returns the battery of `n` words of `word`.
+    fn battery_of(n: u64, mut word: impl FnMut(u64) -> u64) -> Battery {
+        let mut battery = Battery::new();
+        for i in 0..n {
+            battery.write_all(&word(i).to_le_bytes()).unwrap();
+        }
+        battery
+    }
+
+    /// Returns the names of the failed tests.
+    fn failures(battery: &Battery) -> Vec<String> {
+        battery
+            .results()
+            .into_iter()
+            .filter(|r| r.p_value.and_then(Evaluation::of) == Some(Evaluation::Fail))
+            .map(|r| r.name)
+            .collect()
+    }
+
+    #[test]
+    fn evaluations() {
+        assert_eq!(Evaluation::of(0.5), None);
+        assert_eq!(Evaluation::of(0.002), None);
+        assert_eq!(Evaluation::of(5e-4), Some(Evaluation::Unusual));
+        assert_eq!(
+            Evaluation::of(1.0 - 5e-5),
+            Some(Evaluation::MildlySuspicious)
+        );
+        assert_eq!(Evaluation::of(1e-9), Some(Evaluation::VerySuspicious));
+        assert_eq!(Evaluation::of(0.0), Some(Evaluation::Fail));
+        assert_eq!(Evaluation::of(1.0).unwrap().to_string(), "FAIL");
+        assert_eq!(Evaluation::HighlySuspicious.to_string(), "VERY SUSPICIOUS");
+    }
+
+    #[test]
+    fn good_generators_pass() {
+        for algorithm in [Algorithm::Xoshiro256StarStar, Algorithm::ChaCha20] {
+            let mut battery = Battery::new();
+            let mut stream = Builder::new().algorithm(algorithm).seed(b"stress").build();
+            stream.write_to(&mut battery, Some(8 << 20)).unwrap();
+            assert_eq!(battery.bytes(), 8 << 20);
+            let results = battery.results();
+            assert_eq!(results.len(), 15);
+            for result in results {
+                if let Some(p) = result.p_value {
+                    // the seed is fixed, so this cannot fail by chance
+                    assert!(
+                        Evaluation::of(p) < Some(Evaluation::Suspicious),
+                        "{}: {}",
+                        algorithm,
+                        result
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn bad_generators_fail() {
+        let mut x = 1u64;
+        let counter = battery_of(1 << 20, |i| i.wrapping_mul(0x9e37_79b9_7f4a_7c15));
+        let lcg = battery_of(1 << 20, |_| {
+            x = x.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1);
+            x
+        });
+        let mut y = 1u64;
+        let biased = battery_of(1 << 20, |_| {
+            y ^= y << 13;
+            y ^= y >> 7;
+            y ^= y << 17;
+            y | y << 29
+        });
+        for (name, battery, expected) in [
+            ("counter", counter, "BirthdaySpacings high 32 bits"),
+            ("LCG", lcg, "Gap low 8 bits"),
+            ("biased", biased, "BCFN 2^6 bits"),
+        ] {
+            let failures = failures(&battery);
+            assert!(
+                failures.iter().any(|f| f == expected),
+                "{}: {:?}",
+                name,
+                failures
+            );
+        }
+    }
+
+    /// A generator that repeats a random table of 2^16 words fails once it repeats, unless it is
+    /// reseeded with a fresh table at every period like the default reseed interval of 512 KiB.
+    #[test]
+    fn reseeding_hides_long_range_defects() {
+        let mut stream = Builder::new().seed(b"period").build();
+        let mut table = vec![0; 1 << 16];
+        for reseeded in [false, true] {
+            stream.fill_words(&mut table).unwrap();
+            let battery = battery_of(1 << 20, |i| {
+                if reseeded && i > 0 && i % (1 << 16) == 0 {
+                    stream.fill_words(&mut table).unwrap();
+                }
+                table[i as usize % (1 << 16)]
+            });
+            let failures = failures(&battery);
+            assert_eq!(failures.is_empty(), reseeded, "{:?}", failures);
+        }
+    }
+
+    #[test]
+    fn partial_words() {
+        let mut bytes = vec![0; 1 << 16];
+        Builder::new()
+            .seed(b"partial")
+            .build()
+            .fill_bytes(&mut bytes)
+            .unwrap();
+        let mut whole = Battery::new();
+        whole.write_all(&bytes).unwrap();
+        let mut pieces = Battery::new();
+        for chunk in bytes.chunks(13) {
+            pieces.write_all(chunk).unwrap();
+        }
+        assert_eq!(pieces.bytes(), whole.bytes());
+        assert_eq!(pieces.results(), whole.results());
+
+        pieces.write_all(&[1, 2, 3]).unwrap();
+        assert_eq!(pieces.bytes(), (1 << 16) + 3);
+        assert_eq!(pieces.results(), whole.results());
+    }
+}
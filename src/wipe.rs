@@ -0,0 +1,516 @@
+//! Overwriting files and block devices in passes, each verified by reading the target back.
+
+use std::{
+    fmt,
+    fs::{self, File},
+    io::{self, Read as _, Write as _},
+    ops::Range,
+    path::Path,
+    str::FromStr,
+};
+
+use crate::{
+    generators::Algorithm,
+    output::{self, AlignedBuf, FileOptions},
+    Builder, RandomStream,
+};
+
+/// Size in bytes of the chunks generated and compared at a time.
+const CHUNK_LEN: usize = 1024 * 1024;
+
+/// Maximum number of mismatching ranges recorded in a [`PassReport`].
+pub const MAX_MISMATCHES: usize = 100;
+
+/// Data written by a pass.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Pass {
+    /// Bytes of a seeded stream, which is regenerated for the verification.
+    Random,
+    /// Zero bytes.
+    Zeros,
+    /// `0xff` bytes.
+    Ones,
+    /// The bytes repeated from the start of the target.
+    Pattern(Vec<u8>),
+}
+
+impl fmt::Display for Pass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Random => f.write_str("random"),
+            Self::Zeros => f.write_str("zeros"),
+            Self::Ones => f.write_str("ones"),
+            Self::Pattern(bytes) => {
+                f.write_str("pattern:")?;
+                bytes.iter().try_for_each(|b| write!(f, "{:02x}", b))
+            }
+        }
+    }
+}
+
+/// Error returned when parsing an invalid pass.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParsePassError(String);
+
+impl fmt::Display for ParsePassError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid pass '{}' (expected random, zeros, ones or pattern:HEX)",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for ParsePassError {}
+
+impl FromStr for Pass {
+    type Err = ParsePassError;
+
+    /// Parses `random`, `zeros`, `ones` or `pattern:HEX`, e.g. `pattern:55aa`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParsePassError(s.to_owned());
+        match s.to_ascii_lowercase().as_str() {
+            "random" => return Ok(Self::Random),
+            "zeros" => return Ok(Self::Zeros),
+            "ones" => return Ok(Self::Ones),
+            _ => {}
+        }
+        let hex = s.strip_prefix("pattern:").ok_or_else(err)?;
+        let hex = hex.strip_prefix("0x").unwrap_or(hex);
+        if hex.is_empty() || !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+            return Err(err());
+        }
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| err()))
+            .collect::<Result<_, _>>()
+            .map(Self::Pattern)
+    }
+}
+
+/// Result of a pass.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PassReport {
+    /// Index of the pass from zero.
+    pub index: usize,
+    /// Data written.
+    pub pass: Pass,
+    /// Number of bytes written.
+    pub bytes: u64,
+    /// Whether the target was read back.
+    pub verified: bool,
+    /// Number of bytes that were read back different from what was written.
+    pub mismatched_bytes: u64,
+    /// The first [`MAX_MISMATCHES`] ranges of offsets of mismatching bytes.
+    pub mismatches: Vec<Range<u64>>,
+}
+
+/// Wipe of a file or block device in passes.
+///
+/// Every pass overwrites the whole target, syncs it to the disk, and is verified by reading the
+/// target back, with `O_DIRECT` or after dropping it from the page cache, and comparing it to the
+/// regenerated data.
+#[derive(Clone, Debug)]
+pub struct Wipe {
+    passes: Vec<Pass>,
+    algorithm: Algorithm,
+    seed: Option<Vec<u8>>,
+    direct: bool,
+    verify: bool,
+}
+
+impl Wipe {
+    /// Creates a wipe that writes `passes` in order.
+    pub fn new(passes: Vec<Pass>) -> Self {
+        Self {
+            passes,
+            algorithm: Algorithm::ChaCha20,
+            seed: None,
+            direct: false,
+            verify: true,
+        }
+    }
+
+    /// Sets the generator algorithm of random passes [default: chacha20].
+    pub fn algorithm(mut self, algorithm: Algorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Derives the streams of random passes from `seed` instead of a random seed: pass `i` uses
+    /// the seed followed by `i` as a little-endian `u64`.
+    pub fn seed(mut self, seed: &[u8]) -> Self {
+        self.seed = Some(seed.to_owned());
+        self
+    }
+
+    /// Writes and reads with `O_DIRECT` (Linux only).
+    pub fn direct(mut self, direct: bool) -> Self {
+        self.direct = direct;
+        self
+    }
+
+    /// Reads the target back after every pass [default: true].
+    pub fn verify(mut self, verify: bool) -> Self {
+        self.verify = verify;
+        self
+    }
+
+    /// Wipes `path`, an existing regular file or block device, calling `on_pass` after every
+    /// pass.
+    pub fn run(
+        &self,
+        path: impl AsRef<Path>,
+        mut on_pass: impl FnMut(&PassReport),
+    ) -> io::Result<Vec<PassReport>> {
+        let path = path.as_ref();
+        let mut reports = Vec::with_capacity(self.passes.len());
+        for (index, pass) in self.passes.iter().enumerate() {
+            let seed = match &self.seed {
+                Some(seed) => [&seed[..], &(index as u64).to_le_bytes()].concat(),
+                None => {
+                    let mut seed = vec![0; 32];
+                    getrandom::getrandom(&mut seed)?;
+                    seed
+                }
+            };
+            let source = || Source::new(pass, self.algorithm, &seed);
+
+            let bytes = write_pass(path, source(), self.direct)?;
+            let mut report = PassReport {
+                index,
+                pass: pass.clone(),
+                bytes,
+                verified: false,
+                mismatched_bytes: 0,
+                mismatches: Vec::new(),
+            };
+            if self.verify {
+                verify_pass(path, source(), self.direct, &mut report)?;
+            }
+            on_pass(&report);
+            reports.push(report);
+        }
+        Ok(reports)
+    }
+}
+
+/// Generator of the data of a pass.
+enum Source {
+    Stream(Box<RandomStream>),
+    Pattern { bytes: Vec<u8>, pos: usize },
+}
+
+impl Source {
+    fn new(pass: &Pass, algorithm: Algorithm, seed: &[u8]) -> Self {
+        let pattern = |bytes: &[u8]| Self::Pattern {
+            bytes: bytes.to_owned(),
+            pos: 0,
+        };
+        match pass {
+            Pass::Random => Self::Stream(Box::new(
+                Builder::new().algorithm(algorithm).seed(seed).build(),
+            )),
+            Pass::Zeros => pattern(&[0]),
+            Pass::Ones => pattern(&[0xff]),
+            Pass::Pattern(bytes) => pattern(bytes),
+        }
+    }
+
+    fn fill(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        match self {
+            Self::Stream(stream) => stream.fill_bytes(buf),
+            Self::Pattern { bytes, pos } => {
+                for e in buf.iter_mut() {
+                    *e = bytes[*pos];
+                    *pos = (*pos + 1) % bytes.len();
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Returns the size of a regular file or block device.
+fn target_size(file: &File) -> io::Result<u64> {
+    let metadata = file.metadata()?;
+    if metadata.is_file() {
+        return Ok(metadata.len());
+    }
+    match output::block_geometry(file)? {
+        (_, Some(size)) => Ok(size),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "not a regular file or block device",
+        )),
+    }
+}
+
+/// Overwrites the whole target with `source` and syncs it, returning the number of bytes written.
+fn write_pass(path: &Path, mut source: Source, direct: bool) -> io::Result<u64> {
+    let mut out = FileOptions::new()
+        .in_place(true)
+        .direct(direct)
+        .fdatasync_at_end(true)
+        .open(path)?;
+    let size = target_size(&File::open(path)?)?;
+
+    let mut chunk = vec![0; CHUNK_LEN];
+    let mut left = size;
+    while left > 0 {
+        let n = left.min(CHUNK_LEN as u64) as usize;
+        source.fill(&mut chunk[..n])?;
+        out.write_all(&chunk[..n])?;
+        left -= n as u64;
+    }
+    out.finish()?;
+    Ok(size)
+}
+
+/// Reads the target back and compares it with `source`, recording mismatches in `report`.
+fn verify_pass(
+    path: &Path,
+    mut source: Source,
+    direct: bool,
+    report: &mut PassReport,
+) -> io::Result<()> {
+    let mut open = fs::OpenOptions::new();
+    open.read(true);
+    if direct {
+        output::set_direct(&mut open)?;
+    }
+    let mut file = open.open(path)?;
+    let (block_size, _) = output::block_geometry(&file)?;
+    if !direct {
+        drop_cache(&file)?;
+    }
+
+    let mut buf = AlignedBuf::new(CHUNK_LEN / 8, block_size);
+    let mut expected = vec![0; CHUNK_LEN];
+    let mut offset = 0;
+    while offset < report.bytes {
+        let len = (report.bytes - offset).min(CHUNK_LEN as u64) as usize;
+        // whole blocks, as reads bypassing the page cache must be
+        let actual = &mut buf.as_bytes_mut()[..len.next_multiple_of(block_size).min(CHUNK_LEN)];
+        let mut n = 0;
+        while n < len {
+            match file.read(&mut actual[n..]) {
+                Ok(0) => break,
+                Ok(k) => n += k,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+            // the next read would start in the middle of a block, which is fine only at the end
+            if direct && n < len && n % block_size != 0 {
+                if offset + (n as u64) < target_size(&file)? {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        format!("short read of a block at offset {}", offset + n as u64),
+                    ));
+                }
+                break;
+            }
+        }
+        source.fill(&mut expected[..len])?;
+
+        // bytes missing at the end of a shrunk file count as mismatches
+        let (actual, expected) = (&actual[..n.min(len)], &expected[..len]);
+        if actual != &expected[..actual.len()] || actual.len() < len {
+            for (i, &e) in expected.iter().enumerate() {
+                if actual.get(i) != Some(&e) {
+                    record_mismatch(report, offset + i as u64);
+                }
+            }
+        }
+        if n < len {
+            break;
+        }
+        offset += len as u64;
+    }
+    report.verified = true;
+    Ok(())
+}
+
+fn record_mismatch(report: &mut PassReport, offset: u64) {
+    report.mismatched_bytes += 1;
+    let ranges = &mut report.mismatches;
+    if ranges.last().is_some_and(|range| range.end == offset) {
+        ranges.last_mut().unwrap().end += 1;
+    } else if ranges.len() < MAX_MISMATCHES {
+        ranges.push(offset..offset + 1);
+    }
+}
+
+/// Drops the cached pages of `file` so that it is read back from the disk.
+fn drop_cache(file: &File) -> io::Result<()> {
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::fd::AsRawFd as _;
+
+        // SAFETY: posix_fadvise does not access memory
+        let ret = unsafe { libc::posix_fadvise(file.as_raw_fd(), 0, 0, libc::POSIX_FADV_DONTNEED) };
+        if ret != 0 {
+            return Err(io::Error::from_raw_os_error(ret));
+        }
+    }
+    #[cfg(not(target_os = "linux"))]
+    let _ = file;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        io::{Seek as _, Write as _},
+        path::PathBuf,
+        process::Command,
+    };
+
+    use super::{verify_pass, write_pass, Pass, PassReport, Source, Wipe};
+    use crate::generators::Algorithm;
+
+    /// Creates a file of `len` bytes in the target directory, where O_DIRECT is more likely to
+    /// be supported than in a tmpfs.
+    fn target_file(name: &str, len: usize) -> PathBuf {
+        let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("target")
+            .join(format!("gen-random-wipe-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        fs::write(&path, vec![0x5a; len]).unwrap();
+        path
+    }
+
+    #[test]
+    fn parse_passes() {
+        for (s, pass) in [
+            ("random", Pass::Random),
+            ("zeros", Pass::Zeros),
+            ("ONES", Pass::Ones),
+            ("pattern:55aa", Pass::Pattern(vec![0x55, 0xaa])),
+            ("pattern:0x00FF01", Pass::Pattern(vec![0x00, 0xff, 0x01])),
+        ] {
+            assert_eq!(s.parse(), Ok(pass.clone()));
+            assert_eq!(pass.to_string().parse(), Ok(pass));
+        }
+        for s in [
+            "",
+            "zero",
+            "pattern:",
+            "pattern:5",
+            "pattern:xy",
+            "pattern:é0",
+        ] {
+            assert!(s.parse::<Pass>().is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn wipes_file_in_passes() {
+        let len = (3 << 20) + 12345;
+        let path = target_file("passes", len);
+        let passes = vec![
+            Pass::Random,
+            Pass::Zeros,
+            Pass::Ones,
+            Pass::Pattern(vec![1, 2, 3]),
+        ];
+        let mut seen = 0;
+        let reports = Wipe::new(passes.clone())
+            .seed(b"wipe")
+            .run(&path, |report| {
+                assert_eq!(report.index, seen);
+                seen += 1;
+            })
+            .unwrap();
+        assert_eq!(seen, 4);
+        for (report, pass) in reports.iter().zip(passes) {
+            assert_eq!(report.pass, pass);
+            assert_eq!(report.bytes, len as u64);
+            assert!(report.verified);
+            assert_eq!(report.mismatched_bytes, 0, "{:?}", report.mismatches);
+        }
+        let data = fs::read(&path).unwrap();
+        assert_eq!(data.len(), len);
+        assert!(data.iter().enumerate().all(|(i, &b)| b == [1, 2, 3][i % 3]));
+
+        // the random pass is reproducible from the seed
+        let reports = Wipe::new(vec![Pass::Random])
+            .seed(b"wipe")
+            .direct(cfg!(target_os = "linux"))
+            .run(&path, |_| {})
+            .unwrap();
+        assert_eq!(reports[0].mismatched_bytes, 0);
+        let mut expected = vec![0; len];
+        Source::new(&Pass::Random, Algorithm::ChaCha20, b"wipe\0\0\0\0\0\0\0\0")
+            .fill(&mut expected)
+            .unwrap();
+        assert!(fs::read(&path).unwrap() == expected);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn reports_mismatching_offsets() {
+        let len = 2 << 20;
+        let path = target_file("mismatch", len);
+        let source = || Source::new(&Pass::Random, Algorithm::Xorshift64Star, b"mismatch");
+        let bytes = write_pass(&path, source(), false).unwrap();
+
+        // corrupt two ranges, the second of which crosses a chunk boundary
+        let mut file = fs::OpenOptions::new().write(true).open(&path).unwrap();
+        for (offset, len) in [(100, 3), ((1 << 20) - 2, 5)] {
+            let mut data = fs::read(&path).unwrap()[offset..offset + len].to_vec();
+            data.iter_mut().for_each(|b| *b = !*b);
+            file.seek(std::io::SeekFrom::Start(offset as u64)).unwrap();
+            file.write_all(&data).unwrap();
+        }
+        drop(file);
+
+        let mut report = PassReport {
+            index: 0,
+            pass: Pass::Random,
+            bytes,
+            verified: false,
+            mismatched_bytes: 0,
+            mismatches: Vec::new(),
+        };
+        verify_pass(&path, source(), false, &mut report).unwrap();
+        assert!(report.verified);
+        assert_eq!(report.mismatched_bytes, 8);
+        assert_eq!(report.mismatches, [100..103, (1 << 20) - 2..(1 << 20) + 3]);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    /// Needs root and losetup(8).
+    #[test]
+    #[ignore]
+    fn wipes_loop_device() {
+        let image = target_file("image", 16 << 20);
+        let output = Command::new("losetup")
+            .args(["--find", "--show"])
+            .arg(&image)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        let device = String::from_utf8(output.stdout).unwrap().trim().to_owned();
+
+        let reports = Wipe::new(vec![Pass::Random, Pass::Pattern(vec![0xa5, 0x5a])])
+            .seed(b"loop")
+            .direct(true)
+            .run(&device, |_| {});
+        Command::new("losetup")
+            .args(["-d", &device])
+            .status()
+            .unwrap();
+
+        for report in reports.unwrap() {
+            assert_eq!(report.bytes, 16 << 20);
+            assert_eq!(report.mismatched_bytes, 0, "{:?}", report.mismatches);
+        }
+        let data = fs::read(&image).unwrap();
+        assert!(data.chunks(2).all(|pair| pair == [0xa5, 0x5a]));
+        fs::remove_dir_all(image.parent().unwrap()).unwrap();
+    }
+}